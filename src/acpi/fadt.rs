@@ -0,0 +1,199 @@
+use super::sdt::{Header, Table, Signature, GenericAddress, AddressSpace};
+use crate::memory::PhysicalAddress;
+
+use bitflags::bitflags;
+
+// The Fixed ACPI Description Table, which describes fixed hardware like the PM timer and RTC.
+//
+// The table has grown with each ACPI revision. Fields past the ACPI 1.0 layout are only read after
+// checking the table is long enough to have them.
+#[repr(C, packed)]
+pub struct FADT {
+    header:                      Header,
+    firmware_control:            u32,
+    dsdt:                        u32,
+    _1:                          u8,
+    preferred_pm_profile:        u8,
+    sci_interrupt:               u16,
+    smi_command_port:            u32,
+    acpi_enable:                 u8,
+    acpi_disable:                u8,
+    s4bios_request:              u8,
+    pstate_control:              u8,
+    pm1a_event_block:            u32,
+    pm1b_event_block:            u32,
+    pm1a_control_block:          u32,
+    pm1b_control_block:          u32,
+    pm2_control_block:           u32,
+    pm_timer_block:              u32,
+    gpe0_block:                  u32,
+    gpe1_block:                  u32,
+    pm1_event_length:            u8,
+    pm1_control_length:          u8,
+    pm2_control_length:          u8,
+    pm_timer_length:             u8,
+    gpe0_block_length:           u8,
+    gpe1_block_length:           u8,
+    gpe1_base:                   u8,
+    cstate_control:              u8,
+    worst_c2_latency:            u16,
+    worst_c3_latency:            u16,
+    flush_size:                  u16,
+    flush_stride:                u16,
+    duty_offset:                 u8,
+    duty_width:                  u8,
+    day_alarm:                   u8,
+    month_alarm:                 u8,
+    century:                     u8,
+    boot_architecture_flags:     u16,
+    _2:                          u8,
+    flags:                       u32,
+
+    // ACPI 2.0 and later
+    reset_register:              GenericAddress,
+    reset_value:                 u8,
+    arm_boot_architecture_flags: u16,
+    minor_version:               u8,
+    extended_firmware_control:   u64,
+    extended_dsdt:               u64,
+    extended_pm1a_event_block:   GenericAddress,
+    extended_pm1b_event_block:   GenericAddress,
+    extended_pm1a_control_block: GenericAddress,
+    extended_pm1b_control_block: GenericAddress,
+    extended_pm2_control_block:  GenericAddress,
+    extended_pm_timer_block:     GenericAddress,
+    extended_gpe0_block:         GenericAddress,
+    extended_gpe1_block:         GenericAddress
+}
+
+// The length of the ACPI 1.0 table, up to and including the flags.
+const VERSION_1_LENGTH: usize = 116;
+
+unsafe impl Table for FADT {
+    const SIGNATURE: &'static Signature = b"FACP";
+    const MINIMUM_LENGTH: usize = VERSION_1_LENGTH;
+}
+
+impl FADT {
+    pub fn sci_interrupt(&self) -> u16 {
+        self.sci_interrupt
+    }
+
+    pub fn dsdt(&self) -> PhysicalAddress {
+        if self.is_extended() && self.extended_dsdt != 0 {
+            PhysicalAddress::new(self.extended_dsdt)
+        } else {
+            PhysicalAddress::new(self.dsdt as u64)
+        }
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(self.flags)
+    }
+
+    pub fn boot_architecture(&self) -> BootArchitecture {
+        // The field was reserved before ACPI 2.0.
+        if self.header.revision() >= 2 {
+            BootArchitecture::from_bits_truncate(self.boot_architecture_flags)
+        } else {
+            BootArchitecture::LEGACY_DEVICES | BootArchitecture::I8042
+        }
+    }
+
+    // Returns the location of the power management timer's counter register, if there is one.
+    pub fn pm_timer(&self) -> Option<GenericAddress> {
+        if self.is_extended() && !self.extended_pm_timer_block.is_null() {
+            Some(self.extended_pm_timer_block)
+        } else if self.pm_timer_block != 0 && self.pm_timer_length == 4 {
+            Some(GenericAddress::new(AddressSpace::SystemIO, 32, self.pm_timer_block as u64))
+        } else {
+            None
+        }
+    }
+
+    // Returns the index of the RTC's century register in CMOS RAM, if it has one.
+    pub fn century_register(&self) -> Option<u8> {
+        if self.century != 0 { Some(self.century) } else { None }
+    }
+
+    // Returns the register to write to reset the machine, and the value to write.
+    pub fn reset_register(&self) -> Option<(GenericAddress, u8)> {
+        if self.is_extended() && self.flags().contains(Flags::RESET_REGISTER_SUPPORTED) {
+            Some((self.reset_register, self.reset_value))
+        } else {
+            None
+        }
+    }
+
+    fn is_extended(&self) -> bool {
+        self.header.length() >= core::mem::size_of::<FADT>()
+    }
+}
+
+bitflags! {
+    pub struct Flags: u32 {
+        const WBINVD                   = 1;
+        const WBINVD_FLUSH             = 1 << 1;
+        const PROCESSOR_C1             = 1 << 2;
+        const P_LVL2_UP                = 1 << 3;
+        const POWER_BUTTON             = 1 << 4;
+        const SLEEP_BUTTON             = 1 << 5;
+        const FIXED_RTC                = 1 << 6;
+        const RTC_S4                   = 1 << 7;
+
+        // The PM timer counter is 32 bits wide rather than 24.
+        const EXTENDED_TIMER_VALUE     = 1 << 8;
+
+        const DOCKING_CAPABLE          = 1 << 9;
+        const RESET_REGISTER_SUPPORTED = 1 << 10;
+        const SEALED_CASE              = 1 << 11;
+        const HEADLESS                 = 1 << 12;
+        const CPU_SW_SLEEP             = 1 << 13;
+        const PCI_EXPRESS_WAKE         = 1 << 14;
+        const USE_PLATFORM_CLOCK       = 1 << 15;
+        const S4_RTC_STATUS_VALID      = 1 << 16;
+        const REMOTE_POWER_ON_CAPABLE  = 1 << 17;
+        const FORCE_APIC_CLUSTER_MODEL = 1 << 18;
+        const FORCE_APIC_PHYSICAL_MODE = 1 << 19;
+        const HARDWARE_REDUCED_ACPI    = 1 << 20;
+        const LOW_POWER_S0_IDLE        = 1 << 21;
+    }
+}
+
+bitflags! {
+    pub struct BootArchitecture: u16 {
+        const LEGACY_DEVICES       = 1;
+        const I8042                = 1 << 1;
+        const VGA_NOT_PRESENT      = 1 << 2;
+        const MSI_NOT_SUPPORTED    = 1 << 3;
+        const PCIE_ASPM_CONTROLS   = 1 << 4;
+        const CMOS_RTC_NOT_PRESENT = 1 << 5;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::sdt::tests::table;
+
+    #[test]
+    fn reading_a_version_1_fadt() {
+        let mut body = [0u8; VERSION_1_LENGTH - 36];
+
+        // The PM timer is at port 0x608, and the century is in CMOS register 0x32.
+        body[76 - 36..80 - 36].copy_from_slice(&0x608u32.to_le_bytes());
+        body[91 - 36] = 4;
+        body[108 - 36] = 0x32;
+        body[112 - 36] = 1 << 6;
+
+        let fadt: &FADT = table(b"FACP", &body).cast().unwrap();
+
+        let timer = fadt.pm_timer().unwrap();
+        assert_eq!(AddressSpace::SystemIO, timer.address_space());
+        assert_eq!(0x608, timer.address());
+
+        assert_eq!(Some(0x32), fadt.century_register());
+        assert_eq!(Flags::FIXED_RTC, fadt.flags());
+        assert_eq!(None, fadt.reset_register().map(|(_, value)| value));
+    }
+}
@@ -0,0 +1,49 @@
+use super::sdt::{Header, Table, Signature, GenericAddress};
+use crate::memory::PhysicalAddress;
+
+// Describes the High Precision Event Timer block.
+#[repr(C, packed)]
+pub struct HPET {
+    header:          Header,
+    event_timer_id:  u32,
+    base_address:    GenericAddress,
+    number:          u8,
+    minimum_tick:    u16,
+    page_protection: u8
+}
+
+unsafe impl Table for HPET {
+    const SIGNATURE: &'static Signature = b"HPET";
+}
+
+impl HPET {
+    // Returns the physical address of the timer block's registers.
+    pub fn base_address(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.base_address.address())
+    }
+
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    // Returns the smallest number of ticks periodic interrupts can be set to without losing any.
+    pub fn minimum_tick(&self) -> u16 {
+        self.minimum_tick
+    }
+
+    pub fn comparators(&self) -> usize {
+        ((self.event_timer_id >> 8) & 0x1F) as usize + 1
+    }
+
+    pub fn has_64_bit_counter(&self) -> bool {
+        self.event_timer_id & (1 << 13) != 0
+    }
+
+    pub fn is_legacy_replacement_capable(&self) -> bool {
+        self.event_timer_id & (1 << 15) != 0
+    }
+
+    pub fn vendor_id(&self) -> u16 {
+        (self.event_timer_id >> 16) as u16
+    }
+}
@@ -0,0 +1,240 @@
+use super::sdt::{Header, Table, Signature, read_u16, read_u32, read_u64};
+use crate::memory::PhysicalAddress;
+
+use bitflags::bitflags;
+
+// The Multiple APIC Description Table, which lists the interrupt controllers.
+#[repr(C, packed)]
+pub struct MADT {
+    header:             Header,
+    local_apic_address: u32,
+    flags:              u32
+}
+
+unsafe impl Table for MADT {
+    const SIGNATURE: &'static Signature = b"APIC";
+}
+
+impl MADT {
+    // Returns the physical address of the local APICs, which an entry can override with a 64-bit one.
+    pub fn local_apic_address(&self) -> PhysicalAddress {
+        self.entries()
+            .find_map(|entry| match entry {
+                Entry::LocalAPICAddressOverride { address } => Some(address),
+                _ => None
+            })
+            .unwrap_or_else(|| PhysicalAddress::new(self.local_apic_address as u64))
+    }
+
+    // Whether the system also has a pair of legacy 8259 PICs, which need to be masked.
+    pub fn has_legacy_pics(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    pub fn entries(&self) -> Entries<'_> {
+        Entries { bytes: self.body() }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    LocalAPIC { processor_id: u8, apic_id: u8, flags: LocalAPICFlags },
+    IOAPIC { id: u8, address: PhysicalAddress, interrupt_base: u32 },
+    InterruptSourceOverride { bus: u8, source: u8, interrupt: u32, flags: InterruptFlags },
+    NMISource { flags: InterruptFlags, interrupt: u32 },
+    LocalAPICNMI { processor_id: u8, flags: InterruptFlags, lint: u8 },
+    LocalAPICAddressOverride { address: PhysicalAddress },
+    LocalX2APIC { x2apic_id: u32, flags: LocalAPICFlags, processor_uid: u32 },
+    Unknown { kind: u8, length: u8 }
+}
+
+bitflags! {
+    pub struct LocalAPICFlags: u32 {
+        const ENABLED        = 1;
+        const ONLINE_CAPABLE = 1 << 1;
+    }
+}
+
+impl LocalAPICFlags {
+    // Whether the processor can be brought up, either now or later.
+    pub fn is_usable(&self) -> bool {
+        self.intersects(LocalAPICFlags::ENABLED | LocalAPICFlags::ONLINE_CAPABLE)
+    }
+}
+
+// The MPS INTI flags describing how an interrupt is signalled. Either half can defer to the
+// conventions of the bus the interrupt comes from, in which case we return None.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptFlags(pub u16);
+
+impl InterruptFlags {
+    pub fn polarity(&self) -> Option<Polarity> {
+        match self.0 & 0b11 {
+            0b01 => Some(Polarity::ActiveHigh),
+            0b11 => Some(Polarity::ActiveLow),
+            _ => None
+        }
+    }
+
+    pub fn trigger_mode(&self) -> Option<TriggerMode> {
+        match (self.0 >> 2) & 0b11 {
+            0b01 => Some(TriggerMode::Edge),
+            0b11 => Some(TriggerMode::Level),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    ActiveHigh,
+    ActiveLow
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+    Edge,
+    Level
+}
+
+pub struct Entries<'a> {
+    bytes: &'a [u8]
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        if self.bytes.len() < 2 {
+            return None
+        }
+
+        let kind = self.bytes[0];
+        let length = self.bytes[1] as usize;
+
+        // Stop at a malformed entry rather than looping forever or reading past the table.
+        if length < 2 || length > self.bytes.len() {
+            self.bytes = &[];
+            return None
+        }
+
+        let entry = &self.bytes[..length];
+        self.bytes = &self.bytes[length..];
+
+        Some(Entry::parse(kind, entry))
+    }
+}
+
+impl Entry {
+    fn parse(kind: u8, entry: &[u8]) -> Entry {
+        let length = entry.len();
+
+        match (kind, length) {
+            (0, 8) => Entry::LocalAPIC {
+                processor_id: entry[2],
+                apic_id:      entry[3],
+                flags:        LocalAPICFlags::from_bits_truncate(read_u32(entry, 4))
+            },
+
+            (1, 12) => Entry::IOAPIC {
+                id:             entry[2],
+                address:        PhysicalAddress::new(read_u32(entry, 4) as u64),
+                interrupt_base: read_u32(entry, 8)
+            },
+
+            (2, 10) => Entry::InterruptSourceOverride {
+                bus:       entry[2],
+                source:    entry[3],
+                interrupt: read_u32(entry, 4),
+                flags:     InterruptFlags(read_u16(entry, 8))
+            },
+
+            (3, 8) => Entry::NMISource {
+                flags:     InterruptFlags(read_u16(entry, 2)),
+                interrupt: read_u32(entry, 4)
+            },
+
+            (4, 6) => Entry::LocalAPICNMI {
+                processor_id: entry[2],
+                flags:        InterruptFlags(read_u16(entry, 3)),
+                lint:         entry[5]
+            },
+
+            (5, 12) => Entry::LocalAPICAddressOverride {
+                address: PhysicalAddress::new(read_u64(entry, 4))
+            },
+
+            (9, 16) => Entry::LocalX2APIC {
+                x2apic_id:     read_u32(entry, 4),
+                flags:         LocalAPICFlags::from_bits_truncate(read_u32(entry, 8)),
+                processor_uid: read_u32(entry, 12)
+            },
+
+            _ => Entry::Unknown { kind, length: length as u8 }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::sdt::tests::table;
+
+    fn madt() -> &'static MADT {
+        let body = [
+            // Local APIC address and flags
+            0x00, 0x00, 0xE0, 0xFE, 0x01, 0x00, 0x00, 0x00,
+
+            // Local APIC: processor 0, APIC 0, enabled
+            0, 8, 0, 0, 1, 0, 0, 0,
+
+            // I/O APIC: ID 2 at 0xFEC00000, handling interrupts 0 and up
+            1, 12, 2, 0, 0x00, 0x00, 0xC0, 0xFE, 0, 0, 0, 0,
+
+            // Interrupt source override: ISA IRQ 0 is interrupt 2
+            2, 10, 0, 0, 2, 0, 0, 0, 0, 0,
+
+            // Interrupt source override: ISA IRQ 9 is level-triggered and active high
+            2, 10, 0, 9, 9, 0, 0, 0, 0b1101, 0,
+
+            // Something we don't know about
+            0x7F, 4, 0, 0
+        ];
+
+        table(b"APIC", &body).cast().unwrap()
+    }
+
+    #[test]
+    fn parsing_madt_entries() {
+        let entries = madt().entries();
+
+        assert!(entries.eq([
+            Entry::LocalAPIC { processor_id: 0, apic_id: 0, flags: LocalAPICFlags::ENABLED },
+            Entry::IOAPIC { id: 2, address: PhysicalAddress::new(0xFEC00000), interrupt_base: 0 },
+            Entry::InterruptSourceOverride { bus: 0, source: 0, interrupt: 2, flags: InterruptFlags(0) },
+            Entry::InterruptSourceOverride { bus: 0, source: 9, interrupt: 9, flags: InterruptFlags(0b1101) },
+            Entry::Unknown { kind: 0x7F, length: 4 }
+        ].iter().copied()));
+    }
+
+    #[test]
+    fn getting_the_local_apic_address() {
+        let madt = madt();
+
+        assert_eq!(PhysicalAddress::new(0xFEE00000), madt.local_apic_address());
+        assert!(madt.has_legacy_pics());
+    }
+
+    #[test]
+    fn reading_interrupt_flags() {
+        assert_eq!(None, InterruptFlags(0).polarity());
+        assert_eq!(None, InterruptFlags(0).trigger_mode());
+
+        assert_eq!(Some(Polarity::ActiveHigh), InterruptFlags(0b1101).polarity());
+        assert_eq!(Some(TriggerMode::Level), InterruptFlags(0b1101).trigger_mode());
+
+        assert_eq!(Some(Polarity::ActiveLow), InterruptFlags(0b0111).polarity());
+        assert_eq!(Some(TriggerMode::Edge), InterruptFlags(0b0111).trigger_mode());
+    }
+}
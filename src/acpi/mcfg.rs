@@ -0,0 +1,68 @@
+use super::sdt::{Header, Table, Signature, read_u16, read_u64};
+use crate::memory::PhysicalAddress;
+
+use core::ops::RangeInclusive;
+
+// Lists the memory-mapped PCI Express configuration space regions.
+#[repr(C, packed)]
+pub struct MCFG {
+    header: Header,
+    _1:     u64
+}
+
+unsafe impl Table for MCFG {
+    const SIGNATURE: &'static Signature = b"MCFG";
+}
+
+impl MCFG {
+    pub fn regions(&self) -> impl Iterator<Item = Region> + '_ {
+        self.body().chunks_exact(16).map(|entry| {
+            Region {
+                base:    PhysicalAddress::new(read_u64(entry, 0)),
+                segment: read_u16(entry, 8),
+                buses:   entry[10]..=entry[11]
+            }
+        })
+    }
+}
+
+// The configuration space for a range of buses in one PCI segment group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub base:    PhysicalAddress,
+    pub segment: u16,
+    pub buses:   RangeInclusive<u8>
+}
+
+impl Region {
+    // Returns the address of the 4 KiB configuration space for the given function.
+    pub fn address(&self, bus: u8, device: u8, function: u8) -> Option<PhysicalAddress> {
+        if self.buses.contains(&bus) && device < 32 && function < 8 {
+            let offset = ((bus - self.buses.start()) as usize) << 20 | (device as usize) << 15 | (function as usize) << 12;
+            Some(self.base + offset)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::sdt::tests::table;
+
+    #[test]
+    fn finding_pci_configuration_space() {
+        let mut body = [0u8; 24];
+        body[8..16].copy_from_slice(&0xB000_0000u64.to_le_bytes());
+        body[19] = 0xFF;
+
+        let mcfg: &MCFG = table(b"MCFG", &body).cast().unwrap();
+        let region = mcfg.regions().next().unwrap();
+
+        assert_eq!(0, region.segment);
+        assert_eq!(0..=0xFF, region.buses);
+        assert_eq!(Some(PhysicalAddress::new(0xB010_A000)), region.address(1, 1, 2));
+        assert_eq!(None, region.address(1, 32, 0));
+    }
+}
@@ -3,16 +3,52 @@
 mod rsdp;
 use rsdp::RSDP;
 
+mod sdt;
+pub use sdt::{Table, GenericAddress, AddressSpace};
+
+mod root;
+use root::Root;
+
+pub mod madt;
+pub use madt::MADT;
+
+pub mod fadt;
+pub use fadt::FADT;
+
+pub mod hpet;
+pub use hpet::HPET;
+
+pub mod mcfg;
+pub use mcfg::MCFG;
+
+use log::{info, warn};
 use spin::Mutex;
 
 static RSDP: Mutex<Option<RSDP>> = Mutex::new(None);
+static ROOT: Mutex<Option<Root>> = Mutex::new(None);
 
 pub fn initialize() {
     if let Some(rsdp) = RSDP::find() {
         RSDP.lock().replace(rsdp);
+
+        match Root::new(&rsdp) {
+            Ok(root) => {
+                info!("ACPI tables: {}", root);
+                ROOT.lock().replace(root);
+            }
+
+            Err(error) => warn!("Ignoring ACPI: {}", error)
+        }
     }
 }
 
+// Finds the given table, if the firmware provided a valid one.
+//
+// Tables are read through the direct physical map, which always covers the first 4 GiB.
+pub fn find<T: Table>() -> Option<&'static T> {
+    ROOT.lock().as_ref().and_then(|root| root.find())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -21,4 +57,17 @@ mod tests {
     fn finding_the_rsdp_on_boot() {
         assert!(RSDP.lock().is_some())
     }
+
+    #[test]
+    fn finding_tables_on_boot() {
+        assert!(find::<MADT>().is_some());
+        assert!(find::<FADT>().is_some());
+        assert!(find::<HPET>().is_some());
+    }
+
+    #[test]
+    fn finding_the_io_apic_in_the_madt() {
+        let madt = find::<MADT>().unwrap();
+        assert!(madt.entries().any(|entry| matches!(entry, madt::Entry::IOAPIC { .. })));
+    }
 }
@@ -0,0 +1,74 @@
+use super::sdt::{Header, Table, TableError, SignatureDisplay, read_u32, read_u64};
+use super::rsdp::RSDP;
+use crate::memory::PhysicalAddress;
+
+use core::mem::size_of;
+
+// The RSDT or XSDT, which lists the physical addresses of every other table. The two only differ
+// in the width of their entries.
+#[derive(Copy, Clone, Debug)]
+pub struct Root {
+    header:   &'static Header,
+    extended: bool
+}
+
+impl Root {
+    pub fn new(rsdp: &RSDP) -> Result<Root, TableError> {
+        let extended = rsdp.revision() > 0;
+        let address = PhysicalAddress::new(rsdp.address().expect("root table is out of reach") as u64);
+
+        // This is safe because the RSDP checked out, so it points to a root table.
+        let header = unsafe { Header::at(address)? };
+
+        let expected = if extended { b"XSDT" } else { b"RSDT" };
+
+        if header.signature() == *expected {
+            Ok(Root { header, extended })
+        } else {
+            Err(TableError::UnexpectedSignature { expected: *expected, found: header.signature() })
+        }
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = PhysicalAddress> + 'static {
+        let entries = &self.header.bytes()[size_of::<Header>()..];
+        let extended = self.extended;
+        let width = if extended { 8 } else { 4 };
+
+        entries.chunks_exact(width).map(move |entry| {
+            if extended {
+                PhysicalAddress::new(read_u64(entry, 0))
+            } else {
+                PhysicalAddress::new(read_u32(entry, 0) as u64)
+            }
+        })
+    }
+
+    // Returns the valid tables the root table points to. Invalid ones are skipped.
+    pub fn tables(&self) -> impl Iterator<Item = &'static Header> + 'static {
+        // This is safe because the firmware put tables at these addresses.
+        self.entries().filter_map(|address| unsafe { Header::at(address) }.ok())
+    }
+
+    pub fn find<T: Table>(&self) -> Option<&'static T> {
+        self.tables().find(|header| header.signature() == *T::SIGNATURE).and_then(|header| header.cast().ok())
+    }
+}
+
+// Lists the signatures of the tables, as in `APIC FACP HPET`.
+impl core::fmt::Display for Root {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (index, header) in self.tables().enumerate() {
+            if index > 0 {
+                formatter.write_str(" ")?;
+            }
+
+            write!(formatter, "{}", SignatureDisplay(header.signature()))?;
+        }
+
+        Ok(())
+    }
+}
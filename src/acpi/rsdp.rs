@@ -1,8 +1,14 @@
 use core::ops::Range;
 use core::convert::TryInto;
+use core::mem::size_of;
+use crate::memory::PhysicalAddress;
+use crate::boot;
 
 const SIGNATURE: &[u8; 8] = b"RSD PTR ";
 
+// The length of the ACPI 1.0 RSDP, which its checksum covers.
+const VERSION_1_LENGTH: usize = 20;
+
 #[repr(C, packed)]
 #[derive(Copy, Clone, Debug)]
 pub struct RSDP {
@@ -20,9 +26,40 @@ pub struct RSDP {
 }
 
 impl RSDP {
+    // Prefers the bootloader's copy of the RSDP. Failing that, searches the first kilobyte of the
+    // EBDA and then the BIOS ROM, as the ACPI specification lays out.
     pub fn find() -> Option<RSDP> {
-        // Search the BIOS ROM.
-        RSDP::find_in(0xE0000..0xFFFFF)
+        boot::get().and_then(|info| info.rsdp()).and_then(RSDP::from_bytes)
+            .or_else(|| RSDP::ebda().and_then(|ebda| RSDP::find_in(ebda..ebda + 1024)))
+            .or_else(|| RSDP::find_in(0xE0000..0xFFFFF))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<RSDP> {
+        if bytes.len() < VERSION_1_LENGTH || bytes[0..8] != *SIGNATURE {
+            return None
+        }
+
+        let mut rsdp = RSDP::default();
+        let length = bytes.len().min(size_of::<RSDP>());
+
+        // This is safe because RSDP is plain old data, and we copy no more than its size.
+        unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), &mut rsdp as *mut RSDP as *mut u8, length) }
+
+        Some(rsdp).filter(|rsdp| rsdp.validate())
+    }
+
+    // Returns the address of the Extended BIOS Data Area, which the BIOS data area records as a
+    // real-mode segment.
+    fn ebda() -> Option<usize> {
+        // This is safe because the first megabyte is reserved and always mapped.
+        let segment = unsafe { *PhysicalAddress::new(0x40E).to_virtual().as_ptr::<u16>() };
+        let address = (segment as usize) << 4;
+
+        if (0x80000..0xA0000).contains(&address) {
+            Some(address)
+        } else {
+            None
+        }
     }
 
     fn find_in(area: Range<usize>) -> Option<RSDP> {
@@ -32,16 +69,20 @@ impl RSDP {
 
     unsafe fn scan(area: Range<usize>) -> Option<RSDP> {
         area.step_by(16).find_map(|address| {
-            let signature = address as *const [u8; 8];
+            let address = PhysicalAddress::new(address as u64).to_virtual();
 
-            if *signature == *SIGNATURE {
-                Some(*(address as *const RSDP))
+            if *address.as_ptr::<[u8; 8]>() == *SIGNATURE {
+                Some(*address.as_ptr::<RSDP>())
             } else {
                 None
             }
         })
     }
 
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
     pub fn address(&self) -> Option<usize> {
         if self.revision == 0 {
             self.rsdt_address.try_into()
@@ -50,27 +91,40 @@ impl RSDP {
         }.ok()
     }
 
+    // The first 20 bytes always have to add up to zero. From ACPI 2.0 on, the extended fields are
+    // covered by a second checksum over the whole structure.
     fn validate(&self) -> bool {
-        self.checksum() == 0
-    }
+        let bytes = self.as_bytes();
 
-    fn checksum(&self) -> u8 {
-        self.as_bytes().iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+        checksum(&bytes[..VERSION_1_LENGTH]) == 0 &&
+            (self.revision < 2 || (self.length as usize == size_of::<RSDP>() && checksum(bytes) == 0))
     }
 
     fn as_bytes(&self) -> &[u8] {
-        unsafe { core::slice::from_raw_parts(self as *const _ as *const u8, self.length()) }
+        unsafe { core::slice::from_raw_parts(self as *const _ as *const u8, size_of::<RSDP>()) }
     }
+}
 
-    fn length(&self) -> usize {
-        if self.revision == 0 {
-            20
-        } else {
-            self.length as usize
+impl Default for RSDP {
+    fn default() -> RSDP {
+        RSDP {
+            signature:    [0; 8],
+            _1:           0,
+            oem_id:       [0; 6],
+            revision:     0,
+            rsdt_address: 0,
+            length:       0,
+            xsdt_address: 0,
+            _2:           0,
+            _3:           [0; 3]
         }
     }
 }
 
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,4 +185,50 @@ mod tests {
 
         assert_eq!(Some(0x7FE14D2), rsdp.address());
     }
+
+    fn v2_rsdp() -> RSDP {
+        let mut rsdp =
+            RSDP {
+                signature:    *b"RSD PTR ",
+                _1:           0,
+                oem_id:       *b"BOCHS ",
+                revision:     2,
+                rsdt_address: 0x7FE14D2,
+
+                length:       36,
+                xsdt_address: 0x7FE1500,
+                _2:           0,
+                _3:           [0; 3]
+            };
+
+        rsdp._1 = 0u8.wrapping_sub(checksum(&rsdp.as_bytes()[..VERSION_1_LENGTH]));
+        rsdp._2 = 0u8.wrapping_sub(checksum(rsdp.as_bytes()));
+        rsdp
+    }
+
+    #[test]
+    fn validating_a_v2_rsdp_when_valid() {
+        let rsdp = v2_rsdp();
+
+        assert!(rsdp.validate());
+        assert_eq!(Some(0x7FE1500), rsdp.address());
+    }
+
+    #[test]
+    fn validating_a_v2_rsdp_when_the_extended_checksum_is_invalid() {
+        let mut rsdp = v2_rsdp();
+        rsdp.xsdt_address = 0x7FE1600;
+
+        assert!(!rsdp.validate());
+    }
+
+    #[test]
+    fn reading_an_rsdp_from_bytes() {
+        let rsdp = v2_rsdp();
+        let bytes = rsdp.as_bytes();
+
+        assert_eq!(Some(0x7FE1500), RSDP::from_bytes(bytes).and_then(|rsdp| rsdp.address()));
+        assert!(RSDP::from_bytes(&bytes[..VERSION_1_LENGTH]).is_none());
+        assert!(RSDP::from_bytes(&bytes[..8]).is_none());
+    }
 }
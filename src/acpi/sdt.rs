@@ -0,0 +1,251 @@
+use crate::memory::PhysicalAddress;
+
+use core::convert::TryInto;
+use core::mem::size_of;
+
+pub type Signature = [u8; 4];
+
+// The header at the start of every ACPI system description table.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct Header {
+    signature:        Signature,
+    length:           u32,
+    revision:         u8,
+    checksum:         u8,
+    oem_id:           [u8; 6],
+    oem_table_id:     [u8; 8],
+    oem_revision:     u32,
+    creator_id:       u32,
+    creator_revision: u32
+}
+
+impl Header {
+    // Finds a table in the direct physical map and validates it. There must be a table there.
+    pub unsafe fn at(address: PhysicalAddress) -> Result<&'static Header, TableError> {
+        let header: &'static Header = &*address.to_virtual().as_ptr();
+        header.validate().map(|_| header)
+    }
+
+    pub fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    pub fn length(&self) -> usize {
+        self.length as usize
+    }
+
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    pub fn oem_id(&self) -> [u8; 6] {
+        self.oem_id
+    }
+
+    // Returns the whole table, header included.
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const _ as *const u8, self.length()) }
+    }
+
+    fn validate(&self) -> Result<(), TableError> {
+        if self.length() < size_of::<Header>() {
+            Err(TableError::Truncated(self.signature))
+        } else if self.bytes().iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+            Err(TableError::InvalidChecksum(self.signature))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Reinterprets the table as the given type after checking its signature and length.
+    pub fn cast<T: Table>(&self) -> Result<&T, TableError> {
+        if self.signature != *T::SIGNATURE {
+            Err(TableError::UnexpectedSignature { expected: *T::SIGNATURE, found: self.signature })
+        } else if self.length() < T::MINIMUM_LENGTH {
+            Err(TableError::Truncated(self.signature))
+        } else {
+            Ok(unsafe { &*(self as *const Header as *const T) })
+        }
+    }
+}
+
+impl core::fmt::Debug for Header {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.debug_struct("Header")
+            .field("signature", &SignatureDisplay(self.signature))
+            .field("length", &self.length())
+            .field("revision", &self.revision)
+            .finish()
+    }
+}
+
+// Implemented by the fixed part of each table we understand. Implementors must be packed, start
+// with a Header, and be no longer than MINIMUM_LENGTH unless their accessors check the length.
+pub unsafe trait Table: Sized {
+    const SIGNATURE: &'static Signature;
+    const MINIMUM_LENGTH: usize = size_of::<Self>();
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self as *const Self as *const Header) }
+    }
+
+    // Returns the variable-length part of the table after its fixed fields.
+    fn body(&self) -> &[u8] {
+        &self.header().bytes()[size_of::<Self>().min(self.header().length())..]
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    InvalidChecksum(Signature),
+    Truncated(Signature),
+    UnexpectedSignature { expected: Signature, found: Signature }
+}
+
+impl core::fmt::Display for TableError {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match *self {
+            TableError::InvalidChecksum(signature) => write!(formatter, "{} table has an invalid checksum", SignatureDisplay(signature)),
+            TableError::Truncated(signature) => write!(formatter, "{} table is too short", SignatureDisplay(signature)),
+
+            TableError::UnexpectedSignature { expected, found } => {
+                write!(formatter, "expected a {} table, found {}", SignatureDisplay(expected), SignatureDisplay(found))
+            }
+        }
+    }
+}
+
+pub struct SignatureDisplay(pub Signature);
+
+impl core::fmt::Display for SignatureDisplay {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str(core::str::from_utf8(&self.0).unwrap_or("????"))
+    }
+}
+
+impl core::fmt::Debug for SignatureDisplay {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "\"{}\"", self)
+    }
+}
+
+
+// Describes the location of a register in one of several address spaces.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct GenericAddress {
+    address_space: u8,
+    bit_width:     u8,
+    bit_offset:    u8,
+    access_size:   u8,
+    address:       u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpace {
+    SystemMemory,
+    SystemIO,
+    PCIConfiguration,
+    Other(u8)
+}
+
+impl GenericAddress {
+    pub fn new(address_space: AddressSpace, bit_width: u8, address: u64) -> GenericAddress {
+        let address_space =
+            match address_space {
+                AddressSpace::SystemMemory => 0,
+                AddressSpace::SystemIO => 1,
+                AddressSpace::PCIConfiguration => 2,
+                AddressSpace::Other(id) => id
+            };
+
+        GenericAddress { address_space, bit_width, bit_offset: 0, access_size: 0, address }
+    }
+
+    pub fn address_space(&self) -> AddressSpace {
+        match self.address_space {
+            0 => AddressSpace::SystemMemory,
+            1 => AddressSpace::SystemIO,
+            2 => AddressSpace::PCIConfiguration,
+            id => AddressSpace::Other(id)
+        }
+    }
+
+    pub fn bit_width(&self) -> u8 {
+        self.bit_width
+    }
+
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.address() == 0
+    }
+}
+
+impl core::fmt::Debug for GenericAddress {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.debug_struct("GenericAddress")
+            .field("address_space", &self.address_space())
+            .field("bit_width", &self.bit_width)
+            .field("address", &format_args!("{:#x}", self.address()))
+            .finish()
+    }
+}
+
+
+// Little-endian reads from the variable-length parts of tables, which aren't necessarily aligned.
+pub(super) fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+pub(super) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub(super) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    // Builds a table in a static buffer with the given signature and body, fixing up its length
+    // and checksum. Tests run one at a time, so each can reuse the buffer.
+    pub fn table(signature: &Signature, body: &[u8]) -> &'static Header {
+        static mut BUFFER: [u8; 256] = [0; 256];
+
+        unsafe {
+            let length = size_of::<Header>() + body.len();
+
+            BUFFER = [0; 256];
+            BUFFER[0..4].copy_from_slice(signature);
+            BUFFER[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+            BUFFER[8] = 1;
+            BUFFER[10..16].copy_from_slice(b"GEORGE");
+            BUFFER[size_of::<Header>()..length].copy_from_slice(body);
+
+            let sum = BUFFER[..length].iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+            BUFFER[9] = 0u8.wrapping_sub(sum);
+
+            &*(BUFFER.as_ptr() as *const Header)
+        }
+    }
+
+    #[test]
+    fn validating_a_table_when_valid() {
+        assert_eq!(Ok(()), table(b"TEST", &[1, 2, 3]).validate());
+    }
+
+    #[test]
+    fn validating_a_table_when_invalid() {
+        let header = table(b"TEST", &[1, 2, 3]);
+        unsafe { (header as *const Header as *mut u8).add(36).write(4) }
+
+        assert_eq!(Err(TableError::InvalidChecksum(*b"TEST")), header.validate());
+    }
+}
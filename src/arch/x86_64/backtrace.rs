@@ -0,0 +1,63 @@
+use super::memory::{VirtualAddress, PHYSICAL_MEMORY_OFFSET, paging::AddressSpace};
+
+// How far to follow the chain before assuming it's corrupt.
+const MAXIMUM_DEPTH: usize = 64;
+
+// Returns the current function's frame pointer. Inlined so that it's the caller's.
+#[inline(always)]
+pub fn frame_pointer() -> u64 {
+    let value: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) value, options(nomem, nostack)); }
+    value
+}
+
+// Walks the stack by following saved frame pointers, which the kernel is built to keep. Each
+// function's prologue pushes the caller's frame pointer just below the return address, then points
+// RBP at it. The boot code zeroes RBP before calling into Rust, which ends the chain.
+//
+// Yields return addresses, starting with the caller of the function whose frame pointer it's given.
+#[derive(Clone)]
+pub struct Frames {
+    frame_pointer: u64,
+    depth:         usize
+}
+
+impl Frames {
+    pub fn new(frame_pointer: u64) -> Frames {
+        Frames { frame_pointer, depth: 0 }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = VirtualAddress;
+
+    fn next(&mut self) -> Option<VirtualAddress> {
+        // Kernel stacks are all in the upper half. Anything else means the chain is broken.
+        if self.frame_pointer < PHYSICAL_MEMORY_OFFSET || self.frame_pointer % 8 != 0 || self.depth == MAXIMUM_DEPTH {
+            return None
+        }
+
+        // A corrupt frame pointer can still land in the upper half, on an unmapped page.
+        if !is_mapped(self.frame_pointer) || !is_mapped(self.frame_pointer + 8) {
+            return None
+        }
+
+        let (caller, return_address) = unsafe {
+            let frame = self.frame_pointer as *const u64;
+            (*frame, *frame.add(1))
+        };
+
+        // Stacks grow down, so callers' frames are always higher up. This also rules out cycles.
+        self.frame_pointer = if caller > self.frame_pointer { caller } else { 0 };
+        self.depth += 1;
+
+        VirtualAddress::try_new(return_address).ok().filter(|address| address.as_u64() != 0)
+    }
+}
+
+// Backtraces are taken while panicking, maybe with the kernel's page tables locked, so this looks
+// at the active ones directly instead of going through paging::translate.
+fn is_mapped(address: u64) -> bool {
+    // This is safe because the address space is only read, and only for as long as this takes.
+    VirtualAddress::try_new(address).ok().and_then(|address| unsafe { AddressSpace::active() }.translate(address)).is_some()
+}
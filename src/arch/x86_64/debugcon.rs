@@ -0,0 +1,27 @@
+use super::io::Port;
+use core::fmt::Write;
+use spin::Mutex;
+
+// QEMU's debug console, which passes whatever's written to port 0xE9 on to wherever `-debugcon`
+// points. It needs no setup, and writes go nowhere on machines without one.
+static DEBUGCON: Mutex<DebugConsole> = Mutex::new(DebugConsole { port: 0xE9 });
+
+struct DebugConsole {
+    port: u16
+}
+
+impl core::fmt::Write for DebugConsole {
+    fn write_str(&mut self, string: &str) -> core::fmt::Result {
+        let port = Port::new(self.port);
+
+        for byte in string.bytes() {
+            unsafe { port.write(byte) }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn print(args: core::fmt::Arguments) {
+    super::interrupts::suppress(|| DEBUGCON.lock().write_fmt(args).unwrap())
+}
@@ -10,9 +10,31 @@ pub unsafe fn rdmsrq(number: u32) -> u64 {
     (high << 32) | low
 }
 
+#[inline(always)]
+pub unsafe fn invlpg(address: u64) {
+    asm!("invlpg [{}]", in(reg) address, options(nostack));
+}
+
 #[inline(always)]
 pub fn flags() -> u64 {
     let flags: u64;
     unsafe { asm!("pushf", "pop {}", out(reg) flags, options(nomem)); }
     flags
 }
+
+#[inline(always)]
+pub unsafe fn wrmsrq(number: u32, value: u64) {
+    asm!("wrmsr", in("ecx") number, in("edx") value >> 32, in("eax") value as u32, options(nostack));
+}
+
+#[inline(always)]
+pub fn rdtsc() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+// Returns EAX, EBX, ECX and EDX for the given CPUID leaf and subleaf.
+#[inline(always)]
+pub fn cpuid(leaf: u32, subleaf: u32) -> [u32; 4] {
+    let result = unsafe { core::arch::x86_64::__cpuid_count(leaf, subleaf) };
+    [result.eax, result.ebx, result.ecx, result.edx]
+}
@@ -1,37 +1,155 @@
 use super::Vector;
+use crate::arch::x86_64::memory::PhysicalAddress;
+use crate::acpi::madt::{MADT, Entry, Polarity};
 
 const IA32_APIC_BASE_MSR: u32 = 0x1B;
 
 #[repr(C)]
 pub struct APIC {
-    _1: [u32; 44],
+    _1: [u32; 8],
+    id_register: volatile::ReadOnly<u32>,
+    _2: [u32; 35],
     end_of_interrupt_register: volatile::WriteOnly<u32>,
-    _2: [u32; 155],
+    _3: [u32; 15],
+    spurious_interrupt_vector_register: volatile::ReadWrite<u32>,
+    _4: [u32; 131],
+    interrupt_command_register_low: volatile::ReadWrite<u32>,
+    _5: [u32; 3],
+    interrupt_command_register_high: volatile::ReadWrite<u32>,
+    _6: [u32; 3],
     timer_vector_register: volatile::ReadWrite<u32>,
-    _3: [u32; 23],
+    _7: [u32; 11],
+    lint0_vector_register: volatile::ReadWrite<u32>,
+    _8: [u32; 3],
+    lint1_vector_register: volatile::ReadWrite<u32>,
+    _9: [u32; 7],
     timer_initial_count_register: volatile::ReadWrite<u32>,
-    _4: [u32; 23],
+    _10: [u32; 3],
+    timer_current_count_register: volatile::ReadOnly<u32>,
+    _11: [u32; 19],
     timer_divide_configuration_register: volatile::ReadWrite<u32>
 }
 
+// How the timer counts: down once and stop, down over and over, or until the TSC reaches the
+// deadline in the IA32_TSC_DEADLINE MSR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    OneShot  = 0b00,
+    Periodic = 0b01,
+    Deadline = 0b10
+}
+
+// An interprocessor interrupt, as the interrupt command register sends it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IPI {
+    // Resets the processor into a state where it waits for a startup IPI.
+    Init,
+
+    // Starts the processor in real mode at the start of the given page, which has to be below
+    // 1 MiB.
+    Startup(u8)
+}
+
+const MASKED: u32 = 1 << 16;
+
+// Set in the spurious interrupt vector register to enable the APIC. It starts out disabled on
+// application processors.
+const SOFTWARE_ENABLE: u32 = 1 << 8;
+
+// Set in the interrupt command register while an IPI's still being sent.
+const DELIVERY_PENDING: u32 = 1 << 12;
+
 impl APIC {
     // This function is unsafe because two separate callers will get mutable references to the same
     // underlying data. It is the caller’s responsibility to ensure no other code writes to the
     // APIC concurrently.
     pub unsafe fn get() -> &'static mut APIC {
-        &mut *(APIC::base() as *mut _)
+        &mut *APIC::base().to_virtual().as_mut_ptr()
     }
 
-    fn base() -> u64 {
+    fn base() -> PhysicalAddress {
         // This is safe because the IA32_APIC_BASE MSR is architecture-specified.
-        unsafe { crate::arch::x86_64::instructions::rdmsrq(IA32_APIC_BASE_MSR) & 0xFFFFFF000 }
+        PhysicalAddress::new(unsafe { crate::arch::x86_64::instructions::rdmsrq(IA32_APIC_BASE_MSR) & 0xFFFFFF000 })
+    }
+
+    // Sets how the timer counts and where it interrupts. A masked timer still counts.
+    pub fn set_timer(&mut self, vector: Vector, mode: TimerMode, masked: bool) {
+        let mask = if masked { MASKED } else { 0 };
+        self.timer_vector_register.write((mode as u32) << 17 | mask | vector.number() as u32);
+    }
+
+    // Starts the timer counting down from the given count, or stops it if that's zero. Ignored in
+    // deadline mode.
+    pub fn start_timer(&mut self, count: u32) {
+        self.timer_initial_count_register.write(count);
+    }
+
+    pub fn timer_count(&self) -> u32 {
+        self.timer_current_count_register.read()
     }
 
-    pub fn initialize(&mut self) {
-        self.timer_vector_register.write(0x20000 | Vector::Timer as u32);
-        self.timer_initial_count_register.write(10000000);
-        self.timer_divide_configuration_register.write(0xB);
-        self.end_of_interrupt_register.write(0);
+    // Sets how many bus clock cycles make up one timer tick: a power of two up to 128.
+    pub fn set_timer_divisor(&mut self, divisor: u32) {
+        // The exponent's lowest two bits go in bits 0 and 1, and its top bit in bit 3. Dividing by
+        // 1 is encoded as though it were dividing by 256.
+        let exponent = (divisor.trailing_zeros() + 7) % 8;
+        self.timer_divide_configuration_register.write((exponent & 0b11) | (exponent & 0b100) << 1);
+    }
+
+    // Enables the APIC, sending spurious interrupts to the given vector.
+    pub fn enable(&mut self, spurious: Vector) {
+        self.spurious_interrupt_vector_register.write(SOFTWARE_ENABLE | spurious.number() as u32);
+    }
+
+    // Sends an IPI to the processor with the given APIC ID, and waits for it to go out.
+    pub fn send(&mut self, destination: u8, ipi: IPI) {
+        // Bits 8 to 10 are the delivery mode, and bit 14 asserts the IPI, as both have to be.
+        let command = match ipi {
+            IPI::Init => 0b101 << 8 | 1 << 14,
+            IPI::Startup(page) => 0b110 << 8 | 1 << 14 | page as u32
+        };
+
+        // Writing the low half sends it, so the destination goes first.
+        self.interrupt_command_register_high.write((destination as u32) << 24);
+        self.interrupt_command_register_low.write(command);
+
+        while self.interrupt_command_register_low.read() & DELIVERY_PENDING != 0 {
+            core::hint::spin_loop();
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        (self.id_register.read() >> 24) as u8
+    }
+
+    // Routes the LINT pins the MADT says are wired to NMI, for this processor or for all of them.
+    pub fn configure_nmis(&mut self, madt: &MADT) {
+        let id = self.id();
+
+        let processor =
+            madt.entries().find_map(|entry| match entry {
+                Entry::LocalAPIC { processor_id, apic_id, .. } if apic_id == id => Some(processor_id),
+                _ => None
+            });
+
+        for entry in madt.entries() {
+            if let Entry::LocalAPICNMI { processor_id, flags, lint } = entry {
+                if processor_id == 0xFF || Some(processor_id) == processor {
+                    // NMIs are always edge-triggered, so only the polarity matters.
+                    let mut value = 0b100 << 8;
+
+                    if flags.polarity() == Some(Polarity::ActiveLow) {
+                        value |= 1 << 13;
+                    }
+
+                    match lint {
+                        0 => self.lint0_vector_register.write(value),
+                        1 => self.lint1_vector_register.write(value),
+                        _ => ()
+                    }
+                }
+            }
+        }
     }
 
     pub fn acknowledge(&mut self) {
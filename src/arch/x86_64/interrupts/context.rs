@@ -0,0 +1,64 @@
+use super::Vector;
+use crate::arch::x86_64::memory::VirtualAddress;
+
+// The state of the interrupted code, as saved on the stack by the CPU and the entry stubs in
+// entry.S. Fields are in order of increasing address, the reverse of the order they're pushed in.
+// Handlers can change the registers, which are restored when the interrupt returns.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Context {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9:  u64,
+    pub r8:  u64,
+    pub rbp: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub rcx: u64,
+    pub rbx: u64,
+    pub rax: u64,
+
+    vector:         u64,
+    pub error_code: u64,
+
+    pub instruction_pointer: VirtualAddress,
+    pub code_segment:        u64,
+    pub flags:               u64,
+    pub stack_pointer:       VirtualAddress,
+    pub stack_segment:       u64
+}
+
+impl Context {
+    pub fn vector(&self) -> Vector {
+        Vector::new(self.vector as u8)
+    }
+}
+
+impl core::fmt::Display for Context {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(formatter, "RIP: {:04x}:{:016x}  RSP: {:04x}:{:016x}  RFLAGS: {:08x}",
+            self.code_segment, self.instruction_pointer.as_u64(), self.stack_segment, self.stack_pointer.as_u64(), self.flags)?;
+        writeln!(formatter, "RAX: {:016x}  RBX: {:016x}  RCX: {:016x}", self.rax, self.rbx, self.rcx)?;
+        writeln!(formatter, "RDX: {:016x}  RSI: {:016x}  RDI: {:016x}", self.rdx, self.rsi, self.rdi)?;
+        writeln!(formatter, "RBP: {:016x}  R8:  {:016x}  R9:  {:016x}", self.rbp, self.r8, self.r9)?;
+        writeln!(formatter, "R10: {:016x}  R11: {:016x}  R12: {:016x}", self.r10, self.r11, self.r12)?;
+        write!(formatter, "R13: {:016x}  R14: {:016x}  R15: {:016x}", self.r13, self.r14, self.r15)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::size_of;
+
+    #[test]
+    fn sizing() {
+        // 15 registers, the vector and error code, and the CPU's five-word frame.
+        assert_eq!(22 * 8, size_of::<Context>());
+    }
+}
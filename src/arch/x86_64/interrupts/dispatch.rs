@@ -0,0 +1,319 @@
+// Every interrupt enters through the stubs in entry.S and is dispatched to whichever handlers are
+// registered for it at the time. Exceptions nobody handles are fatal.
+
+use super::{Vector, vectors::Vectors, Context, Exception, IOAPICS, LAPIC, acknowledge, suppress, local_apic_id};
+use super::exceptions::Report;
+use super::ioapic::{Interrupt, Delivery};
+use crate::arch::x86_64::memory::VirtualAddress;
+
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicU16, Ordering};
+use lazy_static::lazy_static;
+use log::warn;
+use spin::RwLock;
+
+global_asm!(include_str!("entry.S"));
+
+extern "C" {
+    #[link_name = "interrupts.stubs"]
+    static STUBS: u8;
+}
+
+lazy_static! {
+    static ref TABLE: RwLock<Table> = RwLock::new(Table::new());
+}
+
+// The local APIC ID of the processor changing the table, if one is. An exception it raises
+// meanwhile can't wait for the change to finish, but one raised on any other processor can.
+static WRITER: AtomicU16 = AtomicU16::new(NO_WRITER);
+const NO_WRITER: u16 = u16::MAX;
+
+// Something that takes interrupts. Any closure taking the context will do.
+pub trait Handler: Send + Sync {
+    // Returns whether the interrupt came from this handler's device. Handlers sharing an input are
+    // all called, since more than one device can be asking at once.
+    //
+    // For exceptions, returns whether the handler resolved the fault, say by mapping the missing
+    // page or emulating the instruction and moving past it. The interrupted code then carries on
+    // from the context's instruction pointer. Handlers are tried in turn until one resolves it.
+    fn handle(&self, context: &mut Context) -> bool;
+}
+
+impl<F> Handler for F where F: Fn(&mut Context) -> bool + Send + Sync {
+    fn handle(&self, context: &mut Context) -> bool {
+        self(context)
+    }
+}
+
+// Where an interrupt comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IRQ {
+    // A legacy ISA IRQ, which the MADT can reroute to a different I/O APIC input.
+    ISA(u8),
+
+    // An I/O APIC input and how it's signalled, as for PCI devices.
+    Global(Interrupt),
+
+    // Nothing the I/O APICs know about, like the local APIC timer or a message-signalled
+    // interrupt. These get a vector of their own, which the device has to be told about.
+    Local,
+
+    // One of the CPU's exceptions.
+    Exception(Exception)
+}
+
+// Proof of a handler's registration, which unregistering it takes back.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Registration {
+    vector: Vector,
+    id:     u64
+}
+
+impl Registration {
+    pub fn vector(&self) -> Vector {
+        self.vector
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    NoFreeVector,
+    NoSuchInput,
+    Conflict
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::NoFreeVector => write!(formatter, "all interrupt vectors are in use"),
+            Error::NoSuchInput => write!(formatter, "no I/O APIC has that input"),
+            Error::Conflict => write!(formatter, "the input is already in use with a different polarity or trigger mode")
+        }
+    }
+}
+
+// Registers a handler for interrupts from the given source, allocating a vector and routing the
+// I/O APIC input to it the first time. Handlers can't register or unregister others themselves.
+pub fn register<H>(irq: IRQ, handler: H) -> Result<Registration, Error> where H: Handler + 'static {
+    let input = match irq {
+        IRQ::ISA(irq) => Some(IOAPICS.legacy(irq)),
+        IRQ::Global(interrupt) => Some(interrupt),
+        IRQ::Local | IRQ::Exception(_) => None
+    };
+
+    if let Some(input) = input {
+        if IOAPICS.redirection_for(input.number).is_none() {
+            return Err(Error::NoSuchInput)
+        }
+    }
+
+    change(|table| {
+        // Devices sharing an input share its vector, as long as they agree on how it's signalled.
+        let shared = input.and_then(|input| table.find(input.number));
+
+        let vector = match (irq, shared) {
+            (IRQ::Exception(exception), _) => exception.vector(),
+            (_, Some(vector)) if table.slots[vector.number() as usize].input == input => vector,
+            (_, Some(_)) => return Err(Error::Conflict),
+            (_, None) => table.vectors.allocate().ok_or(Error::NoFreeVector)?
+        };
+
+        let id = table.next;
+        table.next += 1;
+
+        let slot = &mut table.slots[vector.number() as usize];
+        slot.input = input;
+        slot.handlers.push((id, Box::new(handler)));
+
+        // Only route the input once there's a handler to take its interrupts.
+        if let (Some(input), None) = (input, shared) {
+            IOAPICS.route(input, Delivery::Fixed(vector.number()), LAPIC.lock().id());
+        }
+
+        Ok(Registration { vector, id })
+    })
+}
+
+// Removes a handler. Once an input has no handlers left, it's masked and its vector is freed.
+pub fn unregister(registration: Registration) {
+    change(|table| {
+        let slot = &mut table.slots[registration.vector.number() as usize];
+
+        slot.handlers.retain(|(id, _)| *id != registration.id);
+
+        if slot.handlers.is_empty() && registration.vector >= Vector::FIRST_DEVICE {
+            if let Some(input) = slot.input.take() {
+                IOAPICS.disable(input.number);
+            }
+
+            table.vectors.free(registration.vector);
+        }
+    })
+}
+
+// Changes the table, noting which processor is doing it.
+fn change<T>(f: impl FnOnce(&mut Table) -> T) -> T {
+    suppress(|| {
+        let mut table = TABLE.write();
+        WRITER.store(local_apic_id() as u16, Ordering::Relaxed);
+
+        let result = f(&mut table);
+
+        WRITER.store(NO_WRITER, Ordering::Relaxed);
+        result
+    })
+}
+
+// Returns the entry stub for a vector.
+pub fn stub(vector: Vector) -> VirtualAddress {
+    VirtualAddress::from(unsafe { &STUBS }) + 16 * vector.number() as u64
+}
+
+// Called by the entry stubs, with interrupts disabled.
+#[no_mangle]
+extern "C" fn dispatch_interrupt(context: &mut Context) {
+    let vector = context.vector();
+
+    if vector < Vector::FIRST_DEVICE {
+        // A fault in the middle of this processor's own change to the table would wait forever for
+        // it to finish, so it goes unhandled. Other processors' changes finish soon enough.
+        let resolved =
+            WRITER.load(Ordering::Relaxed) != local_apic_id() as u16 &&
+                TABLE.read().dispatch(vector, context);
+
+        if !resolved {
+            panic!("{}", Report(context));
+        }
+
+        return
+    }
+
+    // Spurious interrupts aren't real interrupts, so there's nothing to handle or acknowledge.
+    if vector == Vector::SPURIOUS {
+        return
+    }
+
+    if !TABLE.read().dispatch(vector, context) {
+        warn!("Unhandled interrupt on vector {}", vector);
+    }
+
+    acknowledge();
+}
+
+struct Table {
+    vectors: Vectors,
+    slots:   Vec<Slot>,
+    next:    u64
+}
+
+// The handlers for one vector, and the I/O APIC input routed to it, if any.
+#[derive(Default)]
+struct Slot {
+    input:    Option<Interrupt>,
+    handlers: Vec<(u64, Box<dyn Handler>)>
+}
+
+impl Table {
+    fn new() -> Table {
+        Table {
+            vectors: Vectors::new(),
+            slots:   (0..256).map(|_| Slot::default()).collect(),
+            next:    0
+        }
+    }
+
+    // Returns the vector an I/O APIC input is routed to.
+    fn find(&self, input: u32) -> Option<Vector> {
+        self.slots.iter()
+            .position(|slot| slot.input.map(|interrupt| interrupt.number) == Some(input))
+            .map(|index| Vector::new(index as u8))
+    }
+
+    fn dispatch(&self, vector: Vector, context: &mut Context) -> bool {
+        let mut handlers = self.slots[vector.number() as usize].handlers.iter().map(|(_, handler)| handler);
+
+        if vector < Vector::FIRST_DEVICE {
+            handlers.any(|handler| handler.handle(context))
+        } else {
+            handlers.fold(false, |handled, handler| handler.handle(context) || handled)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    fn context() -> Context {
+        unsafe { core::mem::zeroed() }
+    }
+
+    fn counter(count: &Arc<AtomicUsize>, handled: bool) -> impl Fn(&mut Context) -> bool + Send + Sync {
+        let count = count.clone();
+        move |_| { count.fetch_add(1, Ordering::Relaxed); handled }
+    }
+
+    #[test]
+    fn dispatching_to_local_handlers() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let registration = register(IRQ::Local, counter(&count, true)).unwrap();
+        let vector = registration.vector();
+
+        assert!(TABLE.read().dispatch(vector, &mut context()));
+        assert_eq!(1, count.load(Ordering::Relaxed));
+
+        unregister(registration);
+        assert!(!TABLE.read().dispatch(vector, &mut context()));
+        assert!(!TABLE.read().vectors.is_used(vector));
+    }
+
+    #[test]
+    fn chaining_shared_handlers() {
+        // ISA IRQ 5 is free on QEMU's PC.
+        let first = Arc::new(AtomicUsize::new(0));
+        let second = Arc::new(AtomicUsize::new(0));
+
+        let a = register(IRQ::ISA(5), counter(&first, false)).unwrap();
+        let b = register(IRQ::ISA(5), counter(&second, true)).unwrap();
+        let vector = a.vector();
+
+        assert_eq!(vector, b.vector());
+
+        let redirection = IOAPICS.redirection_for(IOAPICS.legacy(5).number).unwrap();
+        assert!(redirection.is_enabled());
+        assert_eq!(vector.number(), redirection.vector());
+
+        // Every handler sees the interrupt, even once one has claimed it.
+        assert!(TABLE.read().dispatch(vector, &mut context()));
+        assert_eq!((1, 1), (first.load(Ordering::Relaxed), second.load(Ordering::Relaxed)));
+
+        unregister(b);
+        assert!(redirection.is_enabled());
+        assert!(!TABLE.read().dispatch(vector, &mut context()));
+
+        unregister(a);
+        assert!(redirection.is_disabled());
+        assert!(!TABLE.read().vectors.is_used(vector));
+    }
+
+    #[test]
+    fn rejecting_conflicting_signalling() {
+        use crate::acpi::madt::{Polarity, TriggerMode};
+
+        let input = IOAPICS.legacy(6);
+        let registration = register(IRQ::Global(input), |_: &mut Context| true).unwrap();
+
+        let level = Interrupt { trigger_mode: TriggerMode::Level, polarity: Polarity::ActiveLow, ..input };
+        assert_eq!(Err(Error::Conflict), register(IRQ::Global(level), |_: &mut Context| true));
+
+        unregister(registration);
+    }
+
+    #[test]
+    fn rejecting_missing_inputs() {
+        let input = Interrupt { number: 100_000, ..IOAPICS.legacy(0) };
+        assert_eq!(Err(Error::NoSuchInput), register(IRQ::Global(input), |_: &mut Context| true));
+    }
+}
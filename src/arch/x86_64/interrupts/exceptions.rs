@@ -0,0 +1,291 @@
+use super::{Vector, Context, idt::PageFaultErrorCode};
+use crate::arch::x86_64::registers::{CR0, CR2, CR3, CR4, DS, ES, FS, GS};
+use crate::backtrace::Backtrace;
+
+// The architectural exceptions, vectors 0 to 31. The gaps are reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    DivideError = 0,
+    Debug,
+    NonMaskableInterrupt,
+    Breakpoint,
+    Overflow,
+    BoundRangeExceeded,
+    InvalidOpcode,
+    DeviceNotAvailable,
+    DoubleFault,
+    CoprocessorSegmentOverrun,
+    InvalidTSS,
+    SegmentNotPresent,
+    StackSegmentFault,
+    GeneralProtectionFault,
+    PageFault,
+    X87FloatingPoint = 16,
+    AlignmentCheck,
+    MachineCheck,
+    SIMDFloatingPoint,
+    Virtualization,
+    ControlProtection,
+    HypervisorInjection = 28,
+    VMMCommunication,
+    Security
+}
+
+impl Exception {
+    pub fn from_vector(vector: Vector) -> Option<Exception> {
+        use Exception::*;
+
+        let exception = match vector.number() {
+            0  => DivideError,
+            1  => Debug,
+            2  => NonMaskableInterrupt,
+            3  => Breakpoint,
+            4  => Overflow,
+            5  => BoundRangeExceeded,
+            6  => InvalidOpcode,
+            7  => DeviceNotAvailable,
+            8  => DoubleFault,
+            9  => CoprocessorSegmentOverrun,
+            10 => InvalidTSS,
+            11 => SegmentNotPresent,
+            12 => StackSegmentFault,
+            13 => GeneralProtectionFault,
+            14 => PageFault,
+            16 => X87FloatingPoint,
+            17 => AlignmentCheck,
+            18 => MachineCheck,
+            19 => SIMDFloatingPoint,
+            20 => Virtualization,
+            21 => ControlProtection,
+            28 => HypervisorInjection,
+            29 => VMMCommunication,
+            30 => Security,
+            _  => return None
+        };
+
+        Some(exception)
+    }
+
+    pub fn vector(self) -> Vector {
+        Vector::new(self as u8)
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        use Exception::*;
+
+        match self {
+            DivideError               => "#DE",
+            Debug                     => "#DB",
+            NonMaskableInterrupt      => "NMI",
+            Breakpoint                => "#BP",
+            Overflow                  => "#OF",
+            BoundRangeExceeded        => "#BR",
+            InvalidOpcode             => "#UD",
+            DeviceNotAvailable        => "#NM",
+            DoubleFault               => "#DF",
+            CoprocessorSegmentOverrun => "CSO",
+            InvalidTSS                => "#TS",
+            SegmentNotPresent         => "#NP",
+            StackSegmentFault         => "#SS",
+            GeneralProtectionFault    => "#GP",
+            PageFault                 => "#PF",
+            X87FloatingPoint          => "#MF",
+            AlignmentCheck            => "#AC",
+            MachineCheck              => "#MC",
+            SIMDFloatingPoint         => "#XM",
+            Virtualization            => "#VE",
+            ControlProtection         => "#CP",
+            HypervisorInjection       => "#HV",
+            VMMCommunication          => "#VC",
+            Security                  => "#SX"
+        }
+    }
+
+    // Decodes the error code the CPU pushed, for the exceptions that have one worth decoding.
+    fn describe_error(self, code: u64, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        use Exception::*;
+
+        match self {
+            PageFault => write!(formatter, "{:?}", PageFaultErrorCode::from_bits_truncate(code)),
+            InvalidTSS | SegmentNotPresent | StackSegmentFault | GeneralProtectionFault => {
+                write!(formatter, "{}", SelectorErrorCode(code))
+            }
+
+            ControlProtection => {
+                let kind = match code & 0x7FFF {
+                    1 => "near return",
+                    2 => "far return or interrupt return",
+                    3 => "missing end-branch",
+                    4 => "shadow stack restore",
+                    5 => "shadow stack busy",
+                    _ => "unknown"
+                };
+
+                write!(formatter, "{}", kind)
+            }
+
+            _ => write!(formatter, "{:#x}", code)
+        }
+    }
+}
+
+impl core::fmt::Display for Exception {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        use Exception::*;
+
+        let name = match self {
+            DivideError               => "Divide error",
+            Debug                     => "Debug",
+            NonMaskableInterrupt      => "Non-maskable interrupt",
+            Breakpoint                => "Breakpoint",
+            Overflow                  => "Overflow",
+            BoundRangeExceeded        => "Bound range exceeded",
+            InvalidOpcode             => "Invalid opcode",
+            DeviceNotAvailable        => "Device not available",
+            DoubleFault               => "Double fault",
+            CoprocessorSegmentOverrun => "Coprocessor segment overrun",
+            InvalidTSS                => "Invalid TSS",
+            SegmentNotPresent         => "Segment not present",
+            StackSegmentFault         => "Stack-segment fault",
+            GeneralProtectionFault    => "General protection fault",
+            PageFault                 => "Page fault",
+            X87FloatingPoint          => "x87 floating-point exception",
+            AlignmentCheck            => "Alignment check",
+            MachineCheck              => "Machine check",
+            SIMDFloatingPoint         => "SIMD floating-point exception",
+            Virtualization            => "Virtualization exception",
+            ControlProtection         => "Control protection exception",
+            HypervisorInjection       => "Hypervisor injection exception",
+            VMMCommunication          => "VMM communication exception",
+            Security                  => "Security exception"
+        };
+
+        write!(formatter, "{} ({})", name, self.mnemonic())
+    }
+}
+
+// The error code of exceptions caused by a segment selector or a gate: which descriptor it was,
+// and whether something outside the program, like a hardware interrupt, caused it.
+pub struct SelectorErrorCode(pub u64);
+
+impl core::fmt::Display for SelectorErrorCode {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if self.0 == 0 {
+            return write!(formatter, "none")
+        }
+
+        let table = match (self.0 >> 1) & 0b11 {
+            0b00 => "GDT",
+            0b10 => "LDT",
+            _    => "IDT"
+        };
+
+        write!(formatter, "{} entry {}", table, (self.0 >> 3) & 0x1FFF)?;
+
+        if self.0 & 1 != 0 {
+            write!(formatter, ", external")?;
+        }
+
+        Ok(())
+    }
+}
+
+// Everything there is to know about an exception: the interrupted code's registers along with the
+// control and segment registers, which the entry stubs don't save.
+pub struct Report<'a>(pub &'a Context);
+
+impl core::fmt::Display for Report<'_> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let context = self.0;
+
+        match Exception::from_vector(context.vector()) {
+            Some(exception) => {
+                write!(formatter, "EXCEPTION: {}\nError code: ", exception)?;
+                exception.describe_error(context.error_code, formatter)?;
+                writeln!(formatter)?;
+            }
+
+            None => writeln!(formatter, "EXCEPTION: reserved vector {}", context.vector())?
+        }
+
+        writeln!(formatter, "{}", context)?;
+        writeln!(formatter, "CS:  {:04x}  SS:  {:04x}  DS:  {:04x}  ES:  {:04x}  FS:  {:04x}  GS:  {:04x}",
+            context.code_segment, context.stack_segment, DS::get(), ES::get(), FS::get(), GS::get())?;
+        writeln!(formatter, "CR0: {:016x}  CR2: {:016x}  CR3: {:016x}  CR4: {:016x}",
+            CR0::read(), CR2::read().as_u64(), CR3::read().as_u64(), CR4::read())?;
+        write!(formatter, "{}", Backtrace::from(context.instruction_pointer, context.rbp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{register, unregister, IRQ};
+    use crate::arch::x86_64::memory::{VirtualAddress, KERNEL_HEAP, paging::{self, PageSize, Flags}};
+    use crate::memory;
+    use alloc::string::ToString;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn numbering_exceptions() {
+        assert_eq!(Some(Exception::PageFault), Exception::from_vector(Vector::new(14)));
+        assert_eq!(Some(Exception::Security), Exception::from_vector(Vector::new(30)));
+        assert_eq!(None, Exception::from_vector(Vector::new(15)));
+        assert_eq!(None, Exception::from_vector(Vector::new(32)));
+    }
+
+    #[test]
+    fn decoding_selector_error_codes() {
+        assert_eq!("none", SelectorErrorCode(0).to_string());
+        assert_eq!("GDT entry 5", SelectorErrorCode(5 << 3).to_string());
+        assert_eq!("IDT entry 13, external", SelectorErrorCode(13 << 3 | 0b011).to_string());
+        assert_eq!("LDT entry 1", SelectorErrorCode(1 << 3 | 0b100).to_string());
+    }
+
+    #[test]
+    fn continuing_after_breakpoints() {
+        unsafe { asm!("int3") }
+    }
+
+    #[test]
+    fn emulating_invalid_opcodes() {
+        static EMULATED: AtomicBool = AtomicBool::new(false);
+
+        let registration = register(IRQ::Exception(Exception::InvalidOpcode), |context: &mut Context| {
+            // Skip the two-byte UD2.
+            context.instruction_pointer = context.instruction_pointer + 2u64;
+            EMULATED.store(true, Ordering::Relaxed);
+            true
+        }).unwrap();
+
+        unsafe { asm!("ud2") }
+        unregister(registration);
+
+        assert!(EMULATED.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn mapping_pages_on_demand() {
+        // Nothing is mapped just below the kernel heap.
+        let page = VirtualAddress::new(KERNEL_HEAP.start - 0x1000);
+
+        let registration = register(IRQ::Exception(Exception::PageFault), move |_: &mut Context| {
+            if CR2::read().as_u64() & !0xFFF != page.as_u64() {
+                return false
+            }
+
+            let frame = memory::allocate_frames(0).unwrap();
+            paging::map(page, frame, PageSize::Size4KiB, Flags::WRITABLE).is_ok()
+        }).unwrap();
+
+        unsafe {
+            core::ptr::write_volatile(page.as_mut_ptr::<u64>().add(1), 42);
+            assert_eq!(42, core::ptr::read_volatile(page.as_ptr::<u64>().add(1)));
+        }
+
+        unregister(registration);
+
+        let (frame, _) = paging::unmap(page).unwrap();
+        memory::free_frames(frame, 0);
+    }
+}
@@ -1,34 +1,21 @@
-use crate::{println, print};
-use super::idt::{InterruptStackFrame, PageFaultErrorCode};
-use super::acknowledge;
-use crate::arch::x86_64::{halt, registers::CR2};
+use crate::print;
+use super::{Context, exceptions::Report};
+use log::warn;
 
-pub extern "x86-interrupt" fn breakpoint(stack_frame: &InterruptStackFrame) {
-    println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame)
+// Breakpoints are for debugging, so show where we are and carry on.
+pub fn breakpoint(context: &mut Context) -> bool {
+    warn!("{}", Report(context));
+    true
 }
 
-pub extern "x86-interrupt" fn double_fault(stack_frame: &InterruptStackFrame, _error_code: u64) -> ! {
-    panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame)
+pub fn timer(_context: &mut Context) -> bool {
+    super::timer::tick();
+    crate::time::update();
+    crate::time::timer::run();
+    true
 }
 
-pub extern "x86-interrupt" fn general_protection_fault(stack_frame: &InterruptStackFrame, error_code: u64) {
-    panic!("EXCEPTION: GENERAL PROTECTION FAULT ({})\n{:?}", error_code, stack_frame)
-}
-
-pub extern "x86-interrupt" fn page_fault(stack_frame: &InterruptStackFrame, error_code: PageFaultErrorCode) {
-    println!("--- PAGE FAULT ---");
-    println!("Linear address: {:?}", CR2::read());
-    println!("Error code: {:?}", error_code);
-    println!("Stack frame: {:?}", stack_frame);
-    halt();
-}
-
-pub extern "x86-interrupt" fn timer(_stack_frame: &InterruptStackFrame) {
-    print!(".");
-    acknowledge();
-}
-
-pub extern "x86-interrupt" fn keyboard(_stack_frame: &InterruptStackFrame) {
+pub fn keyboard(_context: &mut Context) -> bool {
     print!("*");
-    acknowledge();
+    true
 }
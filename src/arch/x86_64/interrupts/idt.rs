@@ -85,6 +85,15 @@ impl InterruptDescriptorTable {
         }
     }
 
+    // Points an entry at raw code rather than a Rust handler, whichever kind of handler the entry
+    // would otherwise take. This is unsafe because the code has to be a valid entrypoint for the
+    // vector, returning with iretq.
+    pub unsafe fn handle_at(&mut self, vector: Vector, address: VirtualAddress) -> &mut EntryOptions {
+        // Entries only differ in their handler type, which the CPU doesn't care about.
+        let entries = &mut *(self as *mut InterruptDescriptorTable as *mut [Entry<Handler>; 256]);
+        entries[vector.number() as usize].handle_at_address(address.as_u64())
+    }
+
     pub fn load(&self) {
         unsafe { lidt(&Pointer::new(self)) }
     }
@@ -154,13 +163,13 @@ impl Index<Vector> for InterruptDescriptorTable {
     type Output = Entry<Handler>;
 
     fn index(&self, index: Vector) -> &Self::Output {
-        &self[index as u8]
+        &self[index.number()]
     }
 }
 
 impl IndexMut<Vector> for InterruptDescriptorTable {
     fn index_mut(&mut self, index: Vector) -> &mut Self::Output {
-        &mut self[index as u8]
+        &mut self[index.number()]
     }
 }
 
@@ -271,6 +280,8 @@ bitflags! {
         const USER_MODE            = 1 << 2;
         const MALFORMED_TABLE      = 1 << 3;
         const INSTRUCTION_FETCH    = 1 << 4;
+        const PROTECTION_KEY       = 1 << 5;
+        const SHADOW_STACK         = 1 << 6;
     }
 }
 
@@ -3,18 +3,150 @@
 use spin::Mutex;
 use bit_field::BitField;
 use tap::tap::Tap;
+use alloc::vec::Vec;
+
+use crate::acpi::madt::{MADT, Entry, InterruptFlags, Polarity, TriggerMode};
+use crate::arch::x86_64::memory::PhysicalAddress;
+
+// All of the system's I/O APICs, along with how legacy ISA IRQs map onto their inputs.
+pub struct IOAPICs {
+    controllers: Vec<IOAPIC>,
+    overrides:   Vec<(u8, Interrupt)>,
+    nmis:        Vec<Interrupt>
+}
+
+impl IOAPICs {
+    // Finds the I/O APICs listed in the MADT. Without one, we assume a single I/O APIC at the
+    // address most chipsets put it, handling interrupts 0 and up with ISA IRQs identity-mapped.
+    pub unsafe fn discover(madt: Option<&MADT>) -> IOAPICs {
+        let mut controllers = Vec::new();
+        let mut overrides = Vec::new();
+        let mut nmis = Vec::new();
+
+        for entry in madt.into_iter().flat_map(|madt| madt.entries()) {
+            match entry {
+                Entry::IOAPIC { id, address, interrupt_base } => {
+                    controllers.push(IOAPIC::new(address, id, interrupt_base))
+                }
+
+                // Bus 0 is ISA, the only bus overrides are defined for.
+                Entry::InterruptSourceOverride { bus: 0, source, interrupt, flags } => {
+                    overrides.push((source, Interrupt::isa(interrupt, flags)))
+                }
+
+                Entry::NMISource { interrupt, flags } => {
+                    nmis.push(Interrupt::isa(interrupt, flags))
+                }
+
+                _ => ()
+            }
+        }
+
+        if controllers.is_empty() {
+            controllers.push(IOAPIC::new(PhysicalAddress::new(IOAPIC::DEFAULT_BASE), 0, 0))
+        }
+
+        IOAPICs { controllers, overrides, nmis }
+    }
+
+    // Masks every input, then routes the non-maskable ones to the given local APIC.
+    pub fn initialize(&self, destination: u8) {
+        for controller in self.controllers.iter() {
+            controller.initialize()
+        }
+
+        for &interrupt in self.nmis.iter() {
+            self.route(interrupt, Delivery::NonMaskable, destination)
+        }
+    }
+
+    // Returns the global system interrupt a legacy ISA IRQ arrives on, and how it's signalled.
+    pub fn legacy(&self, irq: u8) -> Interrupt {
+        self.overrides.iter()
+            .find(|(source, _)| *source == irq)
+            .map(|&(_, interrupt)| interrupt)
+            .unwrap_or_else(|| Interrupt::isa(irq as u32, InterruptFlags(0)))
+    }
+
+    pub fn disable(&self, interrupt: u32) {
+        if let Some(redirection) = self.redirection_for(interrupt) {
+            redirection.disable()
+        }
+    }
+
+    pub fn route(&self, interrupt: Interrupt, delivery: Delivery, destination: u8) {
+        if let Some(redirection) = self.redirection_for(interrupt.number) {
+            redirection.route(delivery, interrupt.polarity, interrupt.trigger_mode, destination)
+        }
+    }
+
+    pub fn redirection_for(&self, interrupt: u32) -> Option<Redirection> {
+        self.controllers.iter()
+            .find(|controller| controller.handles(interrupt))
+            .and_then(|controller| controller.redirection_at((interrupt - controller.interrupt_base) as u8))
+    }
+
+    pub fn controllers(&self) -> &[IOAPIC] {
+        &self.controllers
+    }
+}
+
+// A global system interrupt: an input on one of the I/O APICs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupt {
+    pub number:       u32,
+    pub polarity:     Polarity,
+    pub trigger_mode: TriggerMode
+}
+
+impl Interrupt {
+    // ISA interrupts are active-high and edge-triggered unless the flags say otherwise.
+    fn isa(number: u32, flags: InterruptFlags) -> Interrupt {
+        Interrupt {
+            number,
+            polarity:     flags.polarity().unwrap_or(Polarity::ActiveHigh),
+            trigger_mode: flags.trigger_mode().unwrap_or(TriggerMode::Edge)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Fixed(u8),
+    NonMaskable
+}
 
-use super::vectors::Vector;
 
 pub struct IOAPIC {
-    registers: Mutex<&'static mut Registers>
+    id:             u8,
+    interrupt_base: u32,
+    registers:      Mutex<&'static mut Registers>
 }
 
 impl IOAPIC {
-    const BASE: u64 = 0xFEC00000;
+    const DEFAULT_BASE: u64 = 0xFEC00000;
+
+    // This function is unsafe because the caller must ensure there's an I/O APIC at the given
+    // address, and that nothing else accesses it.
+    pub unsafe fn new(address: PhysicalAddress, id: u8, interrupt_base: u32) -> IOAPIC {
+        IOAPIC {
+            id,
+            interrupt_base,
+            registers: Mutex::new(&mut *address.to_virtual().as_mut_ptr())
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        self.id
+    }
 
-    pub unsafe fn get() -> IOAPIC {
-        IOAPIC { registers: Mutex::new(&mut *(IOAPIC::BASE as *mut _)) }
+    pub fn interrupt_base(&self) -> u32 {
+        self.interrupt_base
+    }
+
+    // Whether the given global system interrupt is one of this I/O APIC's inputs.
+    pub fn handles(&self, interrupt: u32) -> bool {
+        interrupt >= self.interrupt_base && interrupt - self.interrupt_base < self.redirections().count as u32
     }
 
     pub fn initialize(&self) {
@@ -23,12 +155,6 @@ impl IOAPIC {
         }
     }
 
-    pub fn enable(&self, index: u8, vector: Vector) {
-        if let Some(redirection) = self.redirection_at(index) {
-            redirection.enable(vector)
-        }
-    }
-
     fn redirections(&self) -> Redirections {
         Redirections::new(self)
     }
@@ -119,7 +245,8 @@ impl<'a> Redirections<'a> {
     fn new(owner: &IOAPIC) -> Redirections {
         Redirections {
             owner,
-            count: owner.register_at(0x01).get_bits(16..=23) as u8,
+            // The version register holds the index of the last redirection entry.
+            count: owner.register_at(0x01).get_bits(16..=23) as u8 + 1,
             index: 0
         }
     }
@@ -160,8 +287,20 @@ impl<'a> Redirection<'a> {
         }
     }
 
-    fn enable(&self, vector: Vector) {
-        self.lower.write(vector as u32)
+    fn route(&self, delivery: Delivery, polarity: Polarity, trigger_mode: TriggerMode, destination: u8) {
+        let mut lower =
+            match delivery {
+                Delivery::Fixed(vector) => vector as u32,
+                Delivery::NonMaskable => 0b100 << 8
+            };
+
+        lower.set_bit(13, polarity == Polarity::ActiveLow);
+        lower.set_bit(15, trigger_mode == TriggerMode::Level);
+
+        // Keep the input masked until the destination is in place.
+        self.disable();
+        self.upper.write((destination as u32) << 24);
+        self.lower.write(lower);
     }
 
     fn disable(&self) {
@@ -180,4 +319,16 @@ impl<'a> Redirection<'a> {
     pub fn vector(&self) -> u8 {
         self.lower.read() as u8
     }
+
+    pub fn polarity(&self) -> Polarity {
+        if self.lower.get_bit(13) { Polarity::ActiveLow } else { Polarity::ActiveHigh }
+    }
+
+    pub fn trigger_mode(&self) -> TriggerMode {
+        if self.lower.get_bit(15) { TriggerMode::Level } else { TriggerMode::Edge }
+    }
+
+    pub fn destination(&self) -> u8 {
+        self.upper.get_bits(24..=31) as u8
+    }
 }
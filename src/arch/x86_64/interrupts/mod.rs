@@ -1,8 +1,19 @@
 mod vectors;
-use vectors::Vector;
+pub use vectors::Vector;
+
+mod context;
+pub use context::Context;
+
+mod dispatch;
+pub use dispatch::{register, unregister, Handler, IRQ, Registration, Error};
+
+mod exceptions;
+pub use exceptions::Exception;
 
 mod handlers;
 
+pub mod timer;
+
 mod idt;
 use idt::InterruptDescriptorTable;
 
@@ -11,9 +22,13 @@ use pic::{ChainedPIC, PIC};
 
 mod apic;
 use apic::APIC;
+pub use apic::IPI;
 
 mod ioapic;
+use ioapic::IOAPICs;
+pub use ioapic::Interrupt;
 
+use crate::acpi::{self, MADT};
 use lazy_static::lazy_static;
 use spin::Mutex;
 
@@ -21,19 +36,18 @@ lazy_static! {
     static ref INTERRUPT_DESCRIPTOR_TABLE: InterruptDescriptorTable = {
         let mut table = InterruptDescriptorTable::new();
 
-        table.breakpoint.handle_with(self::handlers::breakpoint);
+        // Every vector, exceptions included, goes through the same entry stubs and dispatcher.
+        for number in 0..=255 {
+            let vector = Vector::new(number);
+            unsafe { table.handle_at(vector, dispatch::stub(vector)); }
+        }
 
+        // A double fault often means the stack overflowed, so it gets a stack of its own.
         unsafe {
-            table.double_fault.handle_with(self::handlers::double_fault)
+            table.handle_at(Exception::DoubleFault.vector(), dispatch::stub(Exception::DoubleFault.vector()))
                 .on_stack_with_index(super::memory::segmentation::DOUBLE_FAULT_STACK_INDEX);
         }
 
-        table.general_protection_fault.handle_with(self::handlers::general_protection_fault);
-        table.page_fault.handle_with(self::handlers::page_fault);
-
-        table[Vector::Timer].handle_with(self::handlers::timer);
-        table[Vector::Keyboard].handle_with(self::handlers::keyboard);
-
         table
     };
 
@@ -44,17 +58,57 @@ lazy_static! {
         );
 
     static ref LAPIC: Mutex<&'static mut APIC> = Mutex::new(unsafe { APIC::get() });
-    static ref IOAPIC: ioapic::IOAPIC = unsafe { ioapic::IOAPIC::get() };
+    static ref IOAPICS: IOAPICs = unsafe { IOAPICs::discover(acpi::find::<MADT>()) };
 }
 
 pub(super) fn initialize() {
     INTERRUPT_DESCRIPTOR_TABLE.load();
 
     PICS.disable();
-    LAPIC.lock().initialize();
 
-    IOAPIC.initialize();
-    IOAPIC.enable(1, Vector::Keyboard);
+    let destination = {
+        let mut lapic = LAPIC.lock();
+
+        if let Some(madt) = acpi::find::<MADT>() {
+            lapic.configure_nmis(madt);
+        }
+
+        lapic.id()
+    };
+
+    IOAPICS.initialize(destination);
+
+    register(IRQ::Exception(Exception::Breakpoint), handlers::breakpoint).expect("failed to register the breakpoint handler");
+
+    let registration = register(IRQ::Local, handlers::timer).expect("failed to register the timer handler");
+    timer::initialize(registration.vector());
+
+    register(IRQ::ISA(1), handlers::keyboard).expect("failed to register the keyboard handler");
+}
+
+// Loads the IDT and enables the local APIC on an application processor. Devices only interrupt
+// the bootstrap processor, so there's nothing to route.
+pub(super) fn initialize_application_processor() {
+    INTERRUPT_DESCRIPTOR_TABLE.load();
+
+    let mut lapic = LAPIC.lock();
+    lapic.enable(Vector::SPURIOUS);
+
+    if let Some(madt) = acpi::find::<MADT>() {
+        lapic.configure_nmis(madt);
+    }
+}
+
+// Returns the ID of the running processor's local APIC. This doesn't take the lock, since a
+// faulting processor might already hold it.
+pub(super) fn local_apic_id() -> u8 {
+    // This is safe because reading the ID register changes nothing.
+    unsafe { APIC::get() }.id()
+}
+
+// Sends an interprocessor interrupt to the processor with the given local APIC ID.
+pub(super) fn send(destination: u8, ipi: IPI) {
+    suppress(|| LAPIC.lock().send(destination, ipi))
 }
 
 pub(super) fn enable() {
@@ -93,10 +147,28 @@ fn acknowledge() {
 mod tests {
     use super::*;
 
+    use crate::acpi::madt::{Polarity, TriggerMode};
+
     #[test]
     fn enabling_keyboard_interrupts() {
-        let redirection = IOAPIC.redirection_at(1).unwrap();
+        let redirection = IOAPICS.redirection_for(IOAPICS.legacy(1).number).unwrap();
         assert!(redirection.is_enabled());
-        assert_eq!(Vector::Keyboard as u8, redirection.vector());
+        assert!(redirection.vector() >= Vector::FIRST_DEVICE.number());
+        assert_eq!(LAPIC.lock().id(), redirection.destination());
+        assert_eq!(Polarity::ActiveHigh, redirection.polarity());
+        assert_eq!(TriggerMode::Edge, redirection.trigger_mode());
+    }
+
+    #[test]
+    fn finding_the_io_apics() {
+        assert!(!IOAPICS.controllers().is_empty());
+        assert_eq!(0, IOAPICS.controllers()[0].interrupt_base());
+    }
+
+    #[test]
+    fn applying_interrupt_source_overrides() {
+        // QEMU wires the PIT, ISA IRQ 0, to input 2, like most PCs.
+        assert_eq!(2, IOAPICS.legacy(0).number);
+        assert_eq!(1, IOAPICS.legacy(1).number);
     }
 }
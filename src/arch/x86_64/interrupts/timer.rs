@@ -0,0 +1,238 @@
+// The local APIC timer, calibrated at boot against the HPET or, failing that, the PIT. It ticks
+// periodically by default, and can be rearmed for a one-shot interrupt or a TSC deadline instead.
+
+use super::{Vector, LAPIC, suppress, apic::TimerMode};
+use crate::arch::x86_64::{time::{self, pit}, instructions::{rdtsc, wrmsrq, cpuid}};
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use log::info;
+use spin::RwLock;
+
+// How often the timer ticks by default.
+pub const HZ: u64 = 100;
+
+// Each timer tick is this many bus clock cycles.
+const DIVISOR: u32 = 16;
+
+// How long to measure the timer and TSC for. Longer is more accurate, but slows down booting.
+const CALIBRATION_PERIOD: u64 = 10_000_000;
+
+const IA32_TSC_DEADLINE_MSR: u32 = 0x6E0;
+
+static TIMER: RwLock<Option<Timer>> = RwLock::new(None);
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // Interrupt every interval until rearmed.
+    Periodic,
+
+    // Interrupt once, after the interval.
+    OneShot,
+
+    // Interrupt once, when the TSC reaches the time the interval from now. Only some CPUs can.
+    Deadline
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Uncalibrated,
+    Unsupported,
+    OutOfRange
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::Uncalibrated => write!(formatter, "the timer hasn't been calibrated"),
+            Error::Unsupported => write!(formatter, "the CPU doesn't support TSC deadlines"),
+            Error::OutOfRange => write!(formatter, "the interval is too long for the timer")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Timer {
+    vector:        Vector,
+    frequency:     u64,
+    tsc_frequency: u64,
+    deadline:      bool
+}
+
+// Measures the timer and starts it ticking HZ times a second, interrupting on the given vector.
+pub fn initialize(vector: Vector) {
+    let (frequency, tsc_frequency) = suppress(|| {
+        let mut lapic = LAPIC.lock();
+
+        lapic.set_timer_divisor(DIVISOR);
+        lapic.set_timer(vector, TimerMode::OneShot, true);
+
+        // Count down from as high as possible while the reference clock measures a known interval.
+        let (elapsed, tsc) = match time::hpet() {
+            Some(hpet) => measure(&mut lapic, || hpet.wait(CALIBRATION_PERIOD)),
+            None => measure(&mut lapic, || pit::wait(CALIBRATION_PERIOD))
+        };
+
+        lapic.start_timer(0);
+
+        (elapsed as u64 * 1_000_000_000 / CALIBRATION_PERIOD, tsc * 1_000_000_000 / CALIBRATION_PERIOD)
+    });
+
+    // CPUID.01H:ECX[24] says whether the timer has a TSC-deadline mode.
+    let deadline = cpuid(1, 0)[2] & (1 << 24) != 0;
+
+    info!(
+        "Local APIC timer: {} kHz, TSC: {} kHz{}",
+        frequency / 1000, tsc_frequency / 1000, if deadline { ", with deadlines" } else { "" }
+    );
+
+    TIMER.write().replace(Timer { vector, frequency, tsc_frequency, deadline });
+    arm(Mode::Periodic, 1_000_000_000 / HZ).expect("failed to start the timer");
+}
+
+// Returns how many timer counts and TSC cycles pass while the reference clock waits.
+fn measure(lapic: &mut super::APIC, wait: impl FnOnce()) -> (u32, u64) {
+    lapic.start_timer(u32::MAX);
+    let start = rdtsc();
+
+    wait();
+
+    let tsc = rdtsc() - start;
+    (u32::MAX - lapic.timer_count(), tsc)
+}
+
+// Arms the timer to interrupt after the given number of nanoseconds, replacing whatever it was
+// doing before.
+pub fn arm(mode: Mode, nanoseconds: u64) -> Result<(), Error> {
+    let timer = (*TIMER.read()).ok_or(Error::Uncalibrated)?;
+
+    suppress(|| {
+        let mut lapic = LAPIC.lock();
+
+        match mode {
+            Mode::Periodic | Mode::OneShot => {
+                let count = count(nanoseconds, timer.frequency).ok_or(Error::OutOfRange)?;
+                let mode = if mode == Mode::Periodic { TimerMode::Periodic } else { TimerMode::OneShot };
+
+                lapic.set_timer(timer.vector, mode, false);
+                lapic.start_timer(count);
+            }
+
+            Mode::Deadline => {
+                if !timer.deadline {
+                    return Err(Error::Unsupported)
+                }
+
+                let cycles = (nanoseconds as u128 * timer.tsc_frequency as u128 / 1_000_000_000) as u64;
+
+                lapic.set_timer(timer.vector, TimerMode::Deadline, false);
+
+                // Intel asks for a fence between switching the mode and setting the deadline.
+                core::sync::atomic::fence(Ordering::SeqCst);
+                unsafe { wrmsrq(IA32_TSC_DEADLINE_MSR, rdtsc() + cycles.max(1)) }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+// Stops the timer. Ticks stop until it's armed again.
+pub fn disarm() {
+    suppress(|| {
+        let timer = match *TIMER.read() {
+            Some(timer) => timer,
+            None => return
+        };
+
+        let mut lapic = LAPIC.lock();
+
+        lapic.set_timer(timer.vector, TimerMode::OneShot, true);
+        lapic.start_timer(0);
+
+        if timer.deadline {
+            unsafe { wrmsrq(IA32_TSC_DEADLINE_MSR, 0) }
+        }
+    })
+}
+
+// Returns how fast the TSC counts, as measured along with the timer.
+pub fn tsc_frequency() -> Option<u64> {
+    TIMER.read().map(|timer| timer.tsc_frequency)
+}
+
+// Returns how many times the timer has gone off since boot.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+pub(super) fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+// Converts an interval into timer counts, rounding up so it's never short.
+fn count(nanoseconds: u64, frequency: u64) -> Option<u32> {
+    let count = (nanoseconds as u128 * frequency as u128 + 999_999_999) / 1_000_000_000;
+    core::convert::TryFrom::try_from(count.max(1)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::halt;
+
+    fn wait_for_tick() {
+        let start = ticks();
+
+        while ticks() == start {
+            halt();
+        }
+    }
+
+    #[test]
+    fn converting_intervals_to_counts() {
+        assert_eq!(Some(625_000), count(10_000_000, 62_500_000));
+        assert_eq!(Some(1), count(1, 62_500_000));
+        assert_eq!(Some(1), count(0, 62_500_000));
+        assert_eq!(None, count(100_000_000_000, 62_500_000));
+    }
+
+    #[test]
+    fn calibrating_the_timer() {
+        let timer = (*TIMER.read()).unwrap();
+        assert!(timer.frequency > 0);
+        assert!(timer.tsc_frequency > 0);
+    }
+
+    #[test]
+    fn ticking_periodically() {
+        wait_for_tick();
+        wait_for_tick();
+    }
+
+    #[test]
+    fn firing_once() {
+        arm(Mode::OneShot, 1_000_000).unwrap();
+        wait_for_tick();
+
+        match arm(Mode::Deadline, 1_000_000) {
+            Ok(()) => wait_for_tick(),
+            Err(error) => assert_eq!(Error::Unsupported, error)
+        }
+
+        // Nothing fires once it's disarmed, even well after it would have.
+        arm(Mode::OneShot, 1_000_000).unwrap();
+        disarm();
+
+        let start = ticks();
+        let end = rdtsc() + tsc_frequency().unwrap() / 100;
+
+        while rdtsc() < end {
+            core::hint::spin_loop();
+        }
+
+        assert_eq!(start, ticks());
+
+        arm(Mode::Periodic, 1_000_000_000 / HZ).unwrap();
+    }
+}
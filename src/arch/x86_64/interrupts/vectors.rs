@@ -1,6 +1,99 @@
-#[repr(u8)]
-#[derive(Debug)]
-pub enum Vector {
-    Timer = 32,
-    Keyboard
+// An entry in the interrupt descriptor table. The first 32 are the CPU's exceptions; the rest are
+// handed out to devices as they ask for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Vector(u8);
+
+impl Vector {
+    // The first vector that isn't reserved for an exception.
+    pub const FIRST_DEVICE: Vector = Vector(32);
+
+    // The local APIC delivers spurious interrupts here, which it resets to. They mustn't be
+    // acknowledged, so the vector is never handed out.
+    pub const SPURIOUS: Vector = Vector(255);
+
+    pub fn new(number: u8) -> Vector {
+        Vector(number)
+    }
+
+    pub fn number(self) -> u8 {
+        self.0
+    }
+}
+
+impl core::fmt::Display for Vector {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
+
+// Keeps track of which device vectors are in use, one bit per vector.
+pub struct Vectors {
+    used: [u64; 4]
+}
+
+impl Vectors {
+    pub fn new() -> Vectors {
+        let mut vectors = Vectors { used: [0; 4] };
+
+        for number in 0..Vector::FIRST_DEVICE.0 {
+            vectors.set(Vector(number), true);
+        }
+
+        vectors.set(Vector::SPURIOUS, true);
+        vectors
+    }
+
+    // Hands out the lowest free vector. The local APIC prioritizes interrupts by the upper four
+    // bits of the vector, so earlier claims get lower priority.
+    pub fn allocate(&mut self) -> Option<Vector> {
+        let vector = (0..=255).map(Vector).find(|&vector| !self.is_used(vector))?;
+        self.set(vector, true);
+        Some(vector)
+    }
+
+    pub fn free(&mut self, vector: Vector) {
+        assert!(vector >= Vector::FIRST_DEVICE && vector != Vector::SPURIOUS, "vector {} isn't a device vector", vector);
+        self.set(vector, false)
+    }
+
+    pub fn is_used(&self, vector: Vector) -> bool {
+        self.used[vector.0 as usize / 64] & (1 << (vector.0 % 64)) != 0
+    }
+
+    fn set(&mut self, vector: Vector, used: bool) {
+        let word = &mut self.used[vector.0 as usize / 64];
+
+        if used {
+            *word |= 1 << (vector.0 % 64);
+        } else {
+            *word &= !(1 << (vector.0 % 64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocating_vectors() {
+        let mut vectors = Vectors::new();
+
+        assert_eq!(Some(Vector(32)), vectors.allocate());
+        assert_eq!(Some(Vector(33)), vectors.allocate());
+
+        vectors.free(Vector(32));
+        assert_eq!(Some(Vector(32)), vectors.allocate());
+        assert_eq!(Some(Vector(34)), vectors.allocate());
+    }
+
+    #[test]
+    fn running_out_of_vectors() {
+        let mut vectors = Vectors::new();
+
+        // Everything from 32 up to, but not including, the spurious vector.
+        assert_eq!(223, core::iter::from_fn(|| vectors.allocate()).count());
+        assert!(vectors.is_used(Vector::SPURIOUS));
+        assert_eq!(None, vectors.allocate());
+    }
 }
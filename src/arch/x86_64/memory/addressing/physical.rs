@@ -1,6 +1,8 @@
 use bit_field::BitField;
 
-use core::ops::Add;
+use core::ops::{Add, Sub};
+use crate::util::alignment::{align_up, align_down};
+use crate::arch::x86_64::memory::{VirtualAddress, PHYSICAL_MEMORY_OFFSET};
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
@@ -17,6 +19,31 @@ impl PhysicalAddress {
             _ => Err(())
         }
     }
+
+    pub const fn zero() -> PhysicalAddress {
+        PhysicalAddress(0)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub fn align_up(self, alignment: usize) -> PhysicalAddress {
+        PhysicalAddress::new(align_up(self.0 as usize, alignment) as u64)
+    }
+
+    pub fn align_down(self, alignment: usize) -> PhysicalAddress {
+        PhysicalAddress::new(align_down(self.0 as usize, alignment) as u64)
+    }
+
+    pub fn is_aligned(self, alignment: usize) -> bool {
+        self.align_down(alignment) == self
+    }
+
+    // Returns the address through which the kernel reaches this one in the direct physical map.
+    pub fn to_virtual(self) -> VirtualAddress {
+        VirtualAddress::new(PHYSICAL_MEMORY_OFFSET + self.0)
+    }
 }
 
 impl Add<u64> for PhysicalAddress {
@@ -35,8 +62,46 @@ impl Add<usize> for PhysicalAddress {
     }
 }
 
+impl Sub<PhysicalAddress> for PhysicalAddress {
+    type Output = usize;
+
+    fn sub(self, other: PhysicalAddress) -> Self::Output {
+        (self.0 - other.0) as usize
+    }
+}
+
 impl core::fmt::LowerHex for PhysicalAddress {
     fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         self.0.fmt(formatter)
     }
 }
+
+impl core::fmt::Debug for PhysicalAddress {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "PhysicalAddress({:#x})", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligning_a_physical_address() {
+        assert_eq!(PhysicalAddress(0x101000), PhysicalAddress(0x100001).align_up(0x1000));
+        assert_eq!(PhysicalAddress(0x100000), PhysicalAddress(0x100fff).align_down(0x1000));
+
+        assert!(PhysicalAddress(0x200000).is_aligned(0x200000));
+        assert!(!PhysicalAddress(0x201000).is_aligned(0x200000));
+    }
+
+    #[test]
+    fn converting_a_physical_address_to_virtual() {
+        assert_eq!(VirtualAddress::new(0xffff8000fee00000), PhysicalAddress(0xfee00000).to_virtual());
+    }
+
+    #[test]
+    fn subtracting_physical_addresses() {
+        assert_eq!(0x1000, PhysicalAddress(0x101000) - PhysicalAddress(0x100000));
+    }
+}
@@ -1,8 +1,9 @@
 use bit_field::BitField;
 
 use core::ops::Add;
+use crate::arch::x86_64::memory::{PhysicalAddress, PHYSICAL_MEMORY_OFFSET};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct VirtualAddress(u64);
 
@@ -26,6 +27,33 @@ impl VirtualAddress {
     pub const fn zero() -> VirtualAddress {
         VirtualAddress(0)
     }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub fn as_ptr<T>(self) -> *const T {
+        self.0 as *const T
+    }
+
+    pub fn as_mut_ptr<T>(self) -> *mut T {
+        self.0 as *mut T
+    }
+
+    // Returns the physical address behind an address in the direct physical map.
+    pub fn to_physical(self) -> PhysicalAddress {
+        assert!(self.0 >= PHYSICAL_MEMORY_OFFSET, "{:?} isn't in the direct physical map", self);
+        PhysicalAddress::new(self.0 - PHYSICAL_MEMORY_OFFSET)
+    }
+
+    pub fn is_aligned(self, alignment: usize) -> bool {
+        self.0 & (alignment as u64 - 1) == 0
+    }
+
+    // Returns the index into the page table at the given level (1 through 4) for this address.
+    pub fn table_index(self, level: usize) -> usize {
+        self.0.get_bits((12 + 9 * (level - 1))..(21 + 9 * (level - 1))) as usize
+    }
 }
 
 impl<T> From<&T> for VirtualAddress {
@@ -115,6 +143,16 @@ mod tests {
         assert_eq!(VirtualAddress(0), VirtualAddress::zero())
     }
 
+    #[test]
+    fn getting_page_table_indices() {
+        let address = VirtualAddress::truncate(0xffff_8080_4020_1000);
+
+        assert_eq!(1, address.table_index(1));
+        assert_eq!(1, address.table_index(2));
+        assert_eq!(1, address.table_index(3));
+        assert_eq!(257, address.table_index(4));
+    }
+
     #[test]
     fn adding_to_a_virtual_address() {
         assert_eq!(VirtualAddress(0xfee00010), VirtualAddress(0xfee00000) + 0x10u64);
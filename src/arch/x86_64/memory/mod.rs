@@ -1,8 +1,55 @@
 mod addressing;
 pub(super) mod segmentation;
+pub mod paging;
 
 pub use addressing::{PhysicalAddress, VirtualAddress};
 
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+pub const FRAME_SIZE: usize = 4096;
+
+// The kernel image is linked to run in the top 2 GiB of the address space, this far above where
+// the bootloader loads it. Keep in sync with the linker script and boot/start.S.
+pub const KERNEL_OFFSET: u64 = 0xFFFF_FFFF_8000_0000;
+
+// All of physical memory is mapped linearly starting here, at the bottom of the upper half.
+pub const PHYSICAL_MEMORY_OFFSET: u64 = 0xFFFF_8000_0000_0000;
+
+// The kernel heap grows upward from the start of this range as it needs more memory.
+pub const KERNEL_HEAP: Range<u64> = 0xFFFF_E000_0000_0000..0xFFFF_E100_0000_0000;
+
 pub(super) fn initialize() {
     segmentation::initialize();
+    paging::initialize();
+}
+
+extern "C" {
+    static __kernel_start: u8;
+    static __kernel_end: u8;
+}
+
+// Where the kernel image starts in physical memory, or zero if it's where the linker script says.
+// Multiboot bootloaders load it at its link address, but Limine can put it anywhere.
+static KERNEL_IMAGE_START: AtomicU64 = AtomicU64::new(0);
+
+pub fn relocate_kernel_image(start: PhysicalAddress) {
+    KERNEL_IMAGE_START.store(start.as_u64(), Ordering::Relaxed);
+}
+
+// Returns the physical memory occupied by the kernel image.
+pub fn kernel_image() -> Range<PhysicalAddress> {
+    let (start, end) = unsafe { (&__kernel_start as *const u8 as u64, &__kernel_end as *const u8 as u64) };
+
+    let physical = match KERNEL_IMAGE_START.load(Ordering::Relaxed) {
+        0 => start - KERNEL_OFFSET,
+        physical => physical
+    };
+
+    PhysicalAddress::new(physical)..PhysicalAddress::new(physical + (end - start))
+}
+
+// Returns the virtual address the kernel image starts at, which is always its link address.
+pub fn kernel_base() -> VirtualAddress {
+    VirtualAddress::from(unsafe { &__kernel_start })
 }
@@ -0,0 +1,399 @@
+mod table;
+pub use table::{PageTable, PageTableEntry, Flags};
+
+use super::{PhysicalAddress, VirtualAddress, kernel_image, kernel_base};
+use crate::arch::x86_64::{instructions::invlpg, registers::CR3, interrupts::suppress};
+use crate::memory::{self, AllocationError, FRAME_SIZE, slab::{self, Cache}};
+
+use core::ops::Range;
+use spin::{Mutex, RwLock};
+
+static ADDRESS_SPACE: Mutex<Option<AddressSpace>> = Mutex::new(None);
+
+// Page tables come from a slab cache once there's a heap to create it with. The ones made before
+// then come straight from the frame allocator.
+static TABLES: RwLock<Option<&'static Cache>> = RwLock::new(None);
+
+// Memory-mapped device registers (the IOAPIC, HPET, and LAPIC) live just below 4 GiB.
+const DEVICE_MEMORY: Range<u64> = 0xFE000000..0x100000000;
+
+// Replaces the boot page tables with kernel-owned ones that map all of physical memory at
+// PHYSICAL_MEMORY_OFFSET and the kernel image at KERNEL_OFFSET. Nothing is identity-mapped.
+pub(in crate::arch::x86_64) fn initialize() {
+    let mut space = AddressSpace::new().expect("failed to allocate kernel page tables");
+
+    let size = PageSize::Size2MiB;
+    let end = memory::physical_memory_end().align_up(size.bytes()).as_u64().max(DEVICE_MEMORY.end);
+
+    for address in (0..end).step_by(size.bytes()) {
+        let frame = PhysicalAddress::new(address);
+
+        let flags =
+            if DEVICE_MEMORY.contains(&address) {
+                Flags::WRITABLE | Flags::NO_CACHE | Flags::WRITE_THROUGH
+            } else {
+                Flags::WRITABLE
+            };
+
+        space.map(frame.to_virtual(), frame, size, flags).expect("failed to map physical memory");
+    }
+
+    let image = kernel_image();
+    let offset = kernel_base().as_u64() - image.start.as_u64();
+
+    // Limine can load the kernel at an address that doesn't line up with its link address modulo
+    // 2 MiB, in which case it has to be mapped in smaller pages.
+    let size = if offset % size.bytes() as u64 == 0 { size } else { PageSize::Size4KiB };
+
+    for address in (image.start.align_down(size.bytes()).as_u64()..image.end.as_u64()).step_by(size.bytes()) {
+        space.map(VirtualAddress::new(offset + address), PhysicalAddress::new(address), size, Flags::WRITABLE)
+            .expect("failed to map the kernel image");
+    }
+
+    unsafe { space.activate() }
+
+    ADDRESS_SPACE.lock().replace(space);
+
+    let tables = slab::create("page tables", FRAME_SIZE, FRAME_SIZE, None);
+    suppress(|| TABLES.write().replace(tables));
+}
+
+// Maps a page in the kernel address space.
+pub fn map(page: VirtualAddress, frame: PhysicalAddress, size: PageSize, flags: Flags) -> Result<(), MappingError> {
+    with_address_space(|space| space.map(page, frame, size, flags))
+}
+
+pub fn unmap(page: VirtualAddress) -> Result<(PhysicalAddress, PageSize), MappingError> {
+    with_address_space(|space| space.unmap(page))
+}
+
+pub fn remap(page: VirtualAddress, flags: Flags) -> Result<PageSize, MappingError> {
+    with_address_space(|space| space.remap(page, flags))
+}
+
+pub fn translate(address: VirtualAddress) -> Option<PhysicalAddress> {
+    with_address_space(|space| space.translate(address))
+}
+
+fn with_address_space<F, R>(f: F) -> R where F: FnOnce(&mut AddressSpace) -> R {
+    suppress(|| f(ADDRESS_SPACE.lock().as_mut().expect("kernel page tables aren't initialized")))
+}
+
+
+pub struct AddressSpace {
+    root: PhysicalAddress
+}
+
+impl AddressSpace {
+    pub fn new() -> Result<AddressSpace, MappingError> {
+        Ok(AddressSpace { root: allocate_table()? })
+    }
+
+    // This function is unsafe because the returned address space aliases the active page tables.
+    // It is the caller’s responsibility to ensure nothing else modifies them concurrently.
+    pub unsafe fn active() -> AddressSpace {
+        AddressSpace { root: CR3::read() }
+    }
+
+    pub fn root(&self) -> PhysicalAddress {
+        self.root
+    }
+
+    pub fn is_active(&self) -> bool {
+        CR3::read() == self.root
+    }
+
+    // This function is unsafe because everything the kernel is using, including the code that's
+    // running and its stack, must be mapped in this address space.
+    pub unsafe fn activate(&self) {
+        CR3::write(self.root)
+    }
+
+    pub fn map(&mut self, page: VirtualAddress, frame: PhysicalAddress, size: PageSize, flags: Flags) -> Result<(), MappingError> {
+        if !page.is_aligned(size.bytes()) || !frame.is_aligned(size.bytes()) {
+            return Err(MappingError::Misaligned { page, frame, size })
+        }
+
+        let entry = self.create_entry(page, size.level(), flags)?;
+
+        if entry.is_present() {
+            Err(MappingError::AlreadyMapped(page))
+        } else {
+            entry.set(frame, size.flags(flags));
+            Ok(())
+        }
+    }
+
+    // Removes the mapping for the page at the given address, returning the frame it mapped. Page
+    // tables left empty by the removal aren't freed.
+    pub fn unmap(&mut self, page: VirtualAddress) -> Result<(PhysicalAddress, PageSize), MappingError> {
+        let (entry, size) = self.find_entry(page).ok_or(MappingError::NotMapped(page))?;
+
+        if !page.is_aligned(size.bytes()) {
+            return Err(MappingError::Misaligned { page, frame: entry.address(), size })
+        }
+
+        let frame = entry.address();
+        entry.clear();
+        self.flush(page);
+
+        Ok((frame, size))
+    }
+
+    // Changes the flags of the page containing the given address, returning the page's size.
+    pub fn remap(&mut self, page: VirtualAddress, flags: Flags) -> Result<PageSize, MappingError> {
+        let (entry, size) = self.find_entry(page).ok_or(MappingError::NotMapped(page))?;
+
+        // The tables on the way have to let user code through too.
+        if flags.contains(Flags::USER) {
+            self.create_entry(page, size.level(), flags)?;
+        }
+
+        entry.set_flags(size.flags(flags));
+        self.flush(page);
+
+        Ok(size)
+    }
+
+    pub fn translate(&self, address: VirtualAddress) -> Option<PhysicalAddress> {
+        self.find_entry(address).map(|(entry, size)| {
+            entry.address() + (address.as_u64() & (size.bytes() as u64 - 1))
+        })
+    }
+
+    // Returns the entry at the given level for the address, creating intermediate tables as needed.
+    // Intermediate tables are writable, leaving that to the leaf entry, but only let user code
+    // through on the way to a leaf with the given flags if those include USER.
+    fn create_entry(&mut self, address: VirtualAddress, level: usize, flags: Flags) -> Result<&'static mut PageTableEntry, MappingError> {
+        let mut table = unsafe { table_at(self.root) };
+        let user = flags & Flags::USER;
+
+        for current in ((level + 1)..=4).rev() {
+            let entry = &mut table[address.table_index(current)];
+
+            if !entry.is_present() {
+                entry.set(allocate_table()?, Flags::PRESENT | Flags::WRITABLE | user);
+            } else if entry.is_huge() {
+                return Err(MappingError::MappedByLargerPage(address))
+            } else if !entry.flags().contains(user) {
+                entry.set_flags(entry.flags() | user);
+            }
+
+            table = unsafe { table_at(entry.address()) };
+        }
+
+        Ok(&mut table[address.table_index(level)])
+    }
+
+    // Returns the leaf entry mapping the address and the size of the page it maps, if any.
+    fn find_entry(&self, address: VirtualAddress) -> Option<(&'static mut PageTableEntry, PageSize)> {
+        let mut table = unsafe { table_at(self.root) };
+
+        for level in (1..=4).rev() {
+            let entry = &mut table[address.table_index(level)];
+
+            if !entry.is_present() {
+                return None
+            }
+
+            if level == 1 || (level <= 3 && entry.is_huge()) {
+                return Some((entry, PageSize::at_level(level)))
+            }
+
+            table = unsafe { table_at(entry.address()) };
+        }
+
+        None
+    }
+
+    fn flush(&self, page: VirtualAddress) {
+        if self.is_active() {
+            unsafe { invlpg(page.as_u64()) }
+        }
+    }
+}
+
+fn allocate_table() -> Result<PhysicalAddress, MappingError> {
+    let frame = match *TABLES.read() {
+        Some(tables) => VirtualAddress::from(tables.allocate()?.as_ptr() as *const u8).to_physical(),
+        None => memory::allocate_frames(0)?
+    };
+
+    unsafe { table_at(frame).clear() }
+    Ok(frame)
+}
+
+// Page tables are reached through the direct physical map.
+unsafe fn table_at(address: PhysicalAddress) -> &'static mut PageTable {
+    &mut *address.to_virtual().as_mut_ptr()
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    Size4KiB,
+    Size2MiB,
+    Size1GiB
+}
+
+impl PageSize {
+    pub fn bytes(self) -> usize {
+        match self {
+            PageSize::Size4KiB => 0x1000,
+            PageSize::Size2MiB => 0x200000,
+            PageSize::Size1GiB => 0x40000000
+        }
+    }
+
+    // Returns the level of the page table whose entries map pages of this size.
+    fn level(self) -> usize {
+        match self {
+            PageSize::Size4KiB => 1,
+            PageSize::Size2MiB => 2,
+            PageSize::Size1GiB => 3
+        }
+    }
+
+    fn at_level(level: usize) -> PageSize {
+        match level {
+            1 => PageSize::Size4KiB,
+            2 => PageSize::Size2MiB,
+            3 => PageSize::Size1GiB,
+            _ => panic!("level {} page tables don't map pages", level)
+        }
+    }
+
+    // Returns the flags for a leaf entry mapping a page of this size.
+    fn flags(self, flags: Flags) -> Flags {
+        match self {
+            PageSize::Size4KiB => flags | Flags::PRESENT,
+            _ => flags | Flags::PRESENT | Flags::HUGE
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingError {
+    AlreadyMapped(VirtualAddress),
+    NotMapped(VirtualAddress),
+    MappedByLargerPage(VirtualAddress),
+    Misaligned { page: VirtualAddress, frame: PhysicalAddress, size: PageSize },
+    OutOfMemory(AllocationError)
+}
+
+impl From<AllocationError> for MappingError {
+    fn from(error: AllocationError) -> MappingError {
+        MappingError::OutOfMemory(error)
+    }
+}
+
+impl core::fmt::Display for MappingError {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            MappingError::AlreadyMapped(page) => write!(formatter, "{:?} is already mapped", page),
+            MappingError::NotMapped(page) => write!(formatter, "{:?} isn't mapped", page),
+            MappingError::MappedByLargerPage(page) => write!(formatter, "{:?} is inside a larger page", page),
+
+            MappingError::Misaligned { page, frame, size } => {
+                write!(formatter, "{:?} and {:?} must both be aligned to {:?}", page, frame, size)
+            }
+
+            MappingError::OutOfMemory(error) => write!(formatter, "couldn't allocate a page table: {}", error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translating_addresses_in_the_direct_map() {
+        assert_eq!(Some(PhysicalAddress::new(0xB8123)), translate(PhysicalAddress::new(0xB8123).to_virtual()));
+        assert_eq!(Some(PhysicalAddress::new(0xFEE000B0)), translate(PhysicalAddress::new(0xFEE000B0).to_virtual()));
+    }
+
+    #[test]
+    fn translating_addresses_in_the_kernel_image() {
+        assert_eq!(Some(kernel_image().start), translate(kernel_base()));
+    }
+
+    #[test]
+    fn leaving_low_memory_unmapped() {
+        assert_eq!(None, translate(VirtualAddress::new(0xB8000)));
+    }
+
+    #[test]
+    fn mapping_and_unmapping_a_page() {
+        let frame = memory::allocate_frames(0).unwrap();
+        let page = VirtualAddress::new(0x7000_0000_0000);
+
+        assert_eq!(Ok(()), map(page, frame, PageSize::Size4KiB, Flags::WRITABLE));
+        assert_eq!(Some(frame + 0x10usize), translate(page + 0x10usize));
+
+        unsafe {
+            page.as_mut_ptr::<u64>().write_volatile(42);
+            assert_eq!(42, frame.to_virtual().as_ptr::<u64>().read_volatile());
+        }
+
+        assert_eq!(Ok((frame, PageSize::Size4KiB)), unmap(page));
+        assert_eq!(None, translate(page));
+
+        memory::free_frames(frame, 0);
+    }
+
+    #[test]
+    fn mapping_a_page_twice() {
+        let page = VirtualAddress::new(0x7000_0020_0000);
+
+        assert_eq!(Ok(()), map(page, PhysicalAddress::new(0x200000), PageSize::Size2MiB, Flags::empty()));
+
+        assert_eq!(
+            Err(MappingError::AlreadyMapped(page)),
+            map(page, PhysicalAddress::new(0x400000), PageSize::Size2MiB, Flags::empty())
+        );
+
+        assert_eq!(
+            Err(MappingError::MappedByLargerPage(page + 0x1000usize)),
+            map(page + 0x1000usize, PhysicalAddress::new(0x400000), PageSize::Size4KiB, Flags::empty())
+        );
+
+        assert_eq!(Ok((PhysicalAddress::new(0x200000), PageSize::Size2MiB)), unmap(page));
+    }
+
+    #[test]
+    fn remapping_a_page() {
+        let page = VirtualAddress::new(0x7000_0040_0000);
+
+        assert_eq!(Ok(()), map(page, PhysicalAddress::new(0x200000), PageSize::Size4KiB, Flags::empty()));
+        assert_eq!(Ok(PageSize::Size4KiB), remap(page, Flags::WRITABLE));
+        assert_eq!(Ok((PhysicalAddress::new(0x200000), PageSize::Size4KiB)), unmap(page));
+    }
+
+    #[test]
+    fn only_letting_user_code_through_to_user_pages() {
+        let mut space = AddressSpace::new().unwrap();
+        let kernel = VirtualAddress::new(0x7000_0000_0000);
+        let user = VirtualAddress::new(0x0000_1000_0000);
+
+        // The flags of the top two intermediate entries on the way to the page.
+        let path = |space: &AddressSpace, page: VirtualAddress| unsafe {
+            let entry = &table_at(space.root())[page.table_index(4)];
+            (entry.flags(), table_at(entry.address())[page.table_index(3)].flags())
+        };
+
+        space.map(kernel, PhysicalAddress::new(0x200000), PageSize::Size4KiB, Flags::WRITABLE).unwrap();
+        let (first, second) = path(&space, kernel);
+        assert!(!first.contains(Flags::USER) && !second.contains(Flags::USER));
+
+        space.map(user, PhysicalAddress::new(0x200000), PageSize::Size4KiB, Flags::USER).unwrap();
+        let (first, second) = path(&space, user);
+        assert!(first.contains(Flags::USER) && second.contains(Flags::USER));
+
+        // Making a kernel page accessible opens up the way to it as well.
+        space.remap(kernel, Flags::USER).unwrap();
+        let (first, second) = path(&space, kernel);
+        assert!(first.contains(Flags::USER) && second.contains(Flags::USER));
+    }
+}
@@ -0,0 +1,130 @@
+use core::ops::{Index, IndexMut};
+use bitflags::bitflags;
+use crate::arch::x86_64::memory::PhysicalAddress;
+
+pub const ENTRY_COUNT: usize = 512;
+
+#[repr(C)]
+#[repr(align(4096))]
+pub struct PageTable {
+    entries: [PageTableEntry; ENTRY_COUNT]
+}
+
+impl PageTable {
+    pub fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.clear()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(|entry| entry.is_unused())
+    }
+}
+
+impl Index<usize> for PageTable {
+    type Output = PageTableEntry;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.entries[index]
+    }
+}
+
+impl IndexMut<usize> for PageTable {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.entries[index]
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct PageTableEntry(u64);
+
+impl PageTableEntry {
+    const ADDRESS_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+    pub fn address(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.0 & PageTableEntry::ADDRESS_MASK)
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(self.0)
+    }
+
+    pub fn set(&mut self, address: PhysicalAddress, flags: Flags) {
+        self.0 = (address.as_u64() & PageTableEntry::ADDRESS_MASK) | flags.bits()
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.set(self.address(), flags)
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.flags().contains(Flags::PRESENT)
+    }
+
+    pub fn is_huge(&self) -> bool {
+        self.flags().contains(Flags::HUGE)
+    }
+
+    pub fn is_unused(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = 0
+    }
+}
+
+impl core::fmt::Debug for PageTableEntry {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.debug_struct("PageTableEntry")
+            .field("address", &self.address())
+            .field("flags", &self.flags())
+            .finish()
+    }
+}
+
+bitflags! {
+    pub struct Flags: u64 {
+        const PRESENT       = 1;
+        const WRITABLE      = 1 << 1;
+        const USER          = 1 << 2;
+        const WRITE_THROUGH = 1 << 3;
+        const NO_CACHE      = 1 << 4;
+        const ACCESSED      = 1 << 5;
+        const DIRTY         = 1 << 6;
+        const HUGE          = 1 << 7;
+        const GLOBAL        = 1 << 8;
+
+        // Only valid once EFER.NXE is set. Otherwise, it's a reserved bit and faults.
+        const NO_EXECUTE    = 1 << 63;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::size_of;
+
+    #[test]
+    fn sizing() {
+        assert_eq!(size_of::<PageTableEntry>(), 8);
+        assert_eq!(size_of::<PageTable>(), 4096);
+    }
+
+    #[test]
+    fn setting_a_page_table_entry() {
+        let mut entry = PageTableEntry(0);
+        entry.set(PhysicalAddress::new(0x200000), Flags::PRESENT | Flags::WRITABLE | Flags::HUGE);
+
+        assert_eq!(PhysicalAddress::new(0x200000), entry.address());
+        assert!(entry.is_present());
+        assert!(entry.is_huge());
+
+        entry.set_flags(Flags::PRESENT);
+        assert_eq!(PhysicalAddress::new(0x200000), entry.address());
+        assert_eq!(Flags::PRESENT, entry.flags());
+    }
+}
@@ -2,31 +2,24 @@ mod gdt;
 use gdt::{GlobalDescriptorTable, Selector, Descriptor};
 
 use crate::arch::x86_64::{
-    multitasking::TaskStateSegment, memory::VirtualAddress, registers::* };
+    multitasking::TaskStateSegment, memory::{VirtualAddress, FRAME_SIZE}, registers::* };
 
+use alloc::boxed::Box;
 use lazy_static::lazy_static;
 
 pub const DOUBLE_FAULT_STACK_INDEX: u16 = 0;
 
+// How big each application processor's double fault stack is, in frames: 2^order.
+const DOUBLE_FAULT_STACK_ORDER: usize = 2;
+
 lazy_static! {
-    static ref GLOBAL_DESCRIPTOR_TABLE: (GlobalDescriptorTable, Selectors) = {
-        let mut table = GlobalDescriptorTable::new();
-        let code_selector = table.add(Descriptor::kernel_code_segment());
-        let task_state_segment_selector = table.add(Descriptor::task_state_segment(&TASK_STATE_SEGMENT));
-        (table, Selectors { code_selector, task_state_segment_selector })
-    };
+    static ref GLOBAL_DESCRIPTOR_TABLE: (GlobalDescriptorTable, Selectors) = global_descriptor_table(&TASK_STATE_SEGMENT);
 
     static ref TASK_STATE_SEGMENT: TaskStateSegment = {
-        let mut task_state_segment = TaskStateSegment::new();
-
-        task_state_segment.interrupt_stack_table[DOUBLE_FAULT_STACK_INDEX as usize] = {
-            const STACK_SIZE: usize = 16382;
-            const STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-
-            VirtualAddress::from(&STACK) + STACK_SIZE
-        };
+        const STACK_SIZE: usize = 16382;
+        const STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
 
-        task_state_segment
+        task_state_segment(VirtualAddress::from(&STACK) + STACK_SIZE)
     };
 }
 
@@ -36,12 +29,39 @@ struct Selectors {
 }
 
 pub fn initialize() {
-    GLOBAL_DESCRIPTOR_TABLE.0.load();
+    load(&GLOBAL_DESCRIPTOR_TABLE);
+}
+
+// Gives an application processor a GDT and TSS of its own. Loading a TSS marks it busy in the GDT,
+// so processors can't share them.
+pub fn initialize_application_processor() {
+    let stack = crate::memory::allocate_frames(DOUBLE_FAULT_STACK_ORDER).expect("failed to allocate a double fault stack");
+    let stack = stack.to_virtual() + (FRAME_SIZE << DOUBLE_FAULT_STACK_ORDER);
+
+    let task_state_segment = Box::leak(Box::new(task_state_segment(stack)));
+    load(Box::leak(Box::new(global_descriptor_table(task_state_segment))));
+}
+
+fn global_descriptor_table(task_state_segment: &'static TaskStateSegment) -> (GlobalDescriptorTable, Selectors) {
+    let mut table = GlobalDescriptorTable::new();
+    let code_selector = table.add(Descriptor::kernel_code_segment());
+    let task_state_segment_selector = table.add(Descriptor::task_state_segment(task_state_segment));
+    (table, Selectors { code_selector, task_state_segment_selector })
+}
+
+fn task_state_segment(double_fault_stack: VirtualAddress) -> TaskStateSegment {
+    let mut task_state_segment = TaskStateSegment::new();
+    task_state_segment.interrupt_stack_table[DOUBLE_FAULT_STACK_INDEX as usize] = double_fault_stack;
+    task_state_segment
+}
+
+fn load((table, selectors): &'static (GlobalDescriptorTable, Selectors)) {
+    table.load();
 
     unsafe {
-        CS::set(GLOBAL_DESCRIPTOR_TABLE.1.code_selector.into());
+        CS::set(selectors.code_selector.into());
         SS::invalidate();
-        TR::set(GLOBAL_DESCRIPTOR_TABLE.1.task_state_segment_selector.into());
+        TR::set(selectors.task_state_segment_selector.into());
     }
 }
 
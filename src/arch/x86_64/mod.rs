@@ -4,7 +4,12 @@ pub mod vga;
 pub mod memory;
 pub mod interrupts;
 
+pub mod serial;
+pub mod debugcon;
 pub mod test;
+pub mod backtrace;
+pub mod time;
+pub mod smp;
 
 mod instructions;
 use instructions::flags;
@@ -20,6 +25,9 @@ pub fn initialize() {
     memory::initialize();
     acpi::initialize();
     interrupts::initialize();
+    serial::apply_settings();
+    serial::enable_interrupts();
+    time::rtc::enable_interrupts();
     interrupts::enable();
 }
 
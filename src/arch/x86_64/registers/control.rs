@@ -1,4 +1,15 @@
-use crate::arch::x86_64::memory::VirtualAddress;
+use crate::arch::x86_64::memory::{VirtualAddress, PhysicalAddress};
+
+// Protection, paging and cache control flags
+pub struct CR0;
+
+impl CR0 {
+    pub fn read() -> u64 {
+        let value: u64;
+        unsafe { asm!("mov {}, cr0", out(reg) value, options(nomem, nostack)); }
+        value
+    }
+}
 
 // Page fault linear address
 pub struct CR2;
@@ -10,3 +21,30 @@ impl CR2 {
         VirtualAddress::new(value)
     }
 }
+
+// Page map level 4 table base address
+pub struct CR3;
+
+impl CR3 {
+    pub fn read() -> PhysicalAddress {
+        let value: u64;
+        unsafe { asm!("mov {}, cr3", out(reg) value, options(nomem, nostack)); }
+        PhysicalAddress::new(value & 0x000F_FFFF_FFFF_F000)
+    }
+
+    // Switches to the page tables rooted at the given address, flushing non-global TLB entries.
+    pub unsafe fn write(address: PhysicalAddress) {
+        asm!("mov cr3, {}", in(reg) address.as_u64(), options(nostack))
+    }
+}
+
+// Architectural extension flags, like PAE and SSE support
+pub struct CR4;
+
+impl CR4 {
+    pub fn read() -> u64 {
+        let value: u64;
+        unsafe { asm!("mov {}, cr4", out(reg) value, options(nomem, nostack)); }
+        value
+    }
+}
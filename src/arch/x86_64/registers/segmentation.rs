@@ -39,6 +39,48 @@ impl SS {
     }
 }
 
+// The data segment registers are only read, for register dumps. Long mode ignores DS and ES, and
+// FS and GS only matter for their bases.
+pub struct DS;
+
+impl DS {
+    pub fn get() -> u16 {
+        let selector: u16;
+        unsafe { asm!("mov {:x}, ds", out(reg) selector, options(nomem, nostack)); }
+        selector
+    }
+}
+
+pub struct ES;
+
+impl ES {
+    pub fn get() -> u16 {
+        let selector: u16;
+        unsafe { asm!("mov {:x}, es", out(reg) selector, options(nomem, nostack)); }
+        selector
+    }
+}
+
+pub struct FS;
+
+impl FS {
+    pub fn get() -> u16 {
+        let selector: u16;
+        unsafe { asm!("mov {:x}, fs", out(reg) selector, options(nomem, nostack)); }
+        selector
+    }
+}
+
+pub struct GS;
+
+impl GS {
+    pub fn get() -> u16 {
+        let selector: u16;
+        unsafe { asm!("mov {:x}, gs", out(reg) selector, options(nomem, nostack)); }
+        selector
+    }
+}
+
 pub struct TR;
 
 impl TR {
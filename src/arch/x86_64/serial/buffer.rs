@@ -0,0 +1,65 @@
+// A fixed-size queue of bytes between the interrupt handler and everyone else.
+pub struct Buffer<const N: usize> {
+    bytes:  [u8; N],
+    start:  usize,
+    length: usize
+}
+
+impl<const N: usize> Buffer<N> {
+    pub const fn new() -> Buffer<N> {
+        Buffer { bytes: [0; N], start: 0, length: 0 }
+    }
+
+    // Returns whether there was room for the byte.
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.length == N {
+            return false
+        }
+
+        self.bytes[(self.start + self.length) % N] = byte;
+        self.length += 1;
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.length == 0 {
+            return None
+        }
+
+        let byte = self.bytes[self.start];
+        self.start = (self.start + 1) % N;
+        self.length -= 1;
+        Some(byte)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queueing_bytes() {
+        let mut buffer = Buffer::<4>::new();
+
+        assert!(buffer.push(1));
+        assert!(buffer.push(2));
+        assert!(buffer.push(3));
+        assert_eq!(Some(1), buffer.pop());
+
+        // Wrap around the end.
+        assert!(buffer.push(4));
+        assert!(buffer.push(5));
+        assert!(!buffer.push(6));
+
+        assert_eq!(Some(2), buffer.pop());
+        assert_eq!(Some(3), buffer.pop());
+        assert_eq!(Some(4), buffer.pop());
+        assert_eq!(Some(5), buffer.pop());
+        assert_eq!(None, buffer.pop());
+        assert!(buffer.is_empty());
+    }
+}
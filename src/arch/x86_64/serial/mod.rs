@@ -0,0 +1,310 @@
+mod uart;
+use uart::{UART, Cause, InterruptEnable, FIFO_SIZE};
+pub use uart::{Settings, SettingsError};
+
+mod buffer;
+use buffer::Buffer;
+
+use super::interrupts::{self, IRQ, Context, suppress};
+use crate::cmdline::Parameter;
+use log::warn;
+use spin::Mutex;
+
+// The four standard PC serial ports. COM1 and COM3 share ISA IRQ 4, and COM2 and COM4 share IRQ 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum COM {
+    COM1,
+    COM2,
+    COM3,
+    COM4
+}
+
+impl COM {
+    const ALL: [COM; 4] = [COM::COM1, COM::COM2, COM::COM3, COM::COM4];
+
+    fn base(self) -> u16 {
+        match self {
+            COM::COM1 => 0x3F8,
+            COM::COM2 => 0x2F8,
+            COM::COM3 => 0x3E8,
+            COM::COM4 => 0x2E8
+        }
+    }
+
+    fn irq(self) -> u8 {
+        match self {
+            COM::COM1 | COM::COM3 => 4,
+            COM::COM2 | COM::COM4 => 3
+        }
+    }
+
+    fn port(self) -> &'static Mutex<Option<SerialPort>> {
+        &PORTS[self as usize]
+    }
+}
+
+impl core::fmt::Display for COM {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    NotPresent,
+    Settings(SettingsError)
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::NotPresent => write!(formatter, "there's no such serial port"),
+            Error::Settings(error) => write!(formatter, "{}", error)
+        }
+    }
+}
+
+// How COM1, the serial console's port, talks to the other end: `serial=9600,7e1`. Every port starts
+// out with the defaults, since they're set up before the command line is read.
+pub static SETTINGS: Parameter<Settings> = Parameter::new("serial", Settings::DEFAULT);
+
+// How many bytes each port holds on to in each direction.
+const BUFFER_SIZE: usize = 1024;
+
+static PORTS: [Mutex<Option<SerialPort>>; 4] = [Mutex::new(None), Mutex::new(None), Mutex::new(None), Mutex::new(None)];
+
+// Finds the serial ports and sets them up with the default settings. Until interrupts are enabled,
+// reads and writes poll.
+pub fn initialize() {
+    for &com in COM::ALL.iter() {
+        let mut uart = UART::new(com.base());
+
+        // This is safe because these ports are reserved for serial ports on PCs.
+        if unsafe { uart.probe() } && uart.configure(&Settings::DEFAULT).is_ok() {
+            com.port().lock().replace(SerialPort::new(uart));
+        }
+    }
+}
+
+// Takes interrupts from every port there is. From then on, received bytes are buffered until
+// they're read, and writes go out in the background.
+pub fn enable_interrupts() {
+    for &com in COM::ALL.iter() {
+        if suppress(|| com.port().lock().is_none()) {
+            continue
+        }
+
+        match interrupts::register(IRQ::ISA(com.irq()), move |_: &mut Context| handle(com)) {
+            Ok(_) => with_port(com, |port| {
+                port.uart.set_interrupts(InterruptEnable::RECEIVED | InterruptEnable::LINE_STATUS);
+                port.interrupts = true;
+            }).unwrap(),
+
+            Err(error) => warn!("Polling {}: {}", com, error)
+        }
+    }
+}
+
+// Switches COM1 over to the settings from the command line.
+pub fn apply_settings() {
+    match configure(COM::COM1, &SETTINGS.get()) {
+        Ok(()) | Err(Error::NotPresent) => (),
+        Err(error) => warn!("Keeping COM1's default settings: {}", error)
+    }
+}
+
+pub fn configure(com: COM, settings: &Settings) -> Result<(), Error> {
+    with_port(com, |port| {
+        port.flush();
+
+        let interrupts = port.uart.interrupts();
+        port.uart.configure(settings).map_err(Error::Settings)?;
+        port.uart.set_interrupts(interrupts);
+        Ok(())
+    })?
+}
+
+// Queues as many of the bytes as fit and returns how many did. Without interrupts, they're all
+// sent before returning.
+pub fn write(com: COM, bytes: &[u8]) -> Result<usize, Error> {
+    with_port(com, |port| port.write(bytes))
+}
+
+// Takes whatever's been received, up to the buffer's length, waiting for at least one byte.
+pub fn read_blocking(com: COM, buffer: &mut [u8]) -> Result<usize, Error> {
+    loop {
+        let (count, interrupts) = with_port(com, |port| (port.read(buffer), port.interrupts))?;
+
+        if count > 0 || buffer.is_empty() {
+            return Ok(count)
+        }
+
+        // Sleep until the next interrupt, which might be the one with our byte.
+        if interrupts && interrupts::enabled() {
+            super::halt();
+        } else {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+// Writes kernel output to COM1. This waits for the transmitter rather than queueing, so that
+// everything printed before a crash makes it out.
+pub fn print(args: core::fmt::Arguments) {
+    use core::fmt::Write;
+
+    suppress(|| {
+        if let Some(port) = COM::COM1.port().lock().as_mut() {
+            port.flush();
+            port.write_fmt(args).unwrap();
+        }
+    })
+}
+
+fn with_port<F, R>(com: COM, f: F) -> Result<R, Error> where F: FnOnce(&mut SerialPort) -> R {
+    suppress(|| com.port().lock().as_mut().map(f).ok_or(Error::NotPresent))
+}
+
+// Returns whether the port was asking for attention, since ports share IRQs.
+fn handle(com: COM) -> bool {
+    let mut port = com.port().lock();
+
+    let port = match port.as_mut() {
+        Some(port) => port,
+        None => return false
+    };
+
+    let mut handled = false;
+
+    while let Some(cause) = port.uart.cause() {
+        handled = true;
+
+        match cause {
+            Cause::LineStatus | Cause::Received | Cause::Timeout => port.receive(),
+            Cause::TransmitterEmpty => port.transmit(),
+            Cause::ModemStatus => port.uart.acknowledge_modem_status()
+        }
+    }
+
+    handled
+}
+
+struct SerialPort {
+    uart:       UART,
+    received:   Buffer<BUFFER_SIZE>,
+    outgoing:   Buffer<BUFFER_SIZE>,
+    interrupts: bool
+}
+
+impl SerialPort {
+    fn new(uart: UART) -> SerialPort {
+        SerialPort { uart, received: Buffer::new(), outgoing: Buffer::new(), interrupts: false }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> usize {
+        // Pick up anything the interrupt handler hasn't yet, or everything if it isn't running.
+        self.receive();
+
+        let mut count = 0;
+
+        while count < buffer.len() {
+            match self.received.pop() {
+                Some(byte) => buffer[count] = byte,
+                None => break
+            }
+
+            count += 1;
+        }
+
+        count
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> usize {
+        if !self.interrupts {
+            self.flush();
+            bytes.iter().for_each(|&byte| self.transmit_now(byte));
+            return bytes.len()
+        }
+
+        let count = bytes.iter().take_while(|&&byte| self.outgoing.push(byte)).count();
+        self.transmit();
+        count
+    }
+
+    // Moves received bytes into the buffer. Once it's full, newer bytes are dropped.
+    fn receive(&mut self) {
+        while let Some(byte) = self.uart.receive() {
+            self.received.push(byte);
+        }
+    }
+
+    // Hands the transmitter as much of the queue as it'll take, and asks to be told when it's
+    // done if there's more.
+    fn transmit(&mut self) {
+        if self.uart.can_transmit() {
+            for _ in 0..FIFO_SIZE {
+                match self.outgoing.pop() {
+                    Some(byte) => self.uart.transmit(byte),
+                    None => break
+                }
+            }
+        }
+
+        let mut interrupts = self.uart.interrupts();
+        interrupts.set(InterruptEnable::TRANSMITTER_EMPTY, !self.outgoing.is_empty());
+        self.uart.set_interrupts(interrupts);
+    }
+
+    // Sends everything queued, waiting for the transmitter.
+    fn flush(&mut self) {
+        while let Some(byte) = self.outgoing.pop() {
+            self.transmit_now(byte);
+        }
+
+        self.transmit();
+    }
+
+    fn transmit_now(&mut self, byte: u8) {
+        while !self.uart.can_transmit() {
+            core::hint::spin_loop();
+        }
+
+        self.uart.transmit(byte);
+    }
+}
+
+impl core::fmt::Write for SerialPort {
+    fn write_str(&mut self, string: &str) -> core::fmt::Result {
+        string.bytes().for_each(|byte| self.transmit_now(byte));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receiving_in_loopback_mode() {
+        with_port(COM::COM1, |port| port.uart.set_loopback(true)).unwrap();
+
+        assert_eq!(Ok(5), write(COM::COM1, b"hello"));
+
+        let mut buffer = [0; 5];
+        let mut received = 0;
+
+        while received < buffer.len() {
+            received += read_blocking(COM::COM1, &mut buffer[received..]).unwrap();
+        }
+
+        with_port(COM::COM1, |port| port.uart.set_loopback(false)).unwrap();
+
+        assert_eq!(b"hello", &buffer);
+    }
+
+    #[test]
+    fn rejecting_missing_ports() {
+        // QEMU only gives the tests one serial port.
+        assert_eq!(Err(Error::NotPresent), read_blocking(COM::COM4, &mut [0; 1]));
+    }
+}
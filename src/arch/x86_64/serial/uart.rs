@@ -0,0 +1,338 @@
+use crate::arch::x86_64::io::Port;
+use crate::cmdline::{Value, ValueError};
+use bitflags::bitflags;
+use core::convert::TryFrom;
+
+// Registers, as offsets from the port's base. With the divisor latch access bit set in the line
+// control register, the first two hold the baud rate divisor instead.
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const INTERRUPT_IDENTIFICATION: u16 = 2;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const MODEM_STATUS: u16 = 6;
+
+const DIVISOR_LATCH_ACCESS: u8 = 1 << 7;
+
+// Enable and clear both FIFOs, and interrupt once 14 bytes have arrived. Fewer arriving raise a
+// timeout interrupt instead, after four characters' worth of silence.
+const FIFO_SETTINGS: u8 = 0xC7;
+
+// How many bytes the transmitter takes at once, once it's empty.
+pub const FIFO_SIZE: usize = 16;
+
+// The UART divides this by the divisor to get the baud rate.
+const BASE_BAUD: u32 = 115_200;
+
+bitflags! {
+    pub struct InterruptEnable: u8 {
+        const RECEIVED          = 1 << 0;
+        const TRANSMITTER_EMPTY = 1 << 1;
+        const LINE_STATUS       = 1 << 2;
+        const MODEM_STATUS      = 1 << 3;
+    }
+}
+
+bitflags! {
+    struct LineStatus: u8 {
+        const DATA_READY        = 1 << 0;
+        const OVERRUN           = 1 << 1;
+        const PARITY_ERROR      = 1 << 2;
+        const FRAMING_ERROR     = 1 << 3;
+        const BREAK             = 1 << 4;
+        const TRANSMITTER_EMPTY = 1 << 5;
+        const IDLE              = 1 << 6;
+        const FIFO_ERROR        = 1 << 7;
+    }
+}
+
+bitflags! {
+    struct ModemControl: u8 {
+        const DATA_TERMINAL_READY = 1 << 0;
+        const REQUEST_TO_SEND     = 1 << 1;
+        const OUT1                = 1 << 2;
+
+        // Gates the UART's interrupt line on PCs.
+        const OUT2                = 1 << 3;
+
+        // Connects the transmitter to the receiver.
+        const LOOPBACK            = 1 << 4;
+    }
+}
+
+// Why the UART raised an interrupt, highest priority first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    LineStatus,
+    Received,
+    Timeout,
+    TransmitterEmpty,
+    ModemStatus
+}
+
+// How to talk to whatever's on the other end of the line. The baud rate has to divide 115200.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub baud:      u32,
+    pub data_bits: u8,
+    pub parity:    Parity,
+    pub stop_bits: StopBits
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    Mark,
+    Space
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two
+}
+
+impl Settings {
+    // 115200 baud, 8N1: what QEMU and most terminals expect.
+    pub const DEFAULT: Settings = Settings { baud: 115_200, data_bits: 8, parity: Parity::None, stop_bits: StopBits::One };
+
+    fn divisor(&self) -> Option<u16> {
+        if self.baud == 0 || BASE_BAUD % self.baud != 0 {
+            return None
+        }
+
+        u16::try_from(BASE_BAUD / self.baud).ok()
+    }
+
+    fn line_control(&self) -> u8 {
+        let parity = match self.parity {
+            Parity::None  => 0b000,
+            Parity::Odd   => 0b001,
+            Parity::Even  => 0b011,
+            Parity::Mark  => 0b101,
+            Parity::Space => 0b111
+        };
+
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 0,
+            StopBits::Two => 1
+        };
+
+        (self.data_bits - 5) | stop_bits << 2 | parity << 3
+    }
+}
+
+// On the command line, settings are a baud rate, optionally followed by the character format, as
+// in `9600` or `9600,7e1`: the data bits, then n, o, e, m, or s for the parity, then the stop bits.
+impl Value for Settings {
+    fn parse(value: Option<&str>) -> Result<Settings, ValueError> {
+        let mut parts = value.ok_or(ValueError::Missing)?.splitn(2, ',');
+        let baud = u32::parse(parts.next())?;
+
+        let format = match parts.next() {
+            Some(format) => format.as_bytes(),
+            None => return Ok(Settings { baud, ..Settings::DEFAULT })
+        };
+
+        let (data_bits, parity, stop_bits) = match *format {
+            [data_bits @ b'5'..=b'8', parity, stop_bits] => (data_bits - b'0', parity, stop_bits),
+            _ => return Err(ValueError::Invalid)
+        };
+
+        let parity = match parity {
+            b'n' => Parity::None,
+            b'o' => Parity::Odd,
+            b'e' => Parity::Even,
+            b'm' => Parity::Mark,
+            b's' => Parity::Space,
+            _ => return Err(ValueError::Invalid)
+        };
+
+        let stop_bits = match stop_bits {
+            b'1' => StopBits::One,
+            b'2' => StopBits::Two,
+            _ => return Err(ValueError::Invalid)
+        };
+
+        Ok(Settings { baud, data_bits, parity, stop_bits })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsError {
+    UnsupportedBaudRate,
+    UnsupportedDataBits
+}
+
+impl core::fmt::Display for SettingsError {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            SettingsError::UnsupportedBaudRate => write!(formatter, "the baud rate doesn't divide 115200"),
+            SettingsError::UnsupportedDataBits => write!(formatter, "characters must have 5 to 8 data bits")
+        }
+    }
+}
+
+// A National Semiconductor 16550 UART or a compatible, like every PC serial port since the 90s.
+pub struct UART {
+    base: u16
+}
+
+impl UART {
+    pub const fn new(base: u16) -> UART {
+        UART { base }
+    }
+
+    // Checks there's a working UART at all by having it send a byte to itself. Unsafe because
+    // something else could be at the port.
+    pub unsafe fn probe(&self) -> bool {
+        self.write(INTERRUPT_ENABLE, 0);
+        self.write(MODEM_CONTROL, ModemControl::LOOPBACK.bits());
+        self.write(DATA, 0xAE);
+
+        let present = self.read(DATA) == 0xAE;
+        self.write(MODEM_CONTROL, 0);
+        present
+    }
+
+    // Sets the line up, leaving every interrupt off.
+    pub fn configure(&mut self, settings: &Settings) -> Result<(), SettingsError> {
+        let divisor = settings.divisor().ok_or(SettingsError::UnsupportedBaudRate)?;
+
+        if !(5..=8).contains(&settings.data_bits) {
+            return Err(SettingsError::UnsupportedDataBits)
+        }
+
+        unsafe {
+            self.write(INTERRUPT_ENABLE, 0);
+
+            self.write(LINE_CONTROL, DIVISOR_LATCH_ACCESS);
+            self.write(DATA, divisor as u8);
+            self.write(INTERRUPT_ENABLE, (divisor >> 8) as u8);
+            self.write(LINE_CONTROL, settings.line_control());
+
+            self.write(FIFO_CONTROL, FIFO_SETTINGS);
+            self.write(MODEM_CONTROL, (ModemControl::DATA_TERMINAL_READY | ModemControl::REQUEST_TO_SEND | ModemControl::OUT2).bits());
+        }
+
+        Ok(())
+    }
+
+    pub fn set_interrupts(&mut self, interrupts: InterruptEnable) {
+        unsafe { self.write(INTERRUPT_ENABLE, interrupts.bits()) }
+    }
+
+    pub fn interrupts(&self) -> InterruptEnable {
+        InterruptEnable::from_bits_truncate(unsafe { self.read(INTERRUPT_ENABLE) })
+    }
+
+    #[cfg(test)]
+    pub fn set_loopback(&mut self, loopback: bool) {
+        unsafe {
+            let mut control = ModemControl::from_bits_truncate(self.read(MODEM_CONTROL));
+            control.set(ModemControl::LOOPBACK, loopback);
+            self.write(MODEM_CONTROL, control.bits());
+        }
+    }
+
+    // Returns why the UART wants attention, if it does. Reading it acknowledges a transmitter-empty
+    // interrupt; the others last until their cause is dealt with.
+    pub fn cause(&self) -> Option<Cause> {
+        let identification = unsafe { self.read(INTERRUPT_IDENTIFICATION) };
+
+        match identification & 0b1111 {
+            0b0110 => Some(Cause::LineStatus),
+            0b0100 => Some(Cause::Received),
+            0b1100 => Some(Cause::Timeout),
+            0b0010 => Some(Cause::TransmitterEmpty),
+            0b0000 => Some(Cause::ModemStatus),
+            _      => None
+        }
+    }
+
+    // Takes the next received byte, if there is one. Bytes that arrived garbled are dropped.
+    pub fn receive(&mut self) -> Option<u8> {
+        loop {
+            let status = self.line_status();
+
+            if !status.contains(LineStatus::DATA_READY) {
+                return None
+            }
+
+            let byte = unsafe { self.read(DATA) };
+
+            if !status.intersects(LineStatus::PARITY_ERROR | LineStatus::FRAMING_ERROR | LineStatus::BREAK) {
+                return Some(byte)
+            }
+        }
+    }
+
+    // Whether the transmitter, FIFO included, is empty.
+    pub fn can_transmit(&self) -> bool {
+        self.line_status().contains(LineStatus::TRANSMITTER_EMPTY)
+    }
+
+    pub fn transmit(&mut self, byte: u8) {
+        unsafe { self.write(DATA, byte) }
+    }
+
+    // Reading the modem status acknowledges its interrupt. Nothing here uses the modem lines.
+    pub fn acknowledge_modem_status(&mut self) {
+        unsafe { self.read(MODEM_STATUS); }
+    }
+
+    fn line_status(&self) -> LineStatus {
+        LineStatus::from_bits_truncate(unsafe { self.read(LINE_STATUS) })
+    }
+
+    unsafe fn read(&self, register: u16) -> u8 {
+        Port::new(self.base + register).read()
+    }
+
+    unsafe fn write(&self, register: u16, value: u8) {
+        Port::new(self.base + register).write(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_line_settings() {
+        assert_eq!(Some(1), Settings::DEFAULT.divisor());
+        assert_eq!(0b00_000_0_11, Settings::DEFAULT.line_control());
+
+        let settings = Settings { baud: 9600, data_bits: 7, parity: Parity::Even, stop_bits: StopBits::Two };
+        assert_eq!(Some(12), settings.divisor());
+        assert_eq!(0b00_011_1_10, settings.line_control());
+
+        assert_eq!(None, Settings { baud: 100_000, ..Settings::DEFAULT }.divisor());
+        assert_eq!(None, Settings { baud: 0, ..Settings::DEFAULT }.divisor());
+
+        // Too slow for the divisor latch, which only has 16 bits.
+        assert_eq!(Some(57_600), Settings { baud: 2, ..Settings::DEFAULT }.divisor());
+        assert_eq!(None, Settings { baud: 1, ..Settings::DEFAULT }.divisor());
+    }
+
+    #[test]
+    fn parsing_settings() {
+        assert_eq!(Ok(Settings { baud: 9600, ..Settings::DEFAULT }), Settings::parse(Some("9600")));
+
+        assert_eq!(
+            Ok(Settings { baud: 38_400, data_bits: 7, parity: Parity::Mark, stop_bits: StopBits::Two }),
+            Settings::parse(Some("38400,7m2"))
+        );
+
+        assert_eq!(Err(ValueError::Missing), Settings::parse(None));
+        assert_eq!(Err(ValueError::Invalid), Settings::parse(Some("fast")));
+        assert_eq!(Err(ValueError::Invalid), Settings::parse(Some("9600,9n1")));
+        assert_eq!(Err(ValueError::Invalid), Settings::parse(Some("9600,8x1")));
+        assert_eq!(Err(ValueError::Invalid), Settings::parse(Some("9600,8n3")));
+        assert_eq!(Err(ValueError::Invalid), Settings::parse(Some("9600,8n")));
+    }
+}
@@ -0,0 +1,297 @@
+// Bringing up the application processors, the CPUs besides the one the bootloader started. Each
+// starts in real mode in the trampoline, which takes it to long mode and into `main`, on a stack of
+// its own. Once it's set up, it parks, waiting for interrupts.
+
+use super::interrupts::{self, IPI, suppress};
+use super::memory::{PhysicalAddress, FRAME_SIZE, paging::{PageTable, Flags}};
+use super::registers::CR3;
+use crate::acpi::{self, MADT, madt::{Entry, LocalAPICFlags}};
+use crate::cmdline::Parameter;
+use crate::memory::AllocationError;
+use crate::time::{self, Duration, Instant};
+
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering};
+use log::{info, warn};
+use spin::RwLock;
+
+global_asm!(include_str!("trampoline.S"));
+
+// Leaves the application processors halted: `nosmp`.
+pub static NOSMP: Parameter<bool> = Parameter::new("nosmp", false);
+
+// The trampoline takes a page, and its page tables follow it: a PML4, a PDPT and a page directory.
+pub const TRAMPOLINE_SIZE: usize = 4 * FRAME_SIZE;
+
+// Startup IPIs give the page number to start at, so the trampoline has to be below 1 MiB. The first
+// page, with the real-mode interrupt vectors and the BIOS data area, is left alone.
+const LOW_MEMORY: Range<u64> = FRAME_SIZE as u64..0x100000;
+
+// How big each processor's stack is, in frames: 2^order. It's the same as the bootstrap
+// processor's.
+const STACK_ORDER: usize = 2;
+
+// How long to wait for a processor to come online before giving up on it.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+static ONLINE: RwLock<Vec<CPU>> = RwLock::new(Vec::new());
+
+// Where memory::initialize set aside room for the trampoline, if anywhere.
+static TRAMPOLINE: AtomicU64 = AtomicU64::new(0);
+
+// The kernel's page tables, for processors to switch to once they're out of the trampoline.
+static PAGE_TABLE_ROOT: AtomicU64 = AtomicU64::new(0);
+
+extern "C" {
+    static __trampoline_start: u8;
+    static __trampoline_parameters: u8;
+    static __trampoline_end: u8;
+    static __trampoline_relocations: u16;
+    static __trampoline_relocations_end: u16;
+}
+
+// What the trampoline needs for each processor. Keep in sync with trampoline.S.
+#[repr(C)]
+struct Parameters {
+    page_tables: u64,
+    stack:       u64,
+    entry:       u64,
+    cpu:         u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CPU {
+    // The order it came online in, starting from the bootstrap processor at 0.
+    pub index:   usize,
+    pub apic_id: u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    NoResponse,
+    Allocation(AllocationError)
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::NoResponse => write!(formatter, "it didn't come online"),
+            Error::Allocation(error) => write!(formatter, "failed to allocate its stack: {}", error)
+        }
+    }
+}
+
+// Starts every processor the MADT says is enabled, one at a time. This sleeps while waiting for
+// each one, so the timers have to be running.
+pub fn initialize() {
+    let bootstrap = interrupts::local_apic_id();
+    suppress(|| ONLINE.write().push(CPU { index: 0, apic_id: bootstrap }));
+
+    if NOSMP.get() {
+        info!("Leaving the other CPUs halted: nosmp");
+        return
+    }
+
+    // Processors with x2APIC IDs too big for a local APIC entry would need the x2APIC to start.
+    let processors: Vec<u8> = match acpi::find::<MADT>() {
+        Some(madt) => madt.entries().filter_map(|entry| match entry {
+            Entry::LocalAPIC { apic_id, flags, .. } if flags.contains(LocalAPICFlags::ENABLED) && apic_id != bootstrap => Some(apic_id),
+            _ => None
+        }).collect(),
+
+        None => Vec::new()
+    };
+
+    if processors.is_empty() {
+        return
+    }
+
+    let trampoline = match TRAMPOLINE.load(Ordering::Relaxed) {
+        0 => {
+            warn!("Leaving the other CPUs halted: no low memory for the trampoline");
+            return
+        }
+
+        address => PhysicalAddress::new(address)
+    };
+
+    unsafe { install_trampoline(trampoline) }
+
+    for apic_id in processors {
+        match start(trampoline, apic_id) {
+            Ok(()) => (),
+
+            // A processor that's late rather than dead would still read the trampoline's
+            // parameters, so they can't be changed for the next one.
+            Err(error @ Error::NoResponse) => {
+                warn!("Failed to start the CPU with local APIC {}: {}; starting no more", apic_id, error);
+                break
+            }
+
+            Err(error) => warn!("Failed to start the CPU with local APIC {}: {}", apic_id, error)
+        }
+    }
+
+    info!("{} CPUs online", count());
+}
+
+pub fn count() -> usize {
+    suppress(|| ONLINE.read().len())
+}
+
+// Sets aside room for the trampoline in the first of the available ranges with enough below 1 MiB,
+// and returns it. memory::initialize calls this before the first megabyte's reserved, with the
+// ranges nothing else, boot information included, is using.
+pub fn reserve(available: impl Iterator<Item = Range<PhysicalAddress>>) -> Option<Range<PhysicalAddress>> {
+    let range = find(available)?;
+    TRAMPOLINE.store(range.start.as_u64(), Ordering::Relaxed);
+    Some(range)
+}
+
+fn find(mut available: impl Iterator<Item = Range<PhysicalAddress>>) -> Option<Range<PhysicalAddress>> {
+    available.find_map(|range| {
+        let start = range.start.as_u64().max(LOW_MEMORY.start);
+        let end = start + TRAMPOLINE_SIZE as u64;
+
+        if end <= range.end.as_u64().min(LOW_MEMORY.end) {
+            Some(PhysicalAddress::new(start)..PhysicalAddress::new(end))
+        } else {
+            None
+        }
+    })
+}
+
+// Copies the trampoline to low memory, with page tables mapping it where it is, and the kernel's
+// half of the address space the same as now.
+unsafe fn install_trampoline(trampoline: PhysicalAddress) {
+    let start = &__trampoline_start as *const u8;
+    let length = &__trampoline_end as *const u8 as usize - start as usize;
+    assert!(length <= FRAME_SIZE, "the trampoline doesn't fit in a page");
+
+    let copy: *mut u8 = trampoline.to_virtual().as_mut_ptr();
+    core::ptr::copy_nonoverlapping(start, copy, length);
+
+    // Turn the offsets the trampoline's linked with into physical addresses.
+    let relocations = &__trampoline_relocations as *const u16;
+    let count = (&__trampoline_relocations_end as *const u16).offset_from(relocations) as usize;
+
+    for &offset in core::slice::from_raw_parts(relocations, count) {
+        let field = copy.add(offset as usize) as *mut u32;
+        field.write_unaligned(field.read_unaligned() + trampoline.as_u64() as u32);
+    }
+
+    let page_tables = trampoline + FRAME_SIZE;
+    let table = |index: usize| -> &'static mut PageTable {
+        &mut *(page_tables + index * FRAME_SIZE).to_virtual().as_mut_ptr()
+    };
+
+    let (pml4, pdpt, page_directory) = (table(0), table(1), table(2));
+    pml4.clear();
+    pdpt.clear();
+    page_directory.clear();
+
+    // Identity-map the first 2 MiB, which the trampoline's in.
+    page_directory[0].set(PhysicalAddress::zero(), Flags::PRESENT | Flags::WRITABLE | Flags::HUGE);
+    pdpt[0].set(page_tables + 2 * FRAME_SIZE, Flags::PRESENT | Flags::WRITABLE);
+    pml4[0].set(page_tables + FRAME_SIZE, Flags::PRESENT | Flags::WRITABLE);
+
+    // Share the kernel's PDPTs for the upper half, where the kernel image, its heap and all of
+    // physical memory are.
+    let root = CR3::read();
+    let kernel: &PageTable = &*root.to_virtual().as_ptr();
+
+    for index in 256..512 {
+        pml4[index] = kernel[index];
+    }
+
+    PAGE_TABLE_ROOT.store(root.as_u64(), Ordering::Relaxed);
+}
+
+// Starts a processor with the INIT-SIPI-SIPI sequence, and waits for it to come online.
+fn start(trampoline: PhysicalAddress, apic_id: u8) -> Result<(), Error> {
+    let index = count();
+    let stack = crate::memory::allocate_frames(STACK_ORDER).map_err(Error::Allocation)?;
+
+    unsafe {
+        let offset = &__trampoline_parameters as *const u8 as u64 - &__trampoline_start as *const u8 as u64;
+        let parameters: *mut Parameters = (trampoline + offset).to_virtual().as_mut_ptr();
+
+        parameters.write_volatile(Parameters {
+            page_tables: (trampoline + FRAME_SIZE).as_u64(),
+            stack:       (stack.to_virtual() + (FRAME_SIZE << STACK_ORDER)).as_u64(),
+            entry:       main as extern "C" fn(usize) -> ! as usize as u64,
+            cpu:         index as u64
+        });
+    }
+
+    // Intel's recommended timing: wait 10 ms after INIT, and send a second startup IPI in case the
+    // first went missing.
+    interrupts::send(apic_id, IPI::Init);
+    time::delay(10_000_000);
+
+    for _ in 0..2 {
+        interrupts::send(apic_id, IPI::Startup((trampoline.as_u64() / FRAME_SIZE as u64) as u8));
+        time::delay(200_000);
+    }
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+
+    while count() == index {
+        // It might still be on its way, so its stack can't be freed.
+        if Instant::now() >= deadline {
+            return Err(Error::NoResponse)
+        }
+
+        time::sleep(Duration::from_millis(1));
+    }
+
+    Ok(())
+}
+
+// Where application processors come out of the trampoline, still on its page tables.
+extern "C" fn main(index: usize) -> ! {
+    unsafe { CR3::write(PhysicalAddress::new(PAGE_TABLE_ROOT.load(Ordering::Relaxed))) }
+
+    super::memory::segmentation::initialize_application_processor();
+    interrupts::initialize_application_processor();
+
+    let cpu = CPU { index, apic_id: interrupts::local_apic_id() };
+    suppress(|| ONLINE.write().push(cpu));
+
+    info!("CPU {} online, with local APIC {}", cpu.index, cpu.apic_id);
+
+    interrupts::enable();
+    super::park();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_room_for_the_trampoline() {
+        let range = |start, end| PhysicalAddress::new(start)..PhysicalAddress::new(end);
+
+        // The first page is skipped, and the page tables have to fit too.
+        assert_eq!(Some(range(0x1000, 0x5000)), find(core::iter::once(range(0, 0x9F000))));
+        assert_eq!(Some(range(0x8000, 0xC000)), find([range(0x1000, 0x4000), range(0x8000, 0x9F000)].iter().cloned()));
+        assert_eq!(None, find([range(0x9D000, 0x9F000), range(0x100000, 0x8000000)].iter().cloned()));
+    }
+
+    #[test]
+    fn bringing_up_application_processors() {
+        let enabled = acpi::find::<MADT>().unwrap().entries().filter(|entry| match entry {
+            Entry::LocalAPIC { flags, .. } => flags.contains(LocalAPICFlags::ENABLED),
+            _ => false
+        }).count();
+
+        // The tests run with -smp 4.
+        assert_eq!(4, enabled);
+        assert_eq!(enabled, count());
+
+        let cpus = suppress(|| ONLINE.read().clone());
+        assert_eq!(interrupts::local_apic_id(), cpus[0].apic_id);
+        assert!(cpus.iter().enumerate().all(|(index, cpu)| cpu.index == index));
+    }
+}
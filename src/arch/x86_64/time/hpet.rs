@@ -0,0 +1,91 @@
+use crate::acpi;
+use crate::time::ClockSource;
+use core::ptr;
+
+// Register offsets.
+const CAPABILITIES: usize = 0x00;
+const CONFIGURATION: usize = 0x10;
+const MAIN_COUNTER: usize = 0xF0;
+
+const ENABLE: u64 = 1 << 0;
+const COUNTER_64_BIT: u64 = 1 << 13;
+
+// The High Precision Event Timer's main counter, which counts up at a fixed rate of at least 10 MHz
+// once it's enabled. It's cheap to read and reliable, so it's a good clock source. The comparators
+// aren't used.
+pub struct HPET {
+    registers: *mut u64,
+    period:    u64,
+    mask:      u64
+}
+
+// The registers are memory-mapped, so they can be read from anywhere.
+unsafe impl Send for HPET {}
+unsafe impl Sync for HPET {}
+
+impl HPET {
+    // Finds the timer block the ACPI tables describe and starts its counter.
+    pub fn find() -> Option<HPET> {
+        let table = acpi::find::<acpi::HPET>()?;
+        let registers: *mut u64 = table.base_address().to_virtual().as_mut_ptr();
+
+        // This is safe because the firmware put the timer's registers there.
+        unsafe {
+            let capabilities = ptr::read_volatile(registers.add(CAPABILITIES / 8));
+
+            // The period is in femtoseconds, and has to be nonzero and at most 100 ns.
+            let period = capabilities >> 32;
+            if period == 0 || period > 100_000_000 {
+                return None
+            }
+
+            let mask = if capabilities & COUNTER_64_BIT != 0 { u64::MAX } else { u32::MAX as u64 };
+
+            let configuration = ptr::read_volatile(registers.add(CONFIGURATION / 8));
+            ptr::write_volatile(registers.add(CONFIGURATION / 8), configuration | ENABLE);
+
+            Some(HPET { registers, period, mask })
+        }
+    }
+
+    // Returns the counter's rate in Hz.
+    pub fn frequency(&self) -> u64 {
+        1_000_000_000_000_000 / self.period
+    }
+
+    pub fn counter(&self) -> u64 {
+        unsafe { ptr::read_volatile(self.registers.add(MAIN_COUNTER / 8)) & self.mask }
+    }
+
+    // Busy-waits. 32-bit counters wrap every few minutes, so waits have to be shorter than that.
+    pub fn wait(&self, nanoseconds: u64) {
+        let ticks = (nanoseconds as u128 * 1_000_000 / self.period as u128) as u64;
+        let start = self.counter();
+
+        while self.counter().wrapping_sub(start) & self.mask < ticks {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl ClockSource for HPET {
+    fn name(&self) -> &'static str {
+        "hpet"
+    }
+
+    fn frequency(&self) -> u64 {
+        self.frequency()
+    }
+
+    fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    fn rating(&self) -> u32 {
+        250
+    }
+
+    fn read(&self) -> u64 {
+        self.counter()
+    }
+}
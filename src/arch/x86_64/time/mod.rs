@@ -0,0 +1,35 @@
+pub mod pit;
+pub mod hpet;
+mod pm_timer;
+mod tsc;
+pub mod rtc;
+
+use crate::time::ClockSource;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+pub use pit::wait;
+
+lazy_static! {
+    static ref PIT: pit::PIT = pit::PIT::start();
+    static ref HPET: Option<hpet::HPET> = hpet::HPET::find();
+    static ref PM_TIMER: Option<pm_timer::PMTimer> = pm_timer::PMTimer::find();
+    static ref TSC: Option<tsc::TSC> = tsc::TSC::find();
+}
+
+// Returns every clock source the machine has. The HPET and PM timer come from the ACPI tables, and
+// the TSC's rate from the local APIC timer's calibration, so this has to wait for both.
+pub fn clock_sources() -> Vec<&'static dyn ClockSource> {
+    let mut sources: Vec<&'static dyn ClockSource> = Vec::new();
+
+    sources.push(&*PIT);
+    sources.extend(HPET.as_ref().map(|hpet| hpet as &dyn ClockSource));
+    sources.extend(PM_TIMER.as_ref().map(|timer| timer as &dyn ClockSource));
+    sources.extend(TSC.as_ref().map(|tsc| tsc as &dyn ClockSource));
+
+    sources
+}
+
+pub fn hpet() -> Option<&'static hpet::HPET> {
+    HPET.as_ref()
+}
@@ -0,0 +1,96 @@
+use crate::arch::x86_64::io::Port;
+use crate::arch::x86_64::interrupts::suppress;
+use crate::time::ClockSource;
+use spin::Mutex;
+
+// The 8254 programmable interval timer, which every PC has, counting down at a fixed rate.
+pub const FREQUENCY: u64 = 1_193_182;
+
+const CHANNEL_0: u16 = 0x40;
+const CHANNEL_2: u16 = 0x42;
+const COMMAND: u16 = 0x43;
+
+// Bit 0 gates channel 2, bit 1 connects it to the speaker, and bit 5 reads its output.
+const CHANNEL_2_CONTROL: u16 = 0x61;
+
+// The channels share the command port.
+static COMMANDS: Mutex<()> = Mutex::new(());
+
+// Busy-waits for up to 54 ms, the longest channel 2 can count. It isn't wired to an interrupt, so
+// this works with interrupts off and doesn't disturb anything else.
+pub fn wait(nanoseconds: u64) {
+    let count = (nanoseconds as u128 * FREQUENCY as u128 / 1_000_000_000).max(1).min(0xFFFF) as u16;
+
+    suppress(|| unsafe {
+        let _commands = COMMANDS.lock();
+        let control = Port::new(CHANNEL_2_CONTROL);
+
+        // Open the gate, but keep the speaker quiet.
+        let value: u8 = control.read();
+        control.write(value & !0b10 | 0b01);
+
+        // Channel 2, low then high byte of the count, mode 0: raise the output on reaching zero.
+        Port::new(COMMAND).write(0b10_11_000_0u8);
+        Port::new(CHANNEL_2).write(count as u8);
+        Port::new(CHANNEL_2).write((count >> 8) as u8);
+
+        while control.read::<u8>() & (1 << 5) == 0 {
+            core::hint::spin_loop();
+        }
+    })
+}
+
+// Channel 0, counting down from 65536 over and over. It wraps around every 55 ms, and reading it
+// takes three slow port accesses, so it's the clock source of last resort. Its interrupt isn't
+// routed anywhere.
+pub struct PIT;
+
+impl PIT {
+    pub fn start() -> PIT {
+        suppress(|| unsafe {
+            let _commands = COMMANDS.lock();
+
+            // Channel 0, low then high byte of the count, mode 2: reload on reaching zero. A count
+            // of zero means 65536.
+            Port::new(COMMAND).write(0b00_11_010_0u8);
+            Port::new(CHANNEL_0).write(0u8);
+            Port::new(CHANNEL_0).write(0u8);
+        });
+
+        PIT
+    }
+}
+
+impl ClockSource for PIT {
+    fn name(&self) -> &'static str {
+        "pit"
+    }
+
+    fn frequency(&self) -> u64 {
+        FREQUENCY
+    }
+
+    fn mask(&self) -> u64 {
+        0xFFFF
+    }
+
+    fn rating(&self) -> u32 {
+        100
+    }
+
+    fn read(&self) -> u64 {
+        let count = suppress(|| unsafe {
+            let _commands = COMMANDS.lock();
+
+            // Latch channel 0's count, so the two halves match.
+            Port::new(COMMAND).write(0b00_00_0000u8);
+
+            let low: u8 = Port::new(CHANNEL_0).read();
+            let high: u8 = Port::new(CHANNEL_0).read();
+            (high as u16) << 8 | low as u16
+        });
+
+        // It counts down, and clocks count up.
+        0u16.wrapping_sub(count) as u64
+    }
+}
@@ -0,0 +1,67 @@
+use crate::acpi::{self, FADT, AddressSpace, fadt::Flags};
+use crate::arch::x86_64::io::Port;
+use crate::memory::PhysicalAddress;
+use crate::time::ClockSource;
+
+const FREQUENCY: u64 = 3_579_545;
+
+// The ACPI power management timer, a 24- or 32-bit counter the FADT points to. It's slow to read,
+// but it's been around since ACPI 1.0.
+pub struct PMTimer {
+    register: Register,
+    mask:     u64
+}
+
+enum Register {
+    Port(u16),
+    Memory(*const u32)
+}
+
+// The register is either a port or memory-mapped, so it can be read from anywhere.
+unsafe impl Send for PMTimer {}
+unsafe impl Sync for PMTimer {}
+
+impl PMTimer {
+    pub fn find() -> Option<PMTimer> {
+        let fadt = acpi::find::<FADT>()?;
+        let address = fadt.pm_timer()?;
+
+        let register = match address.address_space() {
+            AddressSpace::SystemIO => Register::Port(address.address() as u16),
+            AddressSpace::SystemMemory => Register::Memory(PhysicalAddress::new(address.address()).to_virtual().as_ptr()),
+            _ => return None
+        };
+
+        let mask = if fadt.flags().contains(Flags::EXTENDED_TIMER_VALUE) { 0xFFFF_FFFF } else { 0xFF_FFFF };
+
+        Some(PMTimer { register, mask })
+    }
+}
+
+impl ClockSource for PMTimer {
+    fn name(&self) -> &'static str {
+        "acpi_pm"
+    }
+
+    fn frequency(&self) -> u64 {
+        FREQUENCY
+    }
+
+    fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    fn rating(&self) -> u32 {
+        200
+    }
+
+    fn read(&self) -> u64 {
+        // This is safe because the FADT says the timer is there.
+        let value = match self.register {
+            Register::Port(number) => unsafe { Port::new(number).read::<u32>() },
+            Register::Memory(address) => unsafe { core::ptr::read_volatile(address) }
+        };
+
+        value as u64 & self.mask
+    }
+}
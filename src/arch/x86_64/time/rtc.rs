@@ -0,0 +1,348 @@
+// The MC146818 real-time clock in CMOS, which keeps the date and time while the machine's off. It
+// only counts whole seconds, so the wall clock reads it once at boot and counts on from there with
+// the monotonic clock. It can also interrupt at a time of day, on ISA IRQ 8.
+
+use crate::acpi::{self, FADT};
+use crate::arch::x86_64::interrupts::{self, IRQ, Context, suppress};
+use crate::arch::x86_64::io::Port;
+use crate::time::DateTime;
+
+use bitflags::bitflags;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+use log::warn;
+use spin::Mutex;
+
+// Writing a register's index to the first port selects it, and the second port reads or writes it.
+// The index port's top bit masks NMIs, and it's always left clear.
+const INDEX: u16 = 0x70;
+const DATA: u16 = 0x71;
+
+// Register indices.
+const SECONDS: u8 = 0x00;
+const SECONDS_ALARM: u8 = 0x01;
+const MINUTES: u8 = 0x02;
+const MINUTES_ALARM: u8 = 0x03;
+const HOURS: u8 = 0x04;
+const HOURS_ALARM: u8 = 0x05;
+const DAY: u8 = 0x07;
+const MONTH: u8 = 0x08;
+const YEAR: u8 = 0x09;
+const STATUS_A: u8 = 0x0A;
+const STATUS_B: u8 = 0x0B;
+const STATUS_C: u8 = 0x0C;
+
+// Status register A's top bit is set while the clock is updating.
+const UPDATE_IN_PROGRESS: u8 = 1 << 7;
+
+// In 12-hour mode, the hour's top bit is set after noon.
+const PM: u8 = 1 << 7;
+
+// An alarm register with its top two bits set matches any value.
+const ANY: u8 = 0xC0;
+
+bitflags! {
+    // Status register B.
+    struct Control: u8 {
+        const SET             = 1 << 7;
+        const PERIODIC        = 1 << 6;
+        const ALARM           = 1 << 5;
+        const UPDATE_ENDED    = 1 << 4;
+        const SQUARE_WAVE     = 1 << 3;
+        const BINARY          = 1 << 2;
+        const HOURS_24        = 1 << 1;
+        const DAYLIGHT_SAVING = 1 << 0;
+    }
+}
+
+bitflags! {
+    // Status register C, which says why the clock interrupted. Reading it acknowledges the
+    // interrupt, and the clock won't interrupt again until it has been.
+    struct Flags: u8 {
+        const INTERRUPT    = 1 << 7;
+        const PERIODIC     = 1 << 6;
+        const ALARM        = 1 << 5;
+        const UPDATE_ENDED = 1 << 4;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    NoInterrupts,
+    InvalidTime
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::NoInterrupts => write!(formatter, "the real-time clock's interrupt isn't available"),
+            Error::InvalidTime => write!(formatter, "there's no such time of day")
+        }
+    }
+}
+
+// When the alarm goes off. Fields left empty match any value, so an alarm with only the second set
+// goes off once a minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alarm {
+    pub hour:   Option<u8>,
+    pub minute: Option<u8>,
+    pub second: Option<u8>
+}
+
+// Selecting a register and then accessing it has to happen without anything else getting between.
+static CMOS: Mutex<()> = Mutex::new(());
+
+static INTERRUPTS: AtomicBool = AtomicBool::new(false);
+static ALARM: Mutex<Option<fn()>> = Mutex::new(None);
+
+lazy_static! {
+    // Where the century is kept. There's no standard place, so it's up to the FADT.
+    static ref CENTURY: Option<u8> = acpi::find::<FADT>().and_then(FADT::century_register);
+}
+
+// Takes the clock's interrupts, for alarms.
+pub fn enable_interrupts() {
+    match interrupts::register(IRQ::ISA(8), handle) {
+        Ok(_) => {
+            // Whatever the firmware left pending would otherwise stop any more interrupts.
+            suppress(|| unsafe {
+                let _cmos = CMOS.lock();
+                read_register(STATUS_C);
+            });
+
+            INTERRUPTS.store(true, Ordering::Relaxed);
+        }
+
+        Err(error) => warn!("No real-time clock interrupts: {}", error)
+    }
+}
+
+// Reads the date and time. The clock's taken to be in UTC, though Windows keeps it in local time.
+pub fn read() -> DateTime {
+    suppress(|| {
+        let _cmos = CMOS.lock();
+
+        // The registers don't agree with each other while the clock updates them, once a second.
+        // Reading twice and getting the same answer makes sure none of the reads caught it halfway.
+        let mut registers = unsafe { Registers::read() };
+
+        loop {
+            let again = unsafe { Registers::read() };
+
+            if again == registers {
+                break
+            }
+
+            registers = again;
+        }
+
+        let control = Control::from_bits_truncate(unsafe { read_register(STATUS_B) });
+        registers.decode(control)
+    })
+}
+
+// Calls the handler whenever the time matches the alarm, replacing any alarm there was before.
+pub fn set_alarm(alarm: Alarm, handler: fn()) -> Result<(), Error> {
+    if !INTERRUPTS.load(Ordering::Relaxed) {
+        return Err(Error::NoInterrupts)
+    }
+
+    if alarm.hour.map_or(false, |hour| hour > 23)
+        || alarm.minute.map_or(false, |minute| minute > 59)
+        || alarm.second.map_or(false, |second| second > 59)
+    {
+        return Err(Error::InvalidTime)
+    }
+
+    suppress(|| unsafe {
+        ALARM.lock().replace(handler);
+
+        let _cmos = CMOS.lock();
+        let control = Control::from_bits_truncate(read_register(STATUS_B));
+
+        write_register(HOURS_ALARM, alarm.hour.map_or(ANY, |hour| encode_hour(hour, control)));
+        write_register(MINUTES_ALARM, alarm.minute.map_or(ANY, |minute| encode(minute, control)));
+        write_register(SECONDS_ALARM, alarm.second.map_or(ANY, |second| encode(second, control)));
+
+        // Acknowledge any match from before, which would otherwise call the handler straight away.
+        read_register(STATUS_C);
+        update_control(|control| control.insert(Control::ALARM));
+    });
+
+    Ok(())
+}
+
+fn handle(_context: &mut Context) -> bool {
+    let flags = {
+        let _cmos = CMOS.lock();
+        Flags::from_bits_truncate(unsafe { read_register(STATUS_C) })
+    };
+
+    // Copy the handler out, so it can set another alarm itself.
+    let alarm = if flags.contains(Flags::ALARM) { *ALARM.lock() } else { None };
+
+    if let Some(handler) = alarm {
+        handler();
+    }
+
+    flags.contains(Flags::INTERRUPT)
+}
+
+// The date and time registers, as the clock keeps them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Registers {
+    second:  u8,
+    minute:  u8,
+    hour:    u8,
+    day:     u8,
+    month:   u8,
+    year:    u8,
+    century: Option<u8>
+}
+
+impl Registers {
+    // Waits for any update to finish, then reads the registers. An update can still start while
+    // they're being read.
+    unsafe fn read() -> Registers {
+        while read_register(STATUS_A) & UPDATE_IN_PROGRESS != 0 {
+            core::hint::spin_loop();
+        }
+
+        Registers {
+            second:  read_register(SECONDS),
+            minute:  read_register(MINUTES),
+            hour:    read_register(HOURS),
+            day:     read_register(DAY),
+            month:   read_register(MONTH),
+            year:    read_register(YEAR),
+            century: CENTURY.map(|index| read_register(index))
+        }
+    }
+
+    // Converts the registers from BCD or binary, and 12 or 24 hours, whichever the clock uses.
+    fn decode(&self, control: Control) -> DateTime {
+        let decode = |value: u8| if control.contains(Control::BINARY) { value } else { from_bcd(value) };
+
+        let mut hour = decode(self.hour & !PM);
+
+        if !control.contains(Control::HOURS_24) {
+            // Midnight is 12 AM, and noon 12 PM.
+            hour %= 12;
+
+            if self.hour & PM != 0 {
+                hour += 12;
+            }
+        }
+
+        // Without a century register, assume it's this century.
+        let century = self.century.map_or(20, decode) as u16;
+
+        DateTime {
+            year:   century * 100 + decode(self.year) as u16,
+            month:  decode(self.month),
+            day:    decode(self.day),
+            hour,
+            minute: decode(self.minute),
+            second: decode(self.second)
+        }
+    }
+}
+
+fn encode(value: u8, control: Control) -> u8 {
+    if control.contains(Control::BINARY) { value } else { to_bcd(value) }
+}
+
+fn encode_hour(hour: u8, control: Control) -> u8 {
+    if control.contains(Control::HOURS_24) {
+        return encode(hour, control)
+    }
+
+    match hour {
+        0 => encode(12, control),
+        1..=11 => encode(hour, control),
+        12 => encode(12, control) | PM,
+        _ => encode(hour - 12, control) | PM
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    (value / 10) << 4 | value % 10
+}
+
+// Changes status register B. The caller has to hold the CMOS lock.
+unsafe fn update_control<F>(f: F) where F: FnOnce(&mut Control) {
+    let mut control = Control::from_bits_truncate(read_register(STATUS_B));
+    f(&mut control);
+    write_register(STATUS_B, control.bits());
+}
+
+unsafe fn read_register(index: u8) -> u8 {
+    Port::new(INDEX).write(index);
+    Port::new(DATA).read()
+}
+
+unsafe fn write_register(index: u8, value: u8) {
+    Port::new(INDEX).write(index);
+    Port::new(DATA).write(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::halt;
+
+    static RUNG: AtomicBool = AtomicBool::new(false);
+
+    #[test]
+    fn decoding_registers() {
+        let registers = Registers { second: 0x59, minute: 0x30, hour: 0x12 | PM, day: 0x29, month: 0x02, year: 0x24, century: Some(0x20) };
+        let date_time = DateTime { year: 2024, month: 2, day: 29, hour: 12, minute: 30, second: 59 };
+        assert_eq!(date_time, registers.decode(Control::empty()));
+
+        let registers = Registers { second: 59, minute: 30, hour: 12, day: 29, month: 2, year: 24, century: None };
+        let date_time = DateTime { year: 2024, month: 2, day: 29, hour: 0, minute: 30, second: 59 };
+        assert_eq!(date_time, registers.decode(Control::BINARY));
+
+        let registers = Registers { second: 0x00, minute: 0x05, hour: 0x23, day: 0x31, month: 0x12, year: 0x99, century: Some(0x19) };
+        let date_time = DateTime { year: 1999, month: 12, day: 31, hour: 23, minute: 5, second: 0 };
+        assert_eq!(date_time, registers.decode(Control::HOURS_24));
+    }
+
+    #[test]
+    fn encoding_alarm_hours() {
+        assert_eq!(0x12, encode_hour(0, Control::empty()));
+        assert_eq!(0x12 | PM, encode_hour(12, Control::empty()));
+        assert_eq!(11 | PM, encode_hour(23, Control::BINARY));
+        assert_eq!(0x23, encode_hour(23, Control::HOURS_24));
+    }
+
+    #[test]
+    fn reading_the_date() {
+        let date_time = read();
+        assert!(date_time.year >= 2021);
+        assert!((1..=12).contains(&date_time.month));
+        assert!((1..=31).contains(&date_time.day));
+        assert!(date_time.hour < 24);
+    }
+
+    #[test]
+    fn ringing_the_alarm() {
+        assert_eq!(Err(Error::InvalidTime), set_alarm(Alarm { hour: Some(24), minute: None, second: None }, || {}));
+
+        // Leave a second to spare, in case the clock ticks over before the alarm's set.
+        let second = (read().second + 2) % 60;
+        set_alarm(Alarm { hour: None, minute: None, second: Some(second) }, || RUNG.store(true, Ordering::Relaxed)).unwrap();
+
+        while !RUNG.load(Ordering::Relaxed) {
+            halt();
+        }
+
+        // The wall clock uses the alarm the rest of the time.
+        crate::time::keep_in_step();
+    }
+}
@@ -0,0 +1,45 @@
+use crate::arch::x86_64::instructions::{rdtsc, cpuid};
+use crate::arch::x86_64::interrupts::timer;
+use crate::time::ClockSource;
+
+// The time-stamp counter, which counts at the CPU's nominal rate. It's the cheapest clock there is
+// to read, but only an invariant TSC keeps that rate through power and frequency changes.
+pub struct TSC {
+    frequency: u64
+}
+
+impl TSC {
+    // Returns the TSC if it's invariant. Its rate comes from the local APIC timer's calibration.
+    pub fn find() -> Option<TSC> {
+        // CPUID.80000007H:EDX[8] says whether the TSC is invariant.
+        let invariant = cpuid(0x8000_0000, 0)[0] >= 0x8000_0007 && cpuid(0x8000_0007, 0)[3] & (1 << 8) != 0;
+
+        if !invariant {
+            return None
+        }
+
+        timer::tsc_frequency().map(|frequency| TSC { frequency })
+    }
+}
+
+impl ClockSource for TSC {
+    fn name(&self) -> &'static str {
+        "tsc"
+    }
+
+    fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX
+    }
+
+    fn rating(&self) -> u32 {
+        300
+    }
+
+    fn read(&self) -> u64 {
+        rdtsc()
+    }
+}
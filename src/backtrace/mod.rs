@@ -0,0 +1,109 @@
+mod symbols;
+use symbols::Symbols;
+
+use crate::boot;
+use crate::arch::backtrace::{Frames, frame_pointer};
+use crate::memory::VirtualAddress;
+
+use log::warn;
+use spin::RwLock;
+
+// util/run passes the kernel's symbol table as a boot module with this name.
+pub const SYMBOL_TABLE: &str = "symbols";
+
+static SYMBOLS: RwLock<Option<Symbols<'static>>> = RwLock::new(None);
+
+pub fn initialize(info: &boot::Info) {
+    let module = match info.modules().find(is_symbol_table) {
+        Some(module) => module,
+        None => return
+    };
+
+    match core::str::from_utf8(module.bytes()) {
+        Ok(text) => { SYMBOLS.write().replace(Symbols::new(text)); }
+        Err(_) => warn!("Ignoring symbol table: not UTF-8")
+    }
+}
+
+pub fn is_symbol_table(module: &boot::Module) -> bool {
+    module.name() == SYMBOL_TABLE
+}
+
+// The chain of calls that led somewhere, printed one frame per line, with function names if
+// there's a symbol table.
+pub struct Backtrace {
+    instruction_pointer: Option<VirtualAddress>,
+    frames:              Frames
+}
+
+impl Backtrace {
+    // Starts from the function calling this one.
+    #[inline(always)]
+    pub fn here() -> Backtrace {
+        Backtrace { instruction_pointer: None, frames: Frames::new(frame_pointer()) }
+    }
+
+    // Starts from interrupted code, given its saved registers.
+    pub fn from(instruction_pointer: VirtualAddress, frame_pointer: u64) -> Backtrace {
+        Backtrace { instruction_pointer: Some(instruction_pointer), frames: Frames::new(frame_pointer) }
+    }
+}
+
+impl core::fmt::Display for Backtrace {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(formatter, "Backtrace:")?;
+
+        // This can run from a panic at any point, including while the table is being set.
+        let symbols = SYMBOLS.try_read().and_then(|symbols| *symbols);
+
+        let current = self.instruction_pointer.map(|address| (address.as_u64(), address.as_u64()));
+
+        // Return addresses point just past the call, which can be the start of the next function,
+        // so look up the byte before.
+        let returns = self.frames.clone().map(|address| (address.as_u64(), address.as_u64() - 1));
+
+        for (index, (address, lookup)) in current.into_iter().chain(returns).enumerate() {
+            match symbols.and_then(|symbols| symbols.lookup(lookup)) {
+                Some((name, offset)) => writeln!(formatter, "{:>4}: {:#018x} {}+{:#x}", index, address, name, offset + (address - lookup))?,
+                None => writeln!(formatter, "{:>4}: {:#018x}", index, address)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[inline(never)]
+    fn backtrace() -> Backtrace {
+        Backtrace::here()
+    }
+
+    #[test]
+    fn walking_the_stack() {
+        // At least this function, the test runner, and main.
+        assert!(backtrace().frames.count() >= 3);
+    }
+
+    #[test]
+    fn stopping_at_an_unmapped_frame() {
+        // The heap only ever grows up from the bottom of its range, so the top isn't mapped.
+        let frame_pointer = crate::arch::memory::KERNEL_HEAP.end - 16;
+        assert_eq!(0, Frames::new(frame_pointer).count());
+    }
+
+    #[test]
+    fn finding_the_symbol_table() {
+        use crate::memory::PhysicalAddress;
+
+        let module = |string| boot::Module { start: PhysicalAddress::zero(), end: PhysicalAddress::zero(), string };
+
+        assert!(is_symbol_table(&module("symbols")));
+        assert!(is_symbol_table(&module("target/georgix.symbols symbols")));
+        assert!(!is_symbol_table(&module("target/initramfs.cpio initramfs")));
+        assert!(!is_symbol_table(&module("")));
+    }
+}
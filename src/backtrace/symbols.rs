@@ -0,0 +1,76 @@
+// A symbol table in the format `nm --numeric-sort --demangle` writes, which util/run passes to the
+// kernel as a boot module:
+//
+//     ffffffff80100048 T _start
+//     ffffffff80104d30 T georgix::main::h4a6b8e0f0b6f2f0c
+//
+// It's searched as it is, rather than parsed up front, so that it works without a heap.
+#[derive(Clone, Copy)]
+pub struct Symbols<'a> {
+    text: &'a str
+}
+
+impl<'a> Symbols<'a> {
+    pub fn new(text: &'a str) -> Symbols<'a> {
+        Symbols { text }
+    }
+
+    // Finds the function containing an address, returning its name and the offset into it.
+    pub fn lookup(&self, address: u64) -> Option<(&'a str, u64)> {
+        let mut found = None;
+
+        for (start, name) in self.functions() {
+            if start > address {
+                break
+            }
+
+            found = Some((name, address - start));
+        }
+
+        found
+    }
+
+    fn functions(&self) -> impl Iterator<Item = (u64, &'a str)> {
+        self.text.lines().filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let address = u64::from_str_radix(fields.next()?, 16).ok()?;
+            let kind = fields.next()?;
+            let name = fields.next()?;
+
+            match kind {
+                "T" | "t" | "W" | "w" => Some((address, name)),
+                _ => None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = concat!(
+        "                 U undefined\n",
+        "ffffffff80100000 T __kernel_start\n",
+        "ffffffff80100048 T _start\n",
+        "ffffffff80104d30 t georgix::main::h4a6b8e0f0b6f2f0c\n",
+        "ffffffff80105000 D georgix::VERSION\n",
+        "ffffffff80105100 T <georgix::Foo as core::fmt::Display>::fmt\n"
+    );
+
+    #[test]
+    fn looking_up_symbols() {
+        let symbols = Symbols::new(TABLE);
+
+        assert_eq!(Some(("_start", 0)), symbols.lookup(0xffffffff80100048));
+        assert_eq!(Some(("georgix::main::h4a6b8e0f0b6f2f0c", 0x10)), symbols.lookup(0xffffffff80104d40));
+        assert_eq!(Some(("<georgix::Foo as core::fmt::Display>::fmt", 1)), symbols.lookup(0xffffffff80105101));
+        assert_eq!(None, symbols.lookup(0xffffffff800fffff));
+    }
+
+    #[test]
+    fn skipping_data_symbols() {
+        // The closest function wins, even with a data symbol in between.
+        assert_eq!(Some(("georgix::main::h4a6b8e0f0b6f2f0c", 0x2d0)), Symbols::new(TABLE).lookup(0xffffffff80105000));
+    }
+}
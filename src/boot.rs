@@ -0,0 +1,208 @@
+// What the bootloader tells us, whichever boot protocol it used.
+
+use crate::multiboot::{self, magic, v1};
+use crate::limine;
+use crate::memory::{PhysicalAddress, map::{Region, Kind}};
+use crate::arch::memory::PHYSICAL_MEMORY_OFFSET;
+
+use core::ops::Range;
+use spin::RwLock;
+
+static INFO: RwLock<Option<Info>> = RwLock::new(None);
+
+pub fn set(info: Info) {
+    INFO.write().replace(info);
+}
+
+pub fn get() -> Option<Info> {
+    *INFO.read()
+}
+
+#[derive(Clone, Copy)]
+pub enum Info {
+    Multiboot1(&'static v1::Info),
+    Multiboot2(&'static multiboot::Info),
+    Limine(limine::Info)
+}
+
+impl Info {
+    // Interprets the boot information according to the magic number the bootloader left.
+    //
+    // This is unsafe because the address has to point to valid boot information, and the boot page
+    // tables have to map it. They map the first 4 GiB of physical memory, where bootloaders put it.
+    // Limine has no address to pass; its responses are found through the kernel's own requests.
+    pub unsafe fn new(magic: multiboot::Magic, address: PhysicalAddress) -> Info {
+        let pointer = address.to_virtual();
+
+        match magic {
+            magic::MULTIBOOT1 => Info::Multiboot1(&*pointer.as_ptr()),
+            magic::MULTIBOOT2 => Info::Multiboot2(&*pointer.as_ptr()),
+            limine::MAGIC => Info::Limine(Info::limine()),
+            _ => panic!("Georgix requires a Multiboot- or Limine-compliant bootloader")
+        }
+    }
+
+    // The kernel finds physical memory at a fixed offset, and Limine puts its direct map there too
+    // unless told to randomize it. It can load the kernel image anywhere, though, so note where.
+    unsafe fn limine() -> limine::Info {
+        let info = limine::Info::new();
+
+        match info.direct_map_offset() {
+            Some(PHYSICAL_MEMORY_OFFSET) => (),
+            Some(offset) => panic!("Limine mapped physical memory at {:#x}, not {:#x}", offset, PHYSICAL_MEMORY_OFFSET),
+            None => panic!("Limine didn't say where it mapped physical memory")
+        }
+
+        if let Some((physical, _)) = info.kernel_address() {
+            crate::arch::memory::relocate_kernel_image(physical);
+        }
+
+        info
+    }
+
+    pub fn command_line(&self) -> Option<&'static str> {
+        match *self {
+            Info::Multiboot1(info) => info.command_line(),
+            Info::Multiboot2(info) => info.command_line(),
+            Info::Limine(info) => info.command_line()
+        }
+    }
+
+    pub fn bootloader_name(&self) -> Option<&'static str> {
+        match *self {
+            Info::Multiboot1(info) => info.bootloader_name(),
+            Info::Multiboot2(info) => info.bootloader_name(),
+            Info::Limine(info) => info.bootloader_name()
+        }
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = Module> {
+        match *self {
+            Info::Multiboot1(info) => Iter::Multiboot1(info.modules().iter().map(|module| {
+                Module { start: module.start(), end: module.end(), string: module.string().unwrap_or("") }
+            })),
+
+            Info::Multiboot2(info) => Iter::Multiboot2(info.modules().map(|module| {
+                Module { start: module.start(), end: module.end(), string: module.string() }
+            })),
+
+            Info::Limine(info) => Iter::Limine(info.modules().iter().map(|module| {
+                Module { start: module.start(), end: module.end(), string: module.command_line().unwrap_or("") }
+            }))
+        }
+    }
+
+    pub fn memory_map(&self) -> Option<MemoryMap> {
+        match *self {
+            Info::Multiboot1(info) => info.memory_map().map(MemoryMap::Multiboot1),
+            Info::Multiboot2(info) => info.memory_map().map(MemoryMap::Multiboot2),
+            Info::Limine(info) => info.memory_map().map(MemoryMap::Limine)
+        }
+    }
+
+    // Returns the ACPI RSDP, if the bootloader found it. Multiboot 1 doesn't look.
+    pub fn rsdp(&self) -> Option<&'static [u8]> {
+        match *self {
+            Info::Multiboot1(_) => None,
+            Info::Multiboot2(info) => info.rsdp(),
+            Info::Limine(info) => info.rsdp()
+        }
+    }
+
+    // Returns the physical memory the boot information occupies, which has to be kept intact.
+    // Limine's responses are all in bootloader-reclaimable memory, which its memory map already
+    // marks, so there's nothing to add.
+    pub fn extents(&self) -> impl Iterator<Item = Range<PhysicalAddress>> {
+        match *self {
+            Info::Multiboot1(info) => Iter::Multiboot1(info.extents()),
+            Info::Multiboot2(info) => Iter::Multiboot2(core::iter::once(info.address()..info.address() + info.size())),
+            Info::Limine(_) => Iter::Limine(core::iter::empty())
+        }
+    }
+}
+
+impl core::fmt::Display for Info {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match *self {
+            Info::Multiboot1(info) => write!(formatter, "Multiboot 1:\n{}", info),
+            Info::Multiboot2(info) => write!(formatter, "{}", info),
+            Info::Limine(info) => write!(formatter, "{}", info)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Module {
+    pub start:  PhysicalAddress,
+    pub end:    PhysicalAddress,
+    pub string: &'static str
+}
+
+impl Module {
+    // Returns the last word of the module's string, which names it. QEMU's Multiboot 1 loader puts
+    // the module's path before it, as in "target/georgix.symbols symbols".
+    pub fn name(&self) -> &'static str {
+        self.string.split_whitespace().last().unwrap_or("")
+    }
+
+    pub fn bytes(&self) -> &'static [u8] {
+        // This is safe because modules stay reserved in the physical memory map, and the direct
+        // map covers all of physical memory.
+        unsafe { core::slice::from_raw_parts(self.start.to_virtual().as_ptr(), self.end - self.start) }
+    }
+}
+
+pub enum MemoryMap {
+    Multiboot1(v1::MemoryMap),
+    Multiboot2(multiboot::info::memory::MemoryMap<'static>),
+    Limine(limine::MemoryMap)
+}
+
+impl MemoryMap {
+    pub fn regions(&self) -> impl Iterator<Item = Region> + '_ {
+        match self {
+            MemoryMap::Multiboot1(map) => Iter::Multiboot1(map.regions().map(|region| {
+                Region { range: region.starts_at()..region.ends_at(), kind: Kind::from(region.kind()) }
+            })),
+
+            MemoryMap::Multiboot2(map) => Iter::Multiboot2(map.regions().map(|region| {
+                Region { range: region.starts_at()..region.ends_at(), kind: Kind::from(region.kind()) }
+            })),
+
+            MemoryMap::Limine(map) => Iter::Limine(map.regions().map(|region| {
+                Region { range: region.starts_at()..region.ends_at(), kind: Kind::from(region.kind()) }
+            }))
+        }
+    }
+}
+
+impl core::fmt::Display for MemoryMap {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            MemoryMap::Multiboot1(map) => write!(formatter, "{}", map),
+            MemoryMap::Multiboot2(map) => write!(formatter, "{}", map),
+            MemoryMap::Limine(map) => write!(formatter, "{}", map)
+        }
+    }
+}
+
+// Lets one function return any protocol's iterator.
+enum Iter<A, B, C> {
+    Multiboot1(A),
+    Multiboot2(B),
+    Limine(C)
+}
+
+impl<A, B, C, T> Iterator for Iter<A, B, C>
+    where A: Iterator<Item = T>, B: Iterator<Item = T>, C: Iterator<Item = T>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match self {
+            Iter::Multiboot1(iterator) => iterator.next(),
+            Iter::Multiboot2(iterator) => iterator.next(),
+            Iter::Limine(iterator) => iterator.next()
+        }
+    }
+}
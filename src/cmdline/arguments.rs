@@ -0,0 +1,167 @@
+use arrayvec::ArrayString;
+use core::str::Chars;
+
+pub type Key = ArrayString<64>;
+pub type Text = ArrayString<256>;
+
+// One `key=value` or flag argument, with quotes and escapes already removed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Argument {
+    pub key:   Key,
+    pub value: Option<Text>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentError {
+    TooLong,
+    UnterminatedQuote
+}
+
+impl core::fmt::Display for ArgumentError {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ArgumentError::TooLong => write!(formatter, "argument is too long"),
+            ArgumentError::UnterminatedQuote => write!(formatter, "quote is never closed")
+        }
+    }
+}
+
+// Splits a command line into arguments at unquoted whitespace. Single quotes take everything up
+// to the next single quote literally. Double quotes group whitespace but still allow escapes, and
+// a backslash outside single quotes takes the next character literally. The first unquoted,
+// unescaped `=` separates the key from the value.
+pub struct Arguments<'a> {
+    characters: Chars<'a>
+}
+
+impl<'a> Arguments<'a> {
+    pub fn new(command_line: &'a str) -> Arguments<'a> {
+        Arguments { characters: command_line.chars() }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        loop {
+            match self.characters.clone().next() {
+                Some(character) if character.is_whitespace() => { self.characters.next(); }
+                Some(_) => return true,
+                None => return false
+            }
+        }
+    }
+}
+
+impl Iterator for Arguments<'_> {
+    type Item = Result<Argument, ArgumentError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.skip_whitespace() {
+            return None
+        }
+
+        let mut key = Key::new();
+        let mut value: Option<Text> = None;
+        let mut quote: Option<char> = None;
+        let mut error = None;
+
+        loop {
+            let character = match self.characters.next() {
+                Some(character) => character,
+                None if quote.is_some() => return Some(Err(ArgumentError::UnterminatedQuote)),
+                None => break
+            };
+
+            let literal = match (quote, character) {
+                (None, character) if character.is_whitespace() => break,
+                (None, '"') | (None, '\'') => { quote = Some(character); continue }
+                (Some(open), character) if character == open => { quote = None; continue }
+                (Some('\''), character) => character,
+                (_, '\\') => self.characters.next().unwrap_or('\\'),
+                (None, '=') if value.is_none() => { value = Some(Text::new()); continue }
+                (_, character) => character
+            };
+
+            // Keep consuming the argument after an error so the next one starts in the right place.
+            let pushed = match value.as_mut() {
+                Some(value) => value.try_push(literal).is_ok(),
+                None => key.try_push(literal).is_ok()
+            };
+
+            if !pushed {
+                error = Some(ArgumentError::TooLong);
+            }
+        }
+
+        match error {
+            Some(error) => Some(Err(error)),
+            None => Some(Ok(Argument { key, value }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument(key: &str, value: Option<&str>) -> Result<Argument, ArgumentError> {
+        Ok(Argument { key: Key::from(key).unwrap(), value: value.map(|value| Text::from(value).unwrap()) })
+    }
+
+    #[test]
+    fn splitting_flags_and_values() {
+        let mut arguments = Arguments::new("  console=serial nosmp\tloglevel=debug  ");
+
+        assert_eq!(Some(argument("console", Some("serial"))), arguments.next());
+        assert_eq!(Some(argument("nosmp", None)), arguments.next());
+        assert_eq!(Some(argument("loglevel", Some("debug"))), arguments.next());
+        assert_eq!(None, arguments.next());
+    }
+
+    #[test]
+    fn keeping_empty_values_apart_from_flags() {
+        let mut arguments = Arguments::new("test.filter= a=b=c");
+
+        assert_eq!(Some(argument("test.filter", Some(""))), arguments.next());
+        assert_eq!(Some(argument("a", Some("b=c"))), arguments.next());
+    }
+
+    #[test]
+    fn grouping_quoted_whitespace() {
+        let mut arguments = Arguments::new(r#"name="two words" 'a b'=c "x"y"#);
+
+        assert_eq!(Some(argument("name", Some("two words"))), arguments.next());
+        assert_eq!(Some(argument("a b", Some("c"))), arguments.next());
+        assert_eq!(Some(argument("xy", None)), arguments.next());
+        assert_eq!(None, arguments.next());
+    }
+
+    #[test]
+    fn escaping_characters() {
+        let mut arguments = Arguments::new(r#"a\ b=\"c\" d\=e="\\\"" 'f\g' h\"#);
+
+        assert_eq!(Some(argument("a b", Some("\"c\""))), arguments.next());
+        assert_eq!(Some(argument("d=e", Some("\\\""))), arguments.next());
+        assert_eq!(Some(argument("f\\g", None)), arguments.next());
+        assert_eq!(Some(argument("h\\", None)), arguments.next());
+        assert_eq!(None, arguments.next());
+    }
+
+    #[test]
+    fn rejecting_unterminated_quotes() {
+        let mut arguments = Arguments::new("a=\"b c");
+
+        assert_eq!(Some(Err(ArgumentError::UnterminatedQuote)), arguments.next());
+        assert_eq!(None, arguments.next());
+    }
+
+    #[test]
+    fn rejecting_arguments_that_are_too_long() {
+        let mut line = ArrayString::<128>::new();
+        (0..65).for_each(|_| line.push('k'));
+        line.push_str(" nosmp");
+
+        let mut arguments = Arguments::new(&line);
+
+        assert_eq!(Some(Err(ArgumentError::TooLong)), arguments.next());
+        assert_eq!(Some(argument("nosmp", None)), arguments.next());
+    }
+}
@@ -0,0 +1,239 @@
+mod arguments;
+use arguments::{Arguments, Argument};
+
+use arrayvec::ArrayString;
+use log::warn;
+use spin::RwLock;
+
+// Every option the kernel understands. Subsystems declare their parameters next to the code that
+// reads them and list them here.
+static PARAMETERS: &[&dyn Declaration] = &[
+    &crate::console::DEVICE,
+    &crate::arch::serial::SETTINGS,
+    &crate::logging::LEVEL,
+    &crate::logging::FILTER,
+    &crate::logging::SINKS,
+    &crate::logging::DUMP,
+    &crate::time::CLOCK_SOURCE,
+    &crate::time::timer::TICKLESS,
+    &crate::arch::smp::NOSMP,
+    #[cfg(test)] &crate::test::FILTER
+];
+
+// Applies the options on the kernel command line. Anything that can't be applied is reported and
+// ignored, leaving the parameter at its default.
+pub fn initialize(command_line: &str) {
+    apply(PARAMETERS, command_line)
+}
+
+fn apply(parameters: &[&dyn Declaration], command_line: &str) {
+    let mut arguments = Arguments::new(command_line).peekable();
+
+    // Bootloaders can pass the path of the kernel image first, like argv[0]. Options never have a
+    // slash in their name.
+    if let Some(Ok(Argument { key, value: None })) = arguments.peek() {
+        if key.contains('/') {
+            arguments.next();
+        }
+    }
+
+    for argument in arguments {
+        let Argument { key, value } = match argument {
+            Ok(argument) => argument,
+
+            Err(error) => {
+                warn!("Ignoring malformed command-line option: {}", error);
+                continue
+            }
+        };
+
+        match parameters.iter().find(|parameter| parameter.name() == key.as_str()) {
+            Some(parameter) => {
+                if let Err(error) = parameter.set(value.as_deref()) {
+                    warn!("Ignoring command-line option {}: {}", key, error);
+                }
+            }
+
+            None => warn!("Ignoring unknown command-line option {}", key)
+        }
+    }
+}
+
+// A typed kernel parameter. Subsystems declare one as a static and read it with `get`, which
+// returns the default until the command line sets it.
+pub struct Parameter<T> {
+    name:    &'static str,
+    default: T,
+    value:   RwLock<Option<T>>
+}
+
+impl<T> Parameter<T> {
+    pub const fn new(name: &'static str, default: T) -> Parameter<T> {
+        Parameter { name, default, value: RwLock::new(None) }
+    }
+}
+
+impl<T: Value> Parameter<T> {
+    pub fn get(&self) -> T {
+        self.value.read().unwrap_or(self.default)
+    }
+}
+
+// Lets the command line set parameters of different types.
+trait Declaration: Sync {
+    fn name(&self) -> &'static str;
+    fn set(&self, value: Option<&str>) -> Result<(), ValueError>;
+}
+
+impl<T: Value> Declaration for Parameter<T> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn set(&self, value: Option<&str>) -> Result<(), ValueError> {
+        self.value.write().replace(T::parse(value)?);
+        Ok(())
+    }
+}
+
+// A type a parameter can hold. Flags are given without a value, as in `nosmp`.
+pub trait Value: Copy + Send + Sync {
+    fn parse(value: Option<&str>) -> Result<Self, ValueError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueError {
+    Missing,
+    Invalid,
+    TooLong
+}
+
+impl core::fmt::Display for ValueError {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ValueError::Missing => write!(formatter, "a value is required"),
+            ValueError::Invalid => write!(formatter, "the value is invalid"),
+            ValueError::TooLong => write!(formatter, "the value is too long")
+        }
+    }
+}
+
+impl Value for bool {
+    fn parse(value: Option<&str>) -> Result<bool, ValueError> {
+        match value {
+            None | Some("1") | Some("yes") | Some("on") | Some("true") => Ok(true),
+            Some("0") | Some("no") | Some("off") | Some("false") => Ok(false),
+            Some(_) => Err(ValueError::Invalid)
+        }
+    }
+}
+
+macro_rules! value_impl_for_integer {
+    ($($integer:ty),+) => {
+        $(
+            // Integers are decimal, or hexadecimal with a 0x prefix.
+            impl Value for $integer {
+                fn parse(value: Option<&str>) -> Result<$integer, ValueError> {
+                    let value = value.ok_or(ValueError::Missing)?;
+
+                    let result = match value.strip_prefix("0x") {
+                        Some(digits) => <$integer>::from_str_radix(digits, 16),
+                        None => value.parse()
+                    };
+
+                    result.map_err(|_| ValueError::Invalid)
+                }
+            }
+        )+
+    }
+}
+
+value_impl_for_integer!(u8, u16, u32, u64, usize);
+
+impl<const S: usize> Value for ArrayString<S> {
+    fn parse(value: Option<&str>) -> Result<ArrayString<S>, ValueError> {
+        ArrayString::from(value.ok_or(ValueError::Missing)?).map_err(|_| ValueError::TooLong)
+    }
+}
+
+// For parameters without a sensible default.
+impl<T: Value> Value for Option<T> {
+    fn parse(value: Option<&str>) -> Result<Option<T>, ValueError> {
+        T::parse(value).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Color {
+        Red,
+        Blue
+    }
+
+    impl Value for Color {
+        fn parse(value: Option<&str>) -> Result<Color, ValueError> {
+            match value.ok_or(ValueError::Missing)? {
+                "red" => Ok(Color::Red),
+                "blue" => Ok(Color::Blue),
+                _ => Err(ValueError::Invalid)
+            }
+        }
+    }
+
+    #[test]
+    fn setting_parameters_from_the_command_line() {
+        let color = Parameter::new("color", Color::Red);
+        let count = Parameter::new("test.count", 1usize);
+        let name = Parameter::new("name", ArrayString::<16>::new());
+        let quiet = Parameter::new("quiet", false);
+
+        apply(&[&color, &count, &name, &quiet], "/georgix color=blue test.count=0x20 name='a b' quiet bogus");
+
+        assert_eq!(Color::Blue, color.get());
+        assert_eq!(32, count.get());
+        assert_eq!("a b", name.get().as_str());
+        assert!(quiet.get());
+    }
+
+    #[test]
+    fn skipping_a_relative_kernel_path() {
+        let quiet = Parameter::new("quiet", false);
+
+        // QEMU's Multiboot 1 loader passes the path the kernel was given by.
+        apply(&[&quiet], "target/georgix quiet");
+
+        assert!(quiet.get());
+    }
+
+    #[test]
+    fn keeping_defaults_for_invalid_values() {
+        let color = Parameter::new("color", Color::Red);
+        let count = Parameter::new("count", 1u8);
+        let name = Parameter::new("name", ArrayString::<4>::new());
+
+        apply(&[&color, &count, &name], "color=green count=300 count=x name=toolong");
+
+        assert_eq!(Color::Red, color.get());
+        assert_eq!(1, count.get());
+        assert_eq!("", name.get().as_str());
+    }
+
+    #[test]
+    fn parsing_flags() {
+        assert_eq!(Ok(true), bool::parse(None));
+        assert_eq!(Ok(true), bool::parse(Some("yes")));
+        assert_eq!(Ok(false), bool::parse(Some("off")));
+        assert_eq!(Err(ValueError::Invalid), bool::parse(Some("maybe")));
+    }
+
+    #[test]
+    fn parsing_integers() {
+        assert_eq!(Ok(42), u32::parse(Some("42")));
+        assert_eq!(Ok(0xFF), u32::parse(Some("0xff")));
+        assert_eq!(Err(ValueError::Missing), u32::parse(None));
+        assert_eq!(Err(ValueError::Invalid), u8::parse(Some("-1")));
+    }
+}
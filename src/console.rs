@@ -0,0 +1,79 @@
+use crate::cmdline::{Parameter, Value, ValueError};
+use crate::arch::{self, serial::COM};
+use crate::vga;
+
+// Where kernel output goes: `console=vga` or `console=serial`. Tests report over the serial port,
+// so they send everything there by default.
+pub static DEVICE: Parameter<Device> = Parameter::new("console", if cfg!(test) { Device::Serial } else { Device::VGA });
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    VGA,
+    Serial
+}
+
+impl Value for Device {
+    fn parse(value: Option<&str>) -> Result<Device, ValueError> {
+        match value.ok_or(ValueError::Missing)? {
+            "vga" => Ok(Device::VGA),
+            "serial" => Ok(Device::Serial),
+            _ => Err(ValueError::Invalid)
+        }
+    }
+}
+
+// Both devices are set up, since the command line can only be read after printing is possible.
+pub fn initialize() {
+    vga::text::console::initialize();
+    arch::serial::initialize();
+}
+
+pub fn print(args: core::fmt::Arguments) {
+    match DEVICE.get() {
+        Device::VGA => vga::text::console::print(args),
+        Device::Serial => arch::serial::print(args)
+    }
+}
+
+// Echoes whatever's typed at the serial console back to it, once the kernel has nothing else to
+// do. The VGA console has no keyboard driver behind it yet, so it just waits.
+pub fn echo() -> ! {
+    let mut buffer = [0; 64];
+
+    while DEVICE.get() == Device::Serial {
+        let count = match arch::serial::read_blocking(COM::COM1, &mut buffer) {
+            Ok(count) => count,
+            Err(_) => break
+        };
+
+        for &byte in &buffer[..count] {
+            // Terminals send a carriage return for Enter, and expect one back before the newline.
+            if byte == b'\r' {
+                send(b"\r\n")
+            } else {
+                send(&[byte])
+            }
+        }
+    }
+
+    arch::park()
+}
+
+fn send(mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        match arch::serial::write(COM::COM1, bytes) {
+            Ok(count) => bytes = &bytes[count..],
+            Err(_) => return
+        }
+    }
+}
+
+// The console as a `core::fmt::Write`, for code that writes to one.
+pub struct Writer;
+
+impl core::fmt::Write for Writer {
+    fn write_str(&mut self, string: &str) -> core::fmt::Result {
+        print(format_args!("{}", string));
+        Ok(())
+    }
+}
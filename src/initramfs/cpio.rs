@@ -0,0 +1,144 @@
+use super::{Entry, Kind};
+use crate::util::alignment::align_up;
+
+use alloc::borrow::Cow;
+
+// The "new ASCII" cpio format written by `cpio -H newc`, which Linux uses for its initramfs. Each
+// entry is a 110-byte header of hexadecimal fields, the NUL-terminated name, then the contents.
+// The name and contents are each padded to a multiple of four bytes.
+const HEADER_SIZE: usize = 110;
+
+const MAGIC: &[u8] = b"070701";
+const MAGIC_WITH_CHECKSUM: &[u8] = b"070702";
+
+const TRAILER: &str = "TRAILER!!!";
+
+pub fn detect(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC) || bytes.starts_with(MAGIC_WITH_CHECKSUM)
+}
+
+pub struct Entries<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Entries<'a> {
+    pub fn new(bytes: &'a [u8]) -> Entries<'a> {
+        Entries { bytes }
+    }
+
+    fn parse(&self) -> Option<(Entry<'a>, usize)> {
+        let header = self.bytes.get(..HEADER_SIZE)?;
+
+        if !detect(header) {
+            return None
+        }
+
+        let mode = field(header, 1)?;
+        let size = field(header, 6)? as usize;
+        let name_size = field(header, 11)? as usize;
+
+        // The name includes its terminating NUL.
+        let name = self.bytes.get(HEADER_SIZE..HEADER_SIZE + name_size.checked_sub(1)?)?;
+        let name = core::str::from_utf8(name).ok()?;
+
+        let start = align_up(HEADER_SIZE + name_size, 4);
+        let data = self.bytes.get(start..start + size)?;
+
+        let kind = match mode & 0o170000 {
+            0o100000 => Kind::File,
+            0o040000 => Kind::Directory,
+            0o120000 => Kind::Symlink,
+            _ => Kind::Other
+        };
+
+        Some((Entry { path: Cow::Borrowed(name), kind, data }, align_up(start + size, 4)))
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Entry<'a>> {
+        match self.parse() {
+            Some((entry, _)) if entry.path == TRAILER => None,
+
+            Some((entry, length)) => {
+                self.bytes = self.bytes.get(length..).unwrap_or(&[]);
+                Some(entry)
+            }
+
+            // Stop at a malformed header rather than reading garbage.
+            None => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+// Reads one of the eight-digit hexadecimal fields that follow the magic.
+fn field(header: &[u8], index: usize) -> Option<u32> {
+    let start = MAGIC.len() + index * 8;
+    let digits = core::str::from_utf8(&header[start..start + 8]).ok()?;
+
+    u32::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use alloc::{vec::Vec, format};
+
+    // Builds a cpio archive from (path, mode, contents) entries.
+    pub fn archive(entries: &[(&str, u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        let trailer = (TRAILER, 0, &[][..]);
+
+        for &(path, mode, contents) in entries.iter().chain(core::iter::once(&trailer)) {
+            let header = format!(
+                "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+                0, mode, 0, 0, 1, 0, contents.len(), 0, 0, 0, 0, path.len() + 1, 0
+            );
+
+            bytes.extend_from_slice(header.as_bytes());
+            bytes.extend_from_slice(path.as_bytes());
+            bytes.push(0);
+            bytes.resize(align_up(bytes.len(), 4), 0);
+            bytes.extend_from_slice(contents);
+            bytes.resize(align_up(bytes.len(), 4), 0);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn reading_cpio_entries() {
+        let bytes = archive(&[
+            (".", 0o040755, b""),
+            ("etc", 0o040755, b""),
+            ("etc/motd", 0o100644, b"Hello!\n"),
+            ("bin/sh", 0o120777, b"busybox")
+        ]);
+
+        assert!(detect(&bytes));
+
+        let entries: Vec<_> = Entries::new(&bytes).collect();
+
+        assert_eq!(
+            entries.iter().map(|entry| (&*entry.path, entry.kind, entry.data)).collect::<Vec<_>>(),
+            [
+                (".", Kind::Directory, &b""[..]),
+                ("etc", Kind::Directory, &b""[..]),
+                ("etc/motd", Kind::File, &b"Hello!\n"[..]),
+                ("bin/sh", Kind::Symlink, &b"busybox"[..])
+            ]
+        );
+    }
+
+    #[test]
+    fn stopping_at_a_truncated_entry() {
+        let bytes = archive(&[("etc/motd", 0o100644, b"Hello!\n")]);
+        assert_eq!(0, Entries::new(&bytes[..bytes.len() - 130]).count());
+    }
+}
@@ -0,0 +1,340 @@
+mod cpio;
+mod tar;
+
+use crate::{boot, backtrace};
+
+use log::{info, warn};
+use alloc::{borrow::Cow, string::{String, ToString}, vec::Vec};
+use spin::RwLock;
+
+// The initial RAM filesystem: a read-only archive the bootloader loads as the first boot module,
+// not counting the symbol table.
+static ROOT: RwLock<Option<Archive<'static>>> = RwLock::new(None);
+
+pub fn initialize(info: &boot::Info) {
+    let module = match choose(info.modules()) {
+        Some(module) => module,
+        None => return
+    };
+
+    if let Err(error) = mount(module.bytes()) {
+        warn!("Ignoring boot module {:?}: {}", module.string, error);
+    }
+}
+
+// Picks the initramfs out of the boot modules: the first one that isn't the symbol table.
+fn choose(mut modules: impl Iterator<Item = boot::Module>) -> Option<boot::Module> {
+    modules.find(|module| !backtrace::is_symbol_table(module))
+}
+
+// Mounts an archive as the root, replacing whatever was there. Entries with prefixed paths are
+// allocated on the heap, so this has to come after paging's set up.
+fn mount(bytes: &'static [u8]) -> Result<(), Error> {
+    let archive = Archive::new(bytes)?;
+
+    info!("Initramfs: {} bytes in {} format, {} entries", bytes.len(), archive.format(), archive.entries().count());
+    ROOT.write().replace(archive);
+    Ok(())
+}
+
+pub fn read(path: &str) -> Result<&'static [u8], Error> {
+    ROOT.read().as_ref().ok_or(Error::NotMounted)?.read(path)
+}
+
+pub fn list(path: &str) -> Result<Vec<DirectoryEntry>, Error> {
+    ROOT.read().as_ref().ok_or(Error::NotMounted)?.list(path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    NotMounted,
+    UnknownFormat,
+    NotFound,
+    NotAFile,
+    NotADirectory
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::NotMounted => write!(formatter, "no initramfs is mounted"),
+            Error::UnknownFormat => write!(formatter, "not a cpio or ustar archive"),
+            Error::NotFound => write!(formatter, "no such file or directory"),
+            Error::NotAFile => write!(formatter, "not a file"),
+            Error::NotADirectory => write!(formatter, "not a directory")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Cpio,
+    Tar
+}
+
+impl core::fmt::Display for Format {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Format::Cpio => write!(formatter, "cpio"),
+            Format::Tar => write!(formatter, "ustar")
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Archive<'a> {
+    bytes:  &'a [u8],
+    format: Format
+}
+
+impl<'a> Archive<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Archive<'a>, Error> {
+        let format =
+            if cpio::detect(bytes) {
+                Format::Cpio
+            } else if tar::detect(bytes) {
+                Format::Tar
+            } else {
+                return Err(Error::UnknownFormat)
+            };
+
+        Ok(Archive { bytes, format })
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn entries(&self) -> Entries<'a> {
+        match self.format {
+            Format::Cpio => Entries::Cpio(cpio::Entries::new(self.bytes)),
+            Format::Tar => Entries::Tar(tar::Entries::new(self.bytes))
+        }
+    }
+
+    // Finds the entry at the given path. Archives can hold the same path more than once, as when
+    // several are concatenated, and the last one wins.
+    pub fn find(&self, path: &str) -> Option<Entry<'a>> {
+        let path = normalize(path);
+        self.entries().filter(|entry| entry.path() == path).last()
+    }
+
+    pub fn read(&self, path: &str) -> Result<&'a [u8], Error> {
+        match self.find(path) {
+            Some(Entry { kind: Kind::File, data, .. }) => Ok(data),
+            Some(_) => Err(Error::NotAFile),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    // Lists a directory's immediate children, sorted by name. Archives don't always have entries
+    // for every directory, so directories are also inferred from the paths of the files in them.
+    pub fn list(&self, path: &str) -> Result<Vec<DirectoryEntry>, Error> {
+        let directory = normalize(path);
+
+        match self.find(directory) {
+            Some(Entry { kind: Kind::Directory, .. }) | None => (),
+            Some(_) => return Err(Error::NotADirectory)
+        }
+
+        let mut children: Vec<DirectoryEntry> = Vec::new();
+        let mut found = directory.is_empty();
+
+        for entry in self.entries() {
+            let path = entry.path();
+
+            let rest =
+                if directory.is_empty() {
+                    path
+                } else {
+                    match path.strip_prefix(directory).and_then(|rest| rest.strip_prefix('/')) {
+                        Some(rest) => rest,
+                        None if path == directory => { found = true; continue }
+                        None => continue
+                    }
+                };
+
+            found = true;
+
+            if rest.is_empty() {
+                continue
+            }
+
+            let (name, kind) = match rest.find('/') {
+                Some(index) => (&rest[..index], Kind::Directory),
+                None => (rest, entry.kind)
+            };
+
+            match children.iter_mut().find(|child| child.name == name) {
+                // Only an entry for the child itself can say what it is.
+                Some(child) => if !rest.contains('/') { child.kind = kind },
+                None => children.push(DirectoryEntry { name: name.to_string(), kind })
+            }
+        }
+
+        if !found {
+            return Err(Error::NotFound)
+        }
+
+        children.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        Ok(children)
+    }
+}
+
+pub enum Entries<'a> {
+    Cpio(cpio::Entries<'a>),
+    Tar(tar::Entries<'a>)
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Entry<'a>> {
+        match self {
+            Entries::Cpio(entries) => entries.next(),
+            Entries::Tar(entries) => entries.next()
+        }
+    }
+}
+
+pub struct Entry<'a> {
+    path:     Cow<'a, str>,
+    pub kind: Kind,
+    pub data: &'a [u8]
+}
+
+impl Entry<'_> {
+    // Returns the path relative to the root, without a leading "./" or "/" or a trailing slash.
+    pub fn path(&self) -> &str {
+        normalize(&self.path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Directory,
+    Symlink,
+    Other
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub name: String,
+    pub kind: Kind
+}
+
+// Archive tools write paths like "./etc/motd", "/etc/motd" or "etc/", which all mean the same.
+fn normalize(mut path: &str) -> &str {
+    loop {
+        let trimmed = path.trim_start_matches('/').trim_start_matches("./");
+
+        if trimmed == path {
+            break
+        }
+
+        path = trimmed;
+    }
+
+    match path.trim_end_matches('/') {
+        "." => "",
+        path => path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{format, vec};
+
+    fn directory(name: &str, kind: Kind) -> DirectoryEntry {
+        DirectoryEntry { name: name.to_string(), kind }
+    }
+
+    #[test]
+    fn normalizing_paths() {
+        assert_eq!("etc/motd", normalize("./etc/motd"));
+        assert_eq!("etc/motd", normalize("/etc/motd"));
+        assert_eq!("etc", normalize(".//etc/"));
+        assert_eq!("", normalize("."));
+        assert_eq!("", normalize("/"));
+    }
+
+    #[test]
+    fn reading_files_from_a_cpio_archive() {
+        let bytes = cpio::tests::archive(&[
+            (".", 0o040755, b""),
+            ("./etc", 0o040755, b""),
+            ("./etc/motd", 0o100644, b"Hello!\n"),
+            ("./bin/sh", 0o120777, b"busybox")
+        ]);
+
+        let archive = Archive::new(&bytes).unwrap();
+
+        assert_eq!(Format::Cpio, archive.format());
+        assert_eq!(Ok(&b"Hello!\n"[..]), archive.read("/etc/motd"));
+        assert_eq!(Err(Error::NotAFile), archive.read("etc"));
+        assert_eq!(Err(Error::NotAFile), archive.read("bin/sh"));
+        assert_eq!(Err(Error::NotFound), archive.read("etc/passwd"));
+    }
+
+    #[test]
+    fn listing_directories_in_a_tar_archive() {
+        let bytes = tar::tests::archive(&[
+            ("etc/", b'5', b""),
+            ("etc/motd", b'0', b"Hello!\n"),
+            ("etc/init.d/rc", b'0', b"#!/bin/sh\n"),
+            ("boot.txt", b'0', b"")
+        ]);
+
+        let archive = Archive::new(&bytes).unwrap();
+
+        assert_eq!(Format::Tar, archive.format());
+
+        assert_eq!(Ok(vec![directory("boot.txt", Kind::File), directory("etc", Kind::Directory)]), archive.list("/"));
+        assert_eq!(Ok(vec![directory("init.d", Kind::Directory), directory("motd", Kind::File)]), archive.list("etc"));
+        assert_eq!(Ok(vec![directory("rc", Kind::File)]), archive.list("etc/init.d/"));
+
+        assert_eq!(Err(Error::NotADirectory), archive.list("etc/motd"));
+        assert_eq!(Err(Error::NotFound), archive.list("usr"));
+    }
+
+    #[test]
+    fn letting_later_entries_win() {
+        let bytes = cpio::tests::archive(&[("motd", 0o100644, b"old"), ("motd", 0o100644, b"new")]);
+        assert_eq!(Ok(&b"new"[..]), Archive::new(&bytes).unwrap().read("motd"));
+    }
+
+    #[test]
+    fn mounting_an_archive_with_prefixed_paths() {
+        let parent = "d".repeat(60);
+        let path = format!("{}/{}/motd", parent, parent);
+        let bytes: &'static [u8] = Vec::leak(tar::tests::archive(&[(&path, b'0', b"Hello!\n")]));
+
+        let saved = ROOT.write().take();
+
+        assert_eq!(Ok(()), mount(bytes));
+        assert_eq!(Ok(&b"Hello!\n"[..]), read(&path));
+        assert_eq!(Ok(vec![directory(&parent, Kind::Directory)]), list("/"));
+
+        *ROOT.write() = saved;
+    }
+
+    #[test]
+    fn choosing_the_module() {
+        use crate::memory::PhysicalAddress;
+
+        let module = |string| boot::Module { start: PhysicalAddress::zero(), end: PhysicalAddress::zero(), string };
+
+        // As QEMU's Multiboot 1 loader passes them, with the symbol table first.
+        let modules = [module("target/georgix.symbols symbols"), module("target/initramfs.cpio initramfs")];
+        assert_eq!(Some("target/initramfs.cpio initramfs"), choose(modules.iter().copied()).map(|module| module.string));
+
+        assert!(choose(core::iter::once(module("symbols"))).is_none());
+    }
+
+    #[test]
+    fn rejecting_unknown_formats() {
+        assert_eq!(Err(Error::UnknownFormat), Archive::new(&[0; 1024]).map(|archive| archive.format()));
+    }
+}
@@ -0,0 +1,157 @@
+use super::{Entry, Kind};
+use crate::util::alignment::align_up;
+
+use alloc::{borrow::Cow, format};
+
+// The POSIX ustar format. Each entry is a 512-byte header of NUL-padded strings and octal numbers
+// followed by the contents, padded to a whole block. The archive ends with zeroed blocks.
+const BLOCK_SIZE: usize = 512;
+
+pub fn detect(bytes: &[u8]) -> bool {
+    // GNU tar writes "ustar  \0" rather than "ustar\000".
+    bytes.get(257..262) == Some(&b"ustar"[..])
+}
+
+pub struct Entries<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Entries<'a> {
+    pub fn new(bytes: &'a [u8]) -> Entries<'a> {
+        Entries { bytes }
+    }
+
+    fn parse(&self) -> Option<(Entry<'a>, usize)> {
+        let header = self.bytes.get(..BLOCK_SIZE)?;
+
+        if !detect(header) {
+            return None
+        }
+
+        let name = string(&header[0..100])?;
+        let prefix = string(&header[345..500])?;
+        let size = octal(&header[124..136])?;
+
+        let path = if prefix.is_empty() { Cow::Borrowed(name) } else { Cow::Owned(format!("{}/{}", prefix, name)) };
+
+        let kind = match header[156] {
+            b'0' | 0 => Kind::File,
+            b'5' => Kind::Directory,
+            b'2' => Kind::Symlink,
+            _ => Kind::Other
+        };
+
+        // Symbolic links keep their target in the header rather than the contents.
+        let data =
+            if kind == Kind::Symlink {
+                string(&header[157..257])?.as_bytes()
+            } else {
+                self.bytes.get(BLOCK_SIZE..BLOCK_SIZE + size)?
+            };
+
+        Some((Entry { path, kind, data }, BLOCK_SIZE + align_up(size, BLOCK_SIZE)))
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Entry<'a>> {
+        match self.parse() {
+            Some((entry, length)) => {
+                self.bytes = self.bytes.get(length..).unwrap_or(&[]);
+                Some(entry)
+            }
+
+            // The end-of-archive blocks have no magic, so they land here along with malformed headers.
+            None => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+fn string(field: &[u8]) -> Option<&str> {
+    let length = field.iter().position(|&byte| byte == 0).unwrap_or(field.len());
+    core::str::from_utf8(&field[..length]).ok()
+}
+
+// Numbers are octal, padded with leading zeroes or spaces and ended with a NUL or a space.
+fn octal(field: &[u8]) -> Option<usize> {
+    let digits = string(field)?.trim_matches(' ');
+
+    if digits.is_empty() {
+        Some(0)
+    } else {
+        usize::from_str_radix(digits, 8).ok()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    // Builds a ustar archive from (path, type, contents) entries. Paths longer than 100 bytes are
+    // split into a prefix and a name.
+    pub fn archive(entries: &[(&str, u8, &[u8])]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for &(path, kind, contents) in entries {
+            let mut header = [0u8; BLOCK_SIZE];
+
+            let (prefix, name) = match path.len() {
+                0..=100 => ("", path),
+
+                length => {
+                    let index = length - 101 + path[length - 101..].find('/').unwrap();
+                    (&path[..index], &path[index + 1..])
+                }
+            };
+
+            header[0..name.len()].copy_from_slice(name.as_bytes());
+            header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+            header[156] = kind;
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+
+            bytes.extend_from_slice(&header);
+            bytes.extend_from_slice(contents);
+            bytes.resize(align_up(bytes.len(), BLOCK_SIZE), 0);
+        }
+
+        bytes.resize(bytes.len() + 2 * BLOCK_SIZE, 0);
+        bytes
+    }
+
+    #[test]
+    fn reading_tar_entries() {
+        let bytes = archive(&[
+            ("etc/", b'5', b""),
+            ("etc/motd", b'0', b"Hello!\n"),
+            ("etc/big", b'0', &[0x55; 600])
+        ]);
+
+        assert!(detect(&bytes));
+
+        let entries: Vec<_> = Entries::new(&bytes).collect();
+
+        assert_eq!(3, entries.len());
+        assert_eq!(("etc/", Kind::Directory), (&*entries[0].path, entries[0].kind));
+        assert_eq!(("etc/motd", Kind::File, &b"Hello!\n"[..]), (&*entries[1].path, entries[1].kind, entries[1].data));
+        assert_eq!(600, entries[2].data.len());
+    }
+
+    #[test]
+    fn joining_the_prefix_and_name() {
+        let directory = "d".repeat(60);
+        let path = format!("{}/{}/file", directory, directory);
+
+        let bytes = archive(&[(&path, b'0', b"")]);
+        let entry = Entries::new(&bytes).next().unwrap();
+
+        assert_eq!(path, entry.path);
+    }
+}
@@ -0,0 +1,443 @@
+// The Limine boot protocol, which enters the kernel in 64-bit mode at its ELF entrypoint, already in
+// the higher half.
+//
+// Rather than being handed a table, the kernel leaves requests in its image for the bootloader to
+// find. Each starts with an identifier, and the bootloader fills in a pointer to its response before
+// jumping to the kernel. Responses, and everything they point to, are in the higher-half direct map.
+
+use crate::multiboot::Magic;
+use crate::memory::{PhysicalAddress, VirtualAddress};
+
+use core::cell::UnsafeCell;
+use core::ptr;
+
+// Limine doesn't leave a magic number, so the entrypoint in boot/start.S passes this one to main.
+// It spells "LIME". Keep in sync with boot/start.S.
+pub const MAGIC: Magic = 0x4C494D45;
+
+// The revision of the protocol we follow. The bootloader zeroes the last word if it supports it.
+#[used]
+#[link_section = ".requests"]
+static BASE_REVISION: Tag<[u64; 3]> = Tag::new([0xF9562B2D5C95A6C8, 0x6A7B384944536BDC, 2]);
+
+#[used]
+#[link_section = ".requests"]
+static BOOTLOADER: Request<BootloaderResponse> = Request::new([0xF55038D8E2A1202F, 0x279426FCF5F59740]);
+
+#[used]
+#[link_section = ".requests"]
+static KERNEL_FILE: Request<KernelFileResponse> = Request::new([0xAD97E90E83F1ED67, 0x31EB5D1C5FF23B69]);
+
+#[used]
+#[link_section = ".requests"]
+static KERNEL_ADDRESS: Request<KernelAddressResponse> = Request::new([0x71BA76863CC55F63, 0xB2644A48C516A487]);
+
+#[used]
+#[link_section = ".requests"]
+static HHDM: Request<HHDMResponse> = Request::new([0x48DCF1CB8AD2B852, 0x63984E959A98244B]);
+
+#[used]
+#[link_section = ".requests"]
+static MEMORY_MAP: Request<MemoryMapResponse> = Request::new([0x67CF3D9D378A806F, 0xE304ACDFC50C3C62]);
+
+#[used]
+#[link_section = ".requests"]
+static MODULES: Request<ModulesResponse> = Request::new([0x3E7E279702BE32AF, 0xCA1C4F3BD1280CEE]);
+
+#[used]
+#[link_section = ".requests"]
+static RSDP: Request<RSDPResponse> = Request::new([0xC5E77B6B397E7B43, 0x27637845ACCDCF3C]);
+
+#[used]
+#[link_section = ".requests"]
+static FRAMEBUFFER: Request<FramebufferResponse> = Request::new([0x9D5827DCD881DD75, 0xA3148604F6FAB11B]);
+
+// There's deliberately no SMP request. Without one, Limine leaves the application processors
+// waiting for a startup IPI, so arch::smp starts them the same way whatever the boot protocol.
+
+// Something the bootloader writes to before entering the kernel, behind Rust's back.
+#[repr(transparent)]
+struct Tag<T>(UnsafeCell<T>);
+
+unsafe impl<T> Sync for Tag<T> {}
+
+impl<T: Copy> Tag<T> {
+    const fn new(value: T) -> Tag<T> {
+        Tag(UnsafeCell::new(value))
+    }
+
+    fn get(&self) -> T {
+        unsafe { ptr::read_volatile(self.0.get()) }
+    }
+}
+
+#[repr(C)]
+struct Request<R: 'static> {
+    id:        [u64; 4],
+    _revision: u64,
+    response:  Tag<*const R>
+}
+
+unsafe impl<R> Sync for Request<R> {}
+
+impl<R> Request<R> {
+    const fn new(id: [u64; 2]) -> Request<R> {
+        Request {
+            id: [0xC7B1DD30DF4C8B88, 0x0A82E883A194F07B, id[0], id[1]],
+            _revision: 0,
+            response: Tag::new(ptr::null())
+        }
+    }
+
+    fn response(&self) -> Option<&'static R> {
+        unsafe { self.response.get().as_ref() }
+    }
+}
+
+#[repr(C)]
+struct BootloaderResponse {
+    _revision: u64,
+    name:      *const u8,
+    version:   *const u8
+}
+
+#[repr(C)]
+struct KernelFileResponse {
+    _revision: u64,
+    file:      *const File
+}
+
+#[repr(C)]
+struct KernelAddressResponse {
+    _revision:     u64,
+    physical_base: u64,
+    virtual_base:  u64
+}
+
+#[repr(C)]
+struct HHDMResponse {
+    _revision: u64,
+    offset:    u64
+}
+
+#[repr(C)]
+struct MemoryMapResponse {
+    _revision: u64,
+    count:     u64,
+    entries:   *const &'static Region
+}
+
+#[repr(C)]
+struct ModulesResponse {
+    _revision: u64,
+    count:     u64,
+    modules:   *const &'static File
+}
+
+#[repr(C)]
+struct RSDPResponse {
+    _revision: u64,
+    address:   *const u8
+}
+
+#[repr(C)]
+struct FramebufferResponse {
+    _revision:    u64,
+    count:        u64,
+    framebuffers: *const &'static Framebuffer
+}
+
+// A snapshot of the bootloader's responses. Any of them can be missing if the bootloader didn't
+// understand the request.
+#[derive(Clone, Copy)]
+pub struct Info {
+    bootloader:     Option<&'static BootloaderResponse>,
+    kernel_file:    Option<&'static KernelFileResponse>,
+    kernel_address: Option<&'static KernelAddressResponse>,
+    hhdm:           Option<&'static HHDMResponse>,
+    memory_map:     Option<&'static MemoryMapResponse>,
+    modules:        Option<&'static ModulesResponse>,
+    rsdp:           Option<&'static RSDPResponse>,
+    framebuffer:    Option<&'static FramebufferResponse>
+}
+
+// The responses are full of raw pointers, but nothing writes to them once the kernel is running.
+unsafe impl Send for Info {}
+unsafe impl Sync for Info {}
+
+impl Info {
+    // This is unsafe because it trusts that the responses, if any, were left by a Limine-compliant
+    // bootloader, and that its page tables are still active.
+    pub unsafe fn new() -> Info {
+        if BASE_REVISION.get()[2] != 0 {
+            panic!("The bootloader doesn't support Limine base revision 2");
+        }
+
+        Info {
+            bootloader:     BOOTLOADER.response(),
+            kernel_file:    KERNEL_FILE.response(),
+            kernel_address: KERNEL_ADDRESS.response(),
+            hhdm:           HHDM.response(),
+            memory_map:     MEMORY_MAP.response(),
+            modules:        MODULES.response(),
+            rsdp:           RSDP.response(),
+            framebuffer:    FRAMEBUFFER.response()
+        }
+    }
+
+    pub fn command_line(&self) -> Option<&'static str> {
+        self.kernel_file.and_then(|response| unsafe { response.file.as_ref() }).and_then(File::command_line)
+    }
+
+    pub fn bootloader_name(&self) -> Option<&'static str> {
+        self.bootloader.and_then(|response| string(response.name))
+    }
+
+    pub fn bootloader_version(&self) -> Option<&'static str> {
+        self.bootloader.and_then(|response| string(response.version))
+    }
+
+    // Returns where the bootloader put the kernel image, which needn't be where the linker script
+    // says, as a (physical, virtual) pair.
+    pub fn kernel_address(&self) -> Option<(PhysicalAddress, VirtualAddress)> {
+        self.kernel_address.map(|response| {
+            (PhysicalAddress::new(response.physical_base), VirtualAddress::new(response.virtual_base))
+        })
+    }
+
+    // Returns the offset of the higher-half direct map of physical memory.
+    pub fn direct_map_offset(&self) -> Option<u64> {
+        self.hhdm.map(|response| response.offset)
+    }
+
+    pub fn memory_map(&self) -> Option<MemoryMap> {
+        self.memory_map.map(|response| MemoryMap { response })
+    }
+
+    pub fn modules(&self) -> &'static [&'static File] {
+        self.modules.map_or(&[], |response| unsafe { slice(response.modules, response.count) })
+    }
+
+    // Returns the RSDP, which is in the direct map rather than copied as with Multiboot 2. Only the
+    // first 20 bytes are valid for an ACPI 1.0 RSDP, which the revision field says.
+    pub fn rsdp(&self) -> Option<&'static [u8]> {
+        self.rsdp.filter(|response| !response.address.is_null())
+            .map(|response| unsafe { core::slice::from_raw_parts(response.address, 36) })
+    }
+
+    pub fn framebuffers(&self) -> &'static [&'static Framebuffer] {
+        self.framebuffer.map_or(&[], |response| unsafe { slice(response.framebuffers, response.count) })
+    }
+}
+
+impl core::fmt::Display for Info {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(formatter, "Limine:")?;
+
+        if let Some(command_line) = self.command_line() {
+            writeln!(formatter, "Command line: {:?}", command_line)?;
+        }
+
+        if let Some(name) = self.bootloader_name() {
+            writeln!(formatter, "Bootloader: {} {}", name, self.bootloader_version().unwrap_or(""))?;
+        }
+
+        if let Some((physical, virtual_address)) = self.kernel_address() {
+            writeln!(formatter, "Kernel: {:#x} at {:#x}", physical, virtual_address.as_u64())?;
+        }
+
+        for module in self.modules() {
+            writeln!(formatter, "Module: [{:#16x} - {:#16x}] {}", module.start(), module.end(), module.command_line().unwrap_or(""))?;
+        }
+
+        for framebuffer in self.framebuffers() {
+            writeln!(formatter, "{}", framebuffer)?;
+        }
+
+        if let Some(map) = self.memory_map() {
+            writeln!(formatter, "Memory map: {} regions", map.regions().count())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[repr(C)]
+pub struct File {
+    _revision: u64,
+    address:   *const u8,
+    size:      u64,
+    _path:     *const u8,
+    cmdline:   *const u8
+}
+
+impl File {
+    pub fn start(&self) -> PhysicalAddress {
+        VirtualAddress::from(self.address).to_physical()
+    }
+
+    pub fn end(&self) -> PhysicalAddress {
+        self.start() + self.size
+    }
+
+    pub fn command_line(&self) -> Option<&'static str> {
+        string(self.cmdline)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MemoryMap {
+    response: &'static MemoryMapResponse
+}
+
+impl MemoryMap {
+    pub fn regions(&self) -> impl Iterator<Item = &'static Region> {
+        unsafe { slice(self.response.entries, self.response.count) }.iter().copied()
+    }
+}
+
+impl core::fmt::Display for MemoryMap {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for region in self.regions() {
+            writeln!(formatter, "{}", region)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[repr(C)]
+pub struct Region {
+    base:   u64,
+    length: u64,
+    kind:   u64
+}
+
+impl Region {
+    pub fn starts_at(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.base)
+    }
+
+    pub fn ends_at(&self) -> PhysicalAddress {
+        self.starts_at() + self.length
+    }
+
+    pub fn kind(&self) -> Kind {
+        Kind::from(self.kind)
+    }
+}
+
+impl core::fmt::Display for Region {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "[{:#16x} - {:#16x}] {}", self.starts_at(), self.ends_at(), self.kind())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Usable,
+    Reserved,
+    Reclaimable,
+    Nonvolatile,
+    Defective,
+    BootloaderReclaimable,
+    KernelAndModules,
+    Framebuffer
+}
+
+impl From<u64> for Kind {
+    fn from(kind: u64) -> Kind {
+        match kind {
+            0 => Kind::Usable,
+            2 => Kind::Reclaimable,
+            3 => Kind::Nonvolatile,
+            4 => Kind::Defective,
+            5 => Kind::BootloaderReclaimable,
+            6 => Kind::KernelAndModules,
+            7 => Kind::Framebuffer,
+            _ => Kind::Reserved
+        }
+    }
+}
+
+impl core::fmt::Display for Kind {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match self {
+            Kind::Usable                => "Usable",
+            Kind::Reserved              => "Reserved",
+            Kind::Reclaimable           => "Reclaimable (ACPI)",
+            Kind::Nonvolatile           => "Non-volatile (ACPI)",
+            Kind::Defective             => "Defective",
+            Kind::BootloaderReclaimable => "Bootloader reclaimable",
+            Kind::KernelAndModules      => "Kernel and modules",
+            Kind::Framebuffer           => "Framebuffer"
+        };
+
+        write!(formatter, "{}", name)
+    }
+}
+
+#[repr(C)]
+pub struct Framebuffer {
+    address:          *const u8,
+    width:            u64,
+    height:           u64,
+    pitch:            u64,
+    bits_per_pixel:   u16,
+    memory_model:     u8,
+    red_mask_size:    u8,
+    red_mask_shift:   u8,
+    green_mask_size:  u8,
+    green_mask_shift: u8,
+    blue_mask_size:   u8,
+    blue_mask_shift:  u8
+}
+
+impl Framebuffer {
+    pub fn address(&self) -> PhysicalAddress {
+        VirtualAddress::from(self.address).to_physical()
+    }
+}
+
+impl core::fmt::Display for Framebuffer {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "Framebuffer: {}x{}x{} at {:#x}", self.width, self.height, self.bits_per_pixel, self.address())
+    }
+}
+
+unsafe fn slice<T>(pointer: *const T, count: u64) -> &'static [T] {
+    if pointer.is_null() || count == 0 { &[] } else { core::slice::from_raw_parts(pointer, count as usize) }
+}
+
+// Reads a NUL-terminated string. Anything that isn't UTF-8 is treated as missing.
+fn string(start: *const u8) -> Option<&'static str> {
+    if start.is_null() {
+        return None
+    }
+
+    unsafe {
+        let length = (0..).take_while(|&index| *start.add(index) != 0).count();
+        core::str::from_utf8(core::slice::from_raw_parts(start, length)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::map;
+
+    #[test]
+    fn classifying_limine_memory_map_entries() {
+        let region = Region { base: 0x100000, length: 0x7F00000, kind: 0 };
+
+        assert_eq!(Kind::Usable, region.kind());
+        assert_eq!(PhysicalAddress::new(0x8000000), region.ends_at());
+
+        assert_eq!(Kind::Reserved, Kind::from(1));
+        assert_eq!(Kind::Reserved, Kind::from(42));
+
+        // Limine's page tables and responses live in bootloader-reclaimable memory, which we keep.
+        assert_eq!(map::Kind::BootInformation, map::Kind::from(Kind::BootloaderReclaimable));
+        assert_eq!(map::Kind::Available, map::Kind::from(Kind::Usable));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cmdline::{Value, ValueError};
+
+use arrayvec::ArrayString;
+use log::LevelFilter;
+
+// Records are attributed to the module that logged them, like `georgix::acpi::madt`. Directives
+// leave off the crate's name.
+const CRATE: &str = concat!(env!("CARGO_PKG_NAME"), "::");
+
+// Overrides the default log level for parts of the kernel, as in `acpi=debug,memory::paging=trace`.
+// Each directive covers a module and everything in it, and the most specific one wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Directives(ArrayString<128>);
+
+impl Directives {
+    // Returns the level for records from a module.
+    pub fn level_for(&self, target: &str, default: LevelFilter) -> LevelFilter {
+        let path = module(target);
+
+        self.iter()
+            .filter(|(module, _)| covers(module, path))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(default, |(_, level)| level)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, LevelFilter)> {
+        self.0.split(',').filter_map(|directive| parse(directive).ok())
+    }
+}
+
+impl Value for Directives {
+    fn parse(value: Option<&str>) -> Result<Directives, ValueError> {
+        let value = value.ok_or(ValueError::Missing)?;
+
+        for directive in value.split(',') {
+            parse(directive)?;
+        }
+
+        ArrayString::from(value).map(Directives).map_err(|_| ValueError::TooLong)
+    }
+}
+
+impl Value for LevelFilter {
+    fn parse(value: Option<&str>) -> Result<LevelFilter, ValueError> {
+        value.ok_or(ValueError::Missing)?.parse().map_err(|_| ValueError::Invalid)
+    }
+}
+
+// Returns the module a record came from, without the crate's name.
+pub fn module(target: &str) -> &str {
+    target.strip_prefix(CRATE).unwrap_or(target)
+}
+
+fn parse(directive: &str) -> Result<(&str, LevelFilter), ValueError> {
+    let (module, level) = directive.split_once('=').ok_or(ValueError::Invalid)?;
+
+    if module.is_empty() {
+        return Err(ValueError::Invalid)
+    }
+
+    Ok((module, LevelFilter::parse(Some(level))?))
+}
+
+// Whether a module is the given one or inside it. `memory` covers `memory::paging`, but not
+// `memoryless`.
+fn covers(module: &str, path: &str) -> bool {
+    match path.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directives(value: &str) -> Directives {
+        Directives::parse(Some(value)).unwrap()
+    }
+
+    #[test]
+    fn choosing_the_most_specific_directive() {
+        let directives = directives("acpi=debug,acpi::madt=trace,memory=off");
+
+        assert_eq!(LevelFilter::Debug, directives.level_for("georgix::acpi", LevelFilter::Info));
+        assert_eq!(LevelFilter::Debug, directives.level_for("georgix::acpi::fadt", LevelFilter::Info));
+        assert_eq!(LevelFilter::Trace, directives.level_for("georgix::acpi::madt", LevelFilter::Info));
+        assert_eq!(LevelFilter::Off, directives.level_for("georgix::memory::paging", LevelFilter::Info));
+        assert_eq!(LevelFilter::Info, directives.level_for("georgix::acpilike", LevelFilter::Info));
+        assert_eq!(LevelFilter::Info, directives.level_for("georgix", LevelFilter::Info));
+    }
+
+    #[test]
+    fn rejecting_malformed_directives() {
+        assert_eq!(Err(ValueError::Invalid), Directives::parse(Some("acpi")));
+        assert_eq!(Err(ValueError::Invalid), Directives::parse(Some("acpi=loud")));
+        assert_eq!(Err(ValueError::Invalid), Directives::parse(Some("=debug")));
+        assert_eq!(Err(ValueError::Missing), Directives::parse(None));
+    }
+}
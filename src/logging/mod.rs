@@ -0,0 +1,213 @@
+mod filter;
+use filter::Directives;
+
+mod ring;
+use ring::Ring;
+
+use crate::cmdline::{Parameter, Value, ValueError};
+use crate::console::{self, Device};
+use crate::arch::interrupts::suppress;
+use crate::{arch, vga};
+
+use bitflags::bitflags;
+use core::fmt::Write;
+use core::time::Duration;
+use log::{LevelFilter, Log, Metadata, Record};
+use spin::{Mutex, RwLock};
+
+// How much detail to log: `loglevel=off`, `error`, `warn`, `info`, `debug` or `trace`.
+pub static LEVEL: Parameter<LevelFilter> = Parameter::new("loglevel", LevelFilter::Info);
+
+// Different levels for parts of the kernel, as in `log.filter=acpi=debug,memory::paging=trace`.
+pub static FILTER: Parameter<Option<Directives>> = Parameter::new("log.filter", None);
+
+// Where to send records: any of `vga`, `serial` and `debugcon`, separated by commas. Without it,
+// they go to the console.
+pub static SINKS: Parameter<Option<Sinks>> = Parameter::new("log.sinks", None);
+
+// Prints the most recent records before the message on a panic: `log.dump`. They're kept
+// whichever sinks are in use, so this is how to see them when the console gets none.
+pub static DUMP: Parameter<bool> = Parameter::new("log.dump", false);
+
+// How much of the log to keep in memory for `dump`.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+static BUFFER: Mutex<Ring<BUFFER_SIZE>> = Mutex::new(Ring::new());
+static CLOCK: RwLock<Option<fn() -> Duration>> = RwLock::new(None);
+static LOGGER: Logger = Logger;
+
+bitflags! {
+    pub struct Sinks: u8 {
+        const VGA      = 1 << 0;
+        const SERIAL   = 1 << 1;
+        const DEBUGCON = 1 << 2;
+    }
+}
+
+impl From<Device> for Sinks {
+    fn from(device: Device) -> Sinks {
+        match device {
+            Device::VGA => Sinks::VGA,
+            Device::Serial => Sinks::SERIAL
+        }
+    }
+}
+
+impl Value for Sinks {
+    fn parse(value: Option<&str>) -> Result<Sinks, ValueError> {
+        value.ok_or(ValueError::Missing)?.split(',').try_fold(Sinks::empty(), |sinks, name| {
+            let sink = match name {
+                "vga" => Sinks::VGA,
+                "serial" => Sinks::SERIAL,
+                "debugcon" => Sinks::DEBUGCON,
+                _ => return Err(ValueError::Invalid)
+            };
+
+            Ok(sinks | sink)
+        })
+    }
+}
+
+// Takes over the `log` macros. The parameters are checked for every record, so the command line
+// takes effect as soon as it's applied, and anything logged before then uses the defaults.
+pub fn initialize() {
+    log::set_logger(&LOGGER).expect("a logger is already set");
+    log::set_max_level(LevelFilter::Trace);
+}
+
+// Stamps records with the time since boot, once there's a clock to tell it. Until then, they're
+// stamped with zero.
+pub fn set_clock(clock: fn() -> Duration) {
+    CLOCK.write().replace(clock);
+}
+
+// Writes out the most recent records, oldest first. It gives up rather than waiting if the buffer's
+// in use, since a panic can come while a record's being written.
+pub fn dump(output: &mut dyn Write) -> core::fmt::Result {
+    suppress(|| output.write_str(BUFFER.try_lock().ok_or(core::fmt::Error)?.text()))
+}
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = match FILTER.get() {
+            Some(directives) => directives.level_for(metadata.target(), LEVEL.get()),
+            None => LEVEL.get()
+        };
+
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return
+        }
+
+        // A panic while the clock is being set shouldn't wait for it.
+        let timestamp = CLOCK.try_read().and_then(|clock| *clock).map_or(Duration::from_secs(0), |clock| clock());
+        let line = Line { timestamp, record };
+
+        suppress(|| writeln!(BUFFER.lock(), "{}", line).unwrap());
+
+        let sinks = SINKS.get().unwrap_or_else(|| Sinks::from(console::DEVICE.get()));
+
+        if sinks.contains(Sinks::VGA) {
+            vga::text::console::print(format_args!("{}\n", line));
+        }
+
+        if sinks.contains(Sinks::SERIAL) {
+            arch::serial::print(format_args!("{}\n", line));
+        }
+
+        if sinks.contains(Sinks::DEBUGCON) {
+            arch::debugcon::print(format_args!("{}\n", line));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// A record as it's printed, like `[    1.500000] WARN  acpi: Ignoring HPET`.
+struct Line<'a> {
+    timestamp: Duration,
+    record:    &'a Record<'a>
+}
+
+impl core::fmt::Display for Line<'_> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            formatter, "[{:>5}.{:06}] {:<5} {}: ",
+            self.timestamp.as_secs(), self.timestamp.subsec_micros(), self.record.level(), filter::module(self.record.target())
+        )?;
+
+        // Every record ends up on a line of its own, so drop a newline at the end of the message.
+        TrimEnd { formatter, newline: false }.write_fmt(*self.record.args())
+    }
+}
+
+// Passes text through, holding back a newline at the end of each piece until more text follows.
+struct TrimEnd<'a, 'b> {
+    formatter: &'a mut core::fmt::Formatter<'b>,
+    newline:   bool
+}
+
+impl core::fmt::Write for TrimEnd<'_, '_> {
+    fn write_str(&mut self, string: &str) -> core::fmt::Result {
+        if string.is_empty() {
+            return Ok(())
+        }
+
+        if core::mem::take(&mut self.newline) {
+            self.formatter.write_char('\n')?;
+        }
+
+        match string.strip_suffix('\n') {
+            Some(rest) => {
+                self.newline = true;
+                self.formatter.write_str(rest)
+            }
+
+            None => self.formatter.write_str(string)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+    use log::Level;
+
+    fn line(level: Level, target: &str, args: core::fmt::Arguments) -> String {
+        let record = Record::builder().args(args).level(level).target(target).build();
+        Line { timestamp: Duration::from_micros(1_500_042), record: &record }.to_string()
+    }
+
+    #[test]
+    fn formatting_records() {
+        assert_eq!("[    1.500042] WARN  acpi: Ignoring HPET", line(Level::Warn, "georgix::acpi", format_args!("Ignoring {}", "HPET")));
+        assert_eq!("[    1.500042] INFO  georgix: Memory map:\none\ntwo", line(Level::Info, "georgix", format_args!("Memory map:\n{}", "one\ntwo\n")));
+    }
+
+    #[test]
+    fn dumping_the_buffer() {
+        suppress(|| writeln!(BUFFER.lock(), "dumping_the_buffer").unwrap());
+
+        let mut text = String::new();
+        dump(&mut text).unwrap();
+        assert!(text.ends_with("dumping_the_buffer\n"));
+
+        suppress(|| {
+            let _buffer = BUFFER.lock();
+            assert_eq!(Err(core::fmt::Error), dump(&mut String::new()));
+        });
+    }
+
+    #[test]
+    fn choosing_sinks() {
+        assert_eq!(Ok(Sinks::VGA | Sinks::DEBUGCON), Sinks::parse(Some("vga,debugcon")));
+        assert_eq!(Err(ValueError::Invalid), Sinks::parse(Some("serial,printer")));
+        assert_eq!(Sinks::SERIAL, Sinks::from(Device::Serial));
+    }
+}
@@ -0,0 +1,87 @@
+// A fixed-size buffer of the most recent log lines. Once it's full, the oldest lines are dropped to
+// make room, so it always starts at the beginning of a line.
+pub struct Ring<const N: usize> {
+    bytes:  [u8; N],
+    start:  usize,
+    length: usize
+}
+
+impl<const N: usize> Ring<N> {
+    pub const fn new() -> Ring<N> {
+        Ring { bytes: [0; N], start: 0, length: 0 }
+    }
+
+    // Returns everything in the buffer, oldest first.
+    pub fn text(&mut self) -> &str {
+        // Line everything up at the start of the buffer, so it can be returned as one slice.
+        self.bytes.rotate_left(self.start);
+        self.start = 0;
+
+        let bytes = &self.bytes[..self.length];
+
+        // A single line longer than the whole buffer can leave part of a character at the start.
+        let partial = bytes.iter().take_while(|&&byte| byte & 0b1100_0000 == 0b1000_0000).count();
+        core::str::from_utf8(&bytes[partial..]).unwrap_or("")
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.length == N {
+            self.drop_line();
+        }
+
+        self.bytes[(self.start + self.length) % N] = byte;
+        self.length += 1;
+    }
+
+    fn drop_line(&mut self) {
+        while self.length > 0 {
+            let byte = self.bytes[self.start];
+
+            self.start = (self.start + 1) % N;
+            self.length -= 1;
+
+            if byte == b'\n' {
+                break
+            }
+        }
+    }
+}
+
+impl<const N: usize> core::fmt::Write for Ring<N> {
+    fn write_str(&mut self, string: &str) -> core::fmt::Result {
+        for byte in string.bytes() {
+            self.push(byte);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    #[test]
+    fn keeping_lines_in_order() {
+        let mut ring = Ring::<32>::new();
+
+        write!(ring, "one\ntwo\n").unwrap();
+        assert_eq!("one\ntwo\n", ring.text());
+
+        write!(ring, "three\n").unwrap();
+        assert_eq!("one\ntwo\nthree\n", ring.text());
+    }
+
+    #[test]
+    fn dropping_the_oldest_lines() {
+        let mut ring = Ring::<16>::new();
+
+        write!(ring, "first\nsecond\n").unwrap();
+        write!(ring, "third\n").unwrap();
+        assert_eq!("second\nthird\n", ring.text());
+
+        write!(ring, "fourth\nfifth\n").unwrap();
+        assert_eq!("fourth\nfifth\n", ring.text());
+    }
+}
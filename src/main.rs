@@ -3,58 +3,87 @@
 #![feature(asm, global_asm)]
 #![feature(abi_x86_interrupt)]
 #![feature(const_generics_defaults)]
+#![feature(alloc_error_handler)]
 
 #![reexport_test_harness_main = "test"]
 
+extern crate alloc;
+
 mod arch;
+mod boot;
 mod multiboot;
+mod limine;
+mod cmdline;
+mod console;
+mod logging;
 mod acpi;
 mod vga;
 mod memory;
+mod initramfs;
+mod backtrace;
+mod time;
 mod util;
 mod test;
 
-use arch::park;
-
-#[cfg(not(test))]
-pub use vga::text::console;
-
-#[cfg(test)]
-pub use arch::test::console;
+use log::info;
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 #[no_mangle]
-pub extern "C" fn main(magic: multiboot::Magic, info: &'static multiboot::Info) -> ! {
+pub extern "C" fn main(magic: multiboot::Magic, info: memory::PhysicalAddress) -> ! {
     // Initialize the console early for printing and panic handling.
     console::initialize();
+    logging::initialize();
 
-    multiboot::magic::validate(magic);
-    multiboot::info::set(info);
+    let info = unsafe { boot::Info::new(magic, info) };
+    boot::set(info);
 
-    println!("Georgix v{}", VERSION);
+    cmdline::initialize(info.command_line().unwrap_or(""));
+    backtrace::initialize(&info);
 
-    arch::initialize();
+    info!("Georgix v{}", VERSION);
+    info!("{}", info);
 
     if let Some(memory_map) = info.memory_map() {
-        print!("Memory map:\n{}", memory_map);
+        info!("Memory map:\n{}", memory_map);
 
-        memory::initialize(memory_map);
+        memory::initialize(memory_map, &info);
     } else {
         panic!("Memory map not found");
     }
 
+    arch::initialize();
+
+    // The initramfs can need the heap to grow, which needs the kernel's page tables.
+    initramfs::initialize(&info);
+
+    time::initialize();
+
+    // Starting the other CPUs waits on timers.
+    arch::smp::initialize();
+    memory::slab::reclaim_periodically();
+
+    for statistics in memory::slab::statistics() {
+        info!("{}", statistics);
+    }
+
     #[cfg(test)]
     test();
 
-    park();
+    console::echo();
 }
 
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
+    if logging::DUMP.get() {
+        println!("Log:");
+        logging::dump(&mut console::Writer).ok();
+    }
+
     println!("{}", info);
-    park();
+    print!("{}", backtrace::Backtrace::here());
+    arch::park();
 }
 
 #[macro_export]
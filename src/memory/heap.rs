@@ -0,0 +1,294 @@
+use crate::arch::memory::{paging::{self, PageSize, Flags, MappingError}, KERNEL_HEAP};
+use crate::arch::interrupts::suppress;
+use crate::util::alignment::align_up;
+use crate::memory::{self, VirtualAddress, AllocationError, FRAME_SIZE};
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+
+#[global_allocator]
+static HEAP: KernelHeap = KernelHeap {
+    heap: Mutex::new(Heap::new()),
+    top:  AtomicU64::new(KERNEL_HEAP.start)
+};
+
+// The heap grows by at least this much at a time so that page table updates stay infrequent.
+const MINIMUM_GROWTH: usize = 16 * FRAME_SIZE;
+
+// Backs the global allocator. The heap starts out empty and maps fresh frames above everything
+// it already holds whenever an allocation doesn't fit.
+struct KernelHeap {
+    heap: Mutex<Heap>,
+
+    // The end of the mapped part of the heap's virtual range. Only changed with the heap locked.
+    top: AtomicU64
+}
+
+impl KernelHeap {
+    // Maps enough frames at the top of the heap to satisfy the given allocation.
+    fn grow(&self, heap: &mut Heap, layout: Layout) -> Result<(), MappingError> {
+        let size = align_up(layout.size() + layout.align(), FRAME_SIZE).max(MINIMUM_GROWTH);
+        let start = self.top.load(Ordering::Relaxed);
+
+        if KERNEL_HEAP.end - start < size as u64 {
+            return Err(AllocationError::OutOfMemory { size, alignment: layout.align() }.into())
+        }
+
+        let mut mapped = 0;
+        let mut result = Ok(());
+
+        while mapped < size {
+            let page = VirtualAddress::new(start + mapped as u64);
+
+            result = memory::allocate_frames(0).map_err(MappingError::from).and_then(|frame| {
+                paging::map(page, frame, PageSize::Size4KiB, Flags::WRITABLE).map_err(|error| {
+                    memory::free_frames(frame, 0);
+                    error
+                })
+            });
+
+            if result.is_err() {
+                break
+            }
+
+            mapped += FRAME_SIZE;
+        }
+
+        // Whatever was mapped before a failure is still usable.
+        if mapped > 0 {
+            unsafe { heap.extend(start as *mut u8, mapped) }
+            self.top.store(start + mapped as u64, Ordering::Relaxed);
+        }
+
+        result
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        suppress(|| {
+            let mut heap = self.heap.lock();
+
+            heap.allocate(layout)
+                .or_else(|| self.grow(&mut heap, layout).ok().and_then(|_| heap.allocate(layout)))
+                .map_or(ptr::null_mut(), NonNull::as_ptr)
+        })
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        suppress(|| self.heap.lock().deallocate(NonNull::new_unchecked(pointer), layout))
+    }
+}
+
+#[alloc_error_handler]
+fn out_of_memory(layout: Layout) -> ! {
+    panic!("out of memory allocating {} bytes aligned to {}", layout.size(), layout.align())
+}
+
+
+// Every block is a multiple of this size and aligned to it, so that a free block header fits in
+// any block and any leftover padding can go back on the free list.
+const GRANULE: usize = 16;
+
+// A first-fit allocator over a linked list of free blocks. The list is kept sorted by address so
+// that neighbouring blocks can be merged as they're freed.
+pub struct Heap {
+    head: *mut Block
+}
+
+// The heap only hands out memory it was given, and it's always used behind a lock.
+unsafe impl Send for Heap {}
+
+struct Block {
+    size: usize,
+    next: *mut Block
+}
+
+impl Heap {
+    pub const fn new() -> Heap {
+        Heap { head: ptr::null_mut() }
+    }
+
+    // Adds memory to the heap. The memory must be unused, writable, and never freed.
+    pub unsafe fn extend(&mut self, start: *mut u8, size: usize) {
+        let address = align_up(start as usize, GRANULE);
+        let end = (start as usize + size) & !(GRANULE - 1);
+
+        if address < end {
+            self.insert(address, end - address)
+        }
+    }
+
+    pub fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let (size, alignment) = Heap::adjust(layout);
+
+        let mut previous: *mut Block = ptr::null_mut();
+        let mut current = self.head;
+
+        unsafe {
+            while !current.is_null() {
+                let start = current as usize;
+                let end = start + (*current).size;
+                let address = align_up(start, alignment);
+
+                if address + size <= end {
+                    if previous.is_null() {
+                        self.head = (*current).next
+                    } else {
+                        (*previous).next = (*current).next
+                    }
+
+                    // The padding before the allocation and the remainder after it are both whole
+                    // granules, so they go back on the free list.
+                    if address > start {
+                        self.insert(start, address - start)
+                    }
+
+                    if end > address + size {
+                        self.insert(address + size, end - address - size)
+                    }
+
+                    return NonNull::new(address as *mut u8)
+                }
+
+                previous = current;
+                current = (*current).next;
+            }
+        }
+
+        None
+    }
+
+    // Returns memory to the heap. It must have come from `allocate` with the same layout.
+    pub unsafe fn deallocate(&mut self, pointer: NonNull<u8>, layout: Layout) {
+        let (size, _) = Heap::adjust(layout);
+        self.insert(pointer.as_ptr() as usize, size)
+    }
+
+    // Puts a block on the free list in address order, merging it with its neighbours if they touch.
+    unsafe fn insert(&mut self, address: usize, size: usize) {
+        let mut previous: *mut Block = ptr::null_mut();
+        let mut next = self.head;
+
+        while !next.is_null() && (next as usize) < address {
+            previous = next;
+            next = (*next).next;
+        }
+
+        assert!(
+            (previous.is_null() || previous as usize + (*previous).size <= address) &&
+                (next.is_null() || address + size <= next as usize),
+            "heap block at {:#x} overlaps free memory", address
+        );
+
+        let block = address as *mut Block;
+        block.write(Block { size, next });
+
+        if !next.is_null() && address + size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+
+        if previous.is_null() {
+            self.head = block
+        } else if previous as usize + (*previous).size == address {
+            (*previous).size += (*block).size;
+            (*previous).next = (*block).next;
+        } else {
+            (*previous).next = block
+        }
+    }
+
+    fn adjust(layout: Layout) -> (usize, usize) {
+        (align_up(layout.size().max(GRANULE), GRANULE), layout.align().max(GRANULE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec::Vec, collections::BTreeMap};
+
+    const SIZE: usize = 0x1000;
+
+    #[repr(align(4096))]
+    struct Memory([u8; SIZE]);
+
+    static mut MEMORY: Memory = Memory([0; SIZE]);
+
+    fn heap() -> (Heap, usize) {
+        let mut heap = Heap::new();
+
+        // This is safe because tests run one at a time, and each builds a fresh heap.
+        unsafe {
+            let start = MEMORY.0.as_mut_ptr();
+            heap.extend(start, SIZE);
+            (heap, start as usize)
+        }
+    }
+
+    fn layout(size: usize, alignment: usize) -> Layout {
+        Layout::from_size_align(size, alignment).unwrap()
+    }
+
+    #[test]
+    fn allocating_from_a_heap() {
+        let (mut heap, start) = heap();
+
+        assert_eq!(Some(start), heap.allocate(layout(8, 8)).map(|pointer| pointer.as_ptr() as usize));
+        assert_eq!(Some(start + 16), heap.allocate(layout(20, 4)).map(|pointer| pointer.as_ptr() as usize));
+        assert_eq!(Some(start + 48), heap.allocate(layout(1, 1)).map(|pointer| pointer.as_ptr() as usize));
+    }
+
+    #[test]
+    fn allocating_aligned_memory_from_a_heap() {
+        let (mut heap, start) = heap();
+
+        heap.allocate(layout(16, 16)).unwrap();
+        assert_eq!(Some(start + 0x100), heap.allocate(layout(16, 0x100)).map(|pointer| pointer.as_ptr() as usize));
+
+        // The gap left behind by alignment is still available.
+        assert_eq!(Some(start + 16), heap.allocate(layout(16, 16)).map(|pointer| pointer.as_ptr() as usize));
+    }
+
+    #[test]
+    fn merging_freed_blocks() {
+        let (mut heap, start) = heap();
+
+        let first = heap.allocate(layout(0x400, 8)).unwrap();
+        let second = heap.allocate(layout(0x400, 8)).unwrap();
+        let third = heap.allocate(layout(0x800, 8)).unwrap();
+        assert_eq!(None, heap.allocate(layout(16, 16)));
+
+        unsafe {
+            heap.deallocate(second, layout(0x400, 8));
+            heap.deallocate(first, layout(0x400, 8));
+            heap.deallocate(third, layout(0x800, 8));
+        }
+
+        assert_eq!(Some(start), heap.allocate(layout(SIZE, 8)).map(|pointer| pointer.as_ptr() as usize));
+    }
+
+    #[test]
+    fn running_out_of_heap_memory() {
+        let (mut heap, _) = heap();
+        assert_eq!(None, heap.allocate(layout(SIZE + 1, 8)));
+    }
+
+    #[test]
+    fn using_the_global_heap() {
+        let boxed = Box::new(42u64);
+        assert_eq!(42, *boxed);
+
+        // Big enough to make the heap grow.
+        let vector: Vec<u64> = (0..0x10000).collect();
+        assert_eq!(0x7FFF8000, vector.iter().sum::<u64>());
+
+        let mut map = BTreeMap::new();
+        map.insert("georgix", 1);
+        map.insert("linux", 2);
+        assert_eq!(Some(&2), map.get("linux"));
+    }
+}
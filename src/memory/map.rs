@@ -0,0 +1,281 @@
+use crate::multiboot::info::memory as firmware;
+use crate::limine;
+use crate::memory::{PhysicalAddress, FRAME_SIZE};
+use crate::boot::MemoryMap;
+
+use core::ops::Range;
+use arrayvec::ArrayVec;
+
+// A physical memory map that can be trusted: regions are sorted, don't overlap, and adjacent
+// regions of the same kind are merged. Available regions start and end on frame boundaries.
+//
+// Firmware maps can be unsorted, overlap, or contain empty entries, so entries are collected as
+// they are and only resolved by `normalized`. Everything lives in fixed-size arrays because this
+// runs before there is a heap.
+pub struct Map<const S: usize = 128> {
+    regions: ArrayVec<Region, S>
+}
+
+impl<const S: usize> Map<S> {
+    pub fn new() -> Map<S> {
+        Map { regions: ArrayVec::new() }
+    }
+
+    pub fn add(&mut self, range: Range<PhysicalAddress>, kind: Kind) {
+        if range.start < range.end {
+            self.regions.try_push(Region { range, kind }).expect("too many memory map entries");
+        }
+    }
+
+    pub fn add_from(&mut self, map: &MemoryMap) {
+        for region in map.regions() {
+            self.add(region.range, region.kind)
+        }
+    }
+
+    // Resolves overlaps in favour of the more restrictive kind, then merges and aligns the result.
+    pub fn normalized(&self) -> Map<S> {
+        let mut map = Map::new();
+
+        // Every change of kind happens where some entry starts or ends, so sweep from one of those
+        // points to the next. Maps are short enough that searching for each one is fine.
+        let boundaries = || self.regions.iter().flat_map(|region| [region.range.start, region.range.end]);
+        let mut position = boundaries().min();
+
+        while let Some(start) = position {
+            position = boundaries().filter(|&boundary| boundary > start).min();
+
+            if let Some(end) = position {
+                let kind = self.regions.iter()
+                    .filter(|region| region.range.start <= start && end <= region.range.end)
+                    .map(|region| region.kind)
+                    .max();
+
+                if let Some(kind) = kind {
+                    map.push(start..end, kind)
+                }
+            }
+        }
+
+        map.align_available();
+        map
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter()
+    }
+
+    pub fn available(&self) -> impl Iterator<Item = Range<PhysicalAddress>> + '_ {
+        self.regions().filter(|region| region.kind == Kind::Available).map(|region| region.range.clone())
+    }
+
+    // Appends a region that starts at or after the end of the last one, merging the two if they
+    // touch and are of the same kind.
+    fn push(&mut self, range: Range<PhysicalAddress>, kind: Kind) {
+        if let Some(last) = self.regions.last_mut() {
+            if last.kind == kind && last.range.end == range.start {
+                last.range.end = range.end;
+                return
+            }
+        }
+
+        self.regions.try_push(Region { range, kind }).expect("too many memory map entries");
+    }
+
+    // Shrinks available regions to whole frames, dropping any that don't contain one. The slivers
+    // are left out of the map entirely rather than handed out as partial frames.
+    fn align_available(&mut self) {
+        self.regions.retain(|region| {
+            if region.kind == Kind::Available {
+                let start = region.range.start.align_up(FRAME_SIZE);
+                let end = region.range.end.align_down(FRAME_SIZE);
+
+                if start >= end {
+                    return false
+                }
+
+                region.range = start..end;
+            }
+
+            true
+        })
+    }
+}
+
+impl<const S: usize> core::fmt::Display for Map<S> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for region in self.regions() {
+            writeln!(formatter, "{}", region)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<PhysicalAddress>,
+    pub kind:  Kind
+}
+
+impl core::fmt::Display for Region {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "[{:#16x} - {:#16x}] {}", self.range.start, self.range.end, self.kind)
+    }
+}
+
+// Kinds are ordered from least to most restrictive. Where entries overlap, the later kind wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Available,
+    Reclaimable,
+    BootInformation,
+    Module,
+    Kernel,
+    Nonvolatile,
+    Reserved,
+    Defective
+}
+
+impl From<firmware::Kind> for Kind {
+    fn from(kind: firmware::Kind) -> Kind {
+        match kind {
+            firmware::Kind::Available   => Kind::Available,
+            firmware::Kind::Reclaimable => Kind::Reclaimable,
+            firmware::Kind::Nonvolatile => Kind::Nonvolatile,
+            firmware::Kind::Reserved    => Kind::Reserved,
+            firmware::Kind::Defective   => Kind::Defective
+        }
+    }
+}
+
+// Limine hands its own page tables, stack and responses to the kernel in bootloader-reclaimable
+// memory, so that stays put until something has copied out what it needs.
+impl From<limine::Kind> for Kind {
+    fn from(kind: limine::Kind) -> Kind {
+        match kind {
+            limine::Kind::Usable                => Kind::Available,
+            limine::Kind::Reclaimable           => Kind::Reclaimable,
+            limine::Kind::BootloaderReclaimable => Kind::BootInformation,
+            limine::Kind::KernelAndModules      => Kind::Kernel,
+            limine::Kind::Nonvolatile           => Kind::Nonvolatile,
+            limine::Kind::Reserved              => Kind::Reserved,
+            limine::Kind::Framebuffer           => Kind::Reserved,
+            limine::Kind::Defective             => Kind::Defective
+        }
+    }
+}
+
+impl core::fmt::Display for Kind {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match self {
+            Kind::Available       => "Available",
+            Kind::Reclaimable     => "Reclaimable (ACPI)",
+            Kind::BootInformation => "Boot information",
+            Kind::Module          => "Boot module",
+            Kind::Kernel          => "Kernel",
+            Kind::Nonvolatile     => "Non-volatile (ACPI)",
+            Kind::Reserved        => "Reserved",
+            Kind::Defective       => "Defective"
+        };
+
+        write!(formatter, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(u64, u64, Kind)]) -> Map<16> {
+        let mut map = Map::new();
+
+        for &(start, end, kind) in entries {
+            map.add(PhysicalAddress::new(start)..PhysicalAddress::new(end), kind);
+        }
+
+        map.normalized()
+    }
+
+    fn regions(map: &Map<16>) -> ArrayVec<(u64, u64, Kind), 16> {
+        map.regions().map(|region| (region.range.start.as_u64(), region.range.end.as_u64(), region.kind)).collect()
+    }
+
+    #[test]
+    fn sorting_and_merging_regions() {
+        let map = map(&[
+            (0x200000, 0x300000, Kind::Available),
+            (0x100000, 0x200000, Kind::Available),
+            (0x400000, 0x500000, Kind::Available),
+            (0x300000, 0x400000, Kind::Reserved)
+        ]);
+
+        assert_eq!(&regions(&map)[..], &[
+            (0x100000, 0x300000, Kind::Available),
+            (0x300000, 0x400000, Kind::Reserved),
+            (0x400000, 0x500000, Kind::Available)
+        ]);
+    }
+
+    #[test]
+    fn preferring_the_more_restrictive_kind_when_regions_overlap() {
+        let map = map(&[
+            (0x100000, 0x200000, Kind::Available),
+            (0x180000, 0x280000, Kind::Reclaimable),
+            (0x1C0000, 0x1D0000, Kind::Defective),
+            (0x000000, 0x110000, Kind::Reserved)
+        ]);
+
+        assert_eq!(&regions(&map)[..], &[
+            (0x000000, 0x110000, Kind::Reserved),
+            (0x110000, 0x180000, Kind::Available),
+            (0x180000, 0x1C0000, Kind::Reclaimable),
+            (0x1C0000, 0x1D0000, Kind::Defective),
+            (0x1D0000, 0x280000, Kind::Reclaimable)
+        ]);
+    }
+
+    #[test]
+    fn aligning_available_regions_inward() {
+        let map = map(&[
+            (0x100800, 0x1FF800, Kind::Available),
+            (0x200010, 0x200FF0, Kind::Available),
+            (0x300800, 0x301000, Kind::Reserved)
+        ]);
+
+        // Reserved regions are left as the firmware described them.
+        assert_eq!(&regions(&map)[..], &[
+            (0x101000, 0x1FF000, Kind::Available),
+            (0x300800, 0x301000, Kind::Reserved)
+        ]);
+    }
+
+    #[test]
+    fn ignoring_empty_regions() {
+        let map = map(&[
+            (0x100000, 0x100000, Kind::Reserved),
+            (0x100000, 0x200000, Kind::Available)
+        ]);
+
+        assert_eq!(&regions(&map)[..], &[(0x100000, 0x200000, Kind::Available)]);
+    }
+
+    #[test]
+    fn carving_out_the_kernel_and_boot_information() {
+        let map = map(&[
+            (0x000000, 0x09F000, Kind::Available),
+            (0x100000, 0x8000000, Kind::Available),
+            (0x100000, 0x180400, Kind::Kernel),
+            (0x181000, 0x182200, Kind::BootInformation)
+        ]);
+
+        assert_eq!(&regions(&map)[..], &[
+            (0x000000, 0x09F000, Kind::Available),
+            (0x100000, 0x180400, Kind::Kernel),
+            (0x181000, 0x182200, Kind::BootInformation),
+            (0x183000, 0x8000000, Kind::Available)
+        ]);
+
+        assert!(map.available().all(|range| range.start.is_aligned(FRAME_SIZE) && range.end.is_aligned(FRAME_SIZE)));
+    }
+}
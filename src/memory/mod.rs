@@ -1,13 +1,81 @@
 mod physical;
-use physical::EarlyPhysicalFrameAllocator;
+mod heap;
+pub mod slab;
+pub mod map;
+use physical::{EarlyPhysicalFrameAllocator, BuddyAllocator};
+pub use physical::{AllocationError, Statistics};
 
-use crate::multiboot::info::memory::MemoryMap;
+use crate::boot::{self, MemoryMap};
+use crate::arch::interrupts::suppress;
+use map::{Map, Kind};
+use log::info;
 use spin::Mutex;
 
-pub use crate::arch::memory::{VirtualAddress, PhysicalAddress};
+pub use crate::arch::memory::{VirtualAddress, PhysicalAddress, FRAME_SIZE};
 
-static ALLOCATOR: Mutex<Option<EarlyPhysicalFrameAllocator>> = Mutex::new(None);
+static ALLOCATOR: Mutex<Option<BuddyAllocator>> = Mutex::new(None);
 
-pub fn initialize(map: MemoryMap<'static>) {
-    ALLOCATOR.lock().replace(EarlyPhysicalFrameAllocator::new_from(map));
+pub fn initialize(firmware: MemoryMap, info: &boot::Info) {
+    let mut map: Map = Map::new();
+    map.add_from(&firmware);
+
+    map.add(crate::arch::memory::kernel_image(), Kind::Kernel);
+    for extent in info.extents() {
+        map.add(extent, Kind::BootInformation);
+    }
+
+    for module in info.modules() {
+        map.add(module.start..module.end, Kind::Module);
+    }
+
+    // Application processors start in real mode, so they need a few frames below 1 MiB that
+    // nothing else is using, boot information included.
+    if let Some(trampoline) = crate::arch::smp::reserve(map.normalized().available()) {
+        info!("SMP trampoline: [{:#16x} - {:#16x}]", trampoline.start, trampoline.end);
+        map.add(trampoline, Kind::Reserved);
+    }
+
+    // The first megabyte holds the real-mode interrupt vector table, the BIOS data area, the EBDA,
+    // and the BIOS ROM, where the ACPI RSDP lives. The remaining ACPI tables sit in regions the
+    // firmware marks reclaimable or non-volatile, so they never make it into the available list.
+    map.add(PhysicalAddress::zero()..PhysicalAddress::new(0x100000), Kind::Reserved);
+
+    let map = map.normalized();
+    info!("Physical memory:\n{}", map);
+
+    let early: EarlyPhysicalFrameAllocator = EarlyPhysicalFrameAllocator::new_from(&map);
+
+    // Hand the rest of physical memory over to the buddy allocator, which can take it back.
+    let allocator = BuddyAllocator::new_from(early).expect("failed to set up the physical frame allocator");
+    info!("{}", allocator.statistics());
+
+    ALLOCATOR.lock().replace(allocator);
+}
+
+// Allocates 2^order contiguous physical frames. If none are free, empty slabs are reclaimed first.
+pub fn allocate_frames(order: usize) -> Result<PhysicalAddress, AllocationError> {
+    with_allocator(|allocator| allocator.allocate_frames(order)).or_else(|error| {
+        if slab::reclaim() > 0 {
+            with_allocator(|allocator| allocator.allocate_frames(order))
+        } else {
+            Err(error)
+        }
+    })
+}
+
+pub fn free_frames(address: PhysicalAddress, order: usize) {
+    with_allocator(|allocator| allocator.free_frames(address, order))
+}
+
+// Returns the address just past the end of physical memory.
+pub fn physical_memory_end() -> PhysicalAddress {
+    with_allocator(|allocator| allocator.end())
+}
+
+pub fn statistics() -> Statistics {
+    with_allocator(|allocator| allocator.statistics())
+}
+
+fn with_allocator<F, R>(f: F) -> R where F: FnOnce(&mut BuddyAllocator) -> R {
+    suppress(|| f(ALLOCATOR.lock().as_mut().expect("physical memory allocator isn't initialized")))
 }
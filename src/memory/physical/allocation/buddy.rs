@@ -0,0 +1,319 @@
+use crate::memory::{PhysicalAddress, FRAME_SIZE};
+use super::{AllocationError, EarlyPhysicalFrameAllocator};
+
+use arrayvec::ArrayVec;
+
+// Blocks range from a single 4 KiB frame (order 0) up to 1 GiB (order 18).
+pub const ORDERS: usize = 19;
+pub const MAXIMUM_ORDER: usize = ORDERS - 1;
+
+const BITS_PER_WORD: usize = 64;
+
+// A binary buddy allocator over physical frames.
+//
+// Each order has a bitmap with one bit per block of that order, set while the block is free. The
+// bitmaps live in memory taken from the early allocator rather than in the free frames themselves,
+// so the allocator never touches the memory it manages.
+pub struct BuddyAllocator {
+    base:      PhysicalAddress,
+    frames:    usize,
+    bitmaps:   ArrayVec<&'static mut [u64], ORDERS>,
+    free:      [usize; ORDERS],
+    allocated: [usize; ORDERS]
+}
+
+impl BuddyAllocator {
+    // Takes over the memory the early allocator hasn't handed out. The bitmaps are allocated from
+    // the early allocator first so that they aren't included.
+    pub fn new_from<const S: usize>(mut early: EarlyPhysicalFrameAllocator<S>) -> Result<BuddyAllocator, AllocationError> {
+        let (lowest, highest) = early.regions().fold(None, |extent: Option<(PhysicalAddress, PhysicalAddress)>, region| {
+            match extent {
+                Some((lowest, highest)) => Some((lowest.min(region.start), highest.max(region.end))),
+                None => Some((region.start, region.end))
+            }
+        }).ok_or(AllocationError::OutOfMemory { size: FRAME_SIZE, alignment: FRAME_SIZE })?;
+
+        let base = lowest.align_down(BuddyAllocator::block_size(MAXIMUM_ORDER));
+        let frames = (highest.align_up(FRAME_SIZE) - base) / FRAME_SIZE;
+
+        let words = BuddyAllocator::storage_size(frames);
+        let address = early.allocate(words * core::mem::size_of::<u64>(), core::mem::align_of::<u64>())?;
+
+        // This is safe because the early allocator just handed us this memory.
+        let storage = unsafe { core::slice::from_raw_parts_mut(address.to_virtual().as_mut_ptr(), words) };
+
+        let mut allocator = BuddyAllocator::new(base, frames, storage);
+
+        for region in early.regions() {
+            allocator.free_range(region.start, region.end - region.start);
+        }
+
+        Ok(allocator)
+    }
+
+    // Creates an allocator for the given number of frames starting at `base`, with nothing free yet.
+    // `base` must be aligned to the largest block size.
+    pub fn new(base: PhysicalAddress, frames: usize, storage: &'static mut [u64]) -> BuddyAllocator {
+        assert!(base.is_aligned(BuddyAllocator::block_size(MAXIMUM_ORDER)), "buddy allocator base must be 1 GiB-aligned");
+        assert!(storage.len() >= BuddyAllocator::storage_size(frames), "buddy allocator bitmaps don't fit in the given storage");
+
+        let mut bitmaps = ArrayVec::new();
+        let mut remainder = storage;
+
+        for order in 0..ORDERS {
+            let (bitmap, rest) = core::mem::take(&mut remainder).split_at_mut(BuddyAllocator::words_for(frames, order));
+
+            for word in bitmap.iter_mut() {
+                *word = 0;
+            }
+
+            bitmaps.push(bitmap);
+            remainder = rest;
+        }
+
+        BuddyAllocator {
+            base,
+            frames,
+            bitmaps,
+            free:      [0; ORDERS],
+            allocated: [0; ORDERS]
+        }
+    }
+
+    // Returns the number of words of bitmap storage needed to track the given number of frames.
+    pub fn storage_size(frames: usize) -> usize {
+        (0..ORDERS).map(|order| BuddyAllocator::words_for(frames, order)).sum()
+    }
+
+    // Allocates 2^order contiguous frames, aligned to their combined size.
+    pub fn allocate_frames(&mut self, order: usize) -> Result<PhysicalAddress, AllocationError> {
+        if order > MAXIMUM_ORDER {
+            return Err(AllocationError::InvalidOrder(order))
+        }
+
+        let available = (order..ORDERS).find(|&candidate| self.free[candidate] > 0).ok_or(
+            AllocationError::OutOfMemory {
+                size:      BuddyAllocator::block_size(order),
+                alignment: BuddyAllocator::block_size(order)
+            }
+        )?;
+
+        let mut index = self.take(available);
+
+        // Split the block down to the requested order, freeing the upper half at each step.
+        for lower in (order..available).rev() {
+            index *= 2;
+            self.mark_free(lower, index + 1);
+        }
+
+        self.allocated[order] += 1;
+
+        Ok(self.address_of(index, order))
+    }
+
+    // Returns 2^order frames starting at `address`, which must have come from `allocate_frames`
+    // with the same order.
+    pub fn free_frames(&mut self, address: PhysicalAddress, order: usize) {
+        assert!(order <= MAXIMUM_ORDER, "order {} is too large", order);
+        assert!(self.allocated[order] > 0, "no order-{} blocks are allocated", order);
+
+        let index = self.index_of(address, order);
+        self.allocated[order] -= 1;
+        self.release(index, order);
+    }
+
+    // Adds the given range of physical memory to the free pool, in the largest blocks that fit.
+    pub fn free_range(&mut self, base: PhysicalAddress, length: usize) {
+        let start = base.align_up(FRAME_SIZE);
+        let end = (base + length).align_down(FRAME_SIZE);
+
+        if start >= end {
+            return
+        }
+
+        let mut frame = (start - self.base) / FRAME_SIZE;
+        let last = (end - self.base) / FRAME_SIZE;
+
+        assert!(last <= self.frames, "freed range extends past the end of the allocator");
+
+        while frame < last {
+            let order =
+                (0..=MAXIMUM_ORDER).rev()
+                    .find(|&order| frame % (1 << order) == 0 && frame + (1 << order) <= last)
+                    .unwrap_or(0);
+
+            self.release(frame >> order, order);
+            frame += 1 << order;
+        }
+    }
+
+    // Returns the address just past the last frame the allocator manages.
+    pub fn end(&self) -> PhysicalAddress {
+        self.base + self.frames * FRAME_SIZE
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        Statistics { free: self.free, allocated: self.allocated }
+    }
+
+    // Frees the block at the given index, merging it with its buddy for as long as the buddy is free.
+    fn release(&mut self, mut index: usize, mut order: usize) {
+        assert!(!self.is_free(index, order), "double free of physical memory at {:#x}", self.address_of(index, order));
+
+        while order < MAXIMUM_ORDER && self.is_free(index ^ 1, order) {
+            self.mark_used(order, index ^ 1);
+            index /= 2;
+            order += 1;
+        }
+
+        self.mark_free(order, index);
+    }
+
+    // Claims the lowest free block of the given order and returns its index.
+    fn take(&mut self, order: usize) -> usize {
+        let index =
+            self.bitmaps[order].iter().enumerate()
+                .find(|(_, word)| **word != 0)
+                .map(|(position, word)| position * BITS_PER_WORD + word.trailing_zeros() as usize)
+                .expect("buddy allocator free count is out of sync with its bitmap");
+
+        self.mark_used(order, index);
+        index
+    }
+
+    fn is_free(&self, index: usize, order: usize) -> bool {
+        self.bitmaps[order]
+            .get(index / BITS_PER_WORD)
+            .map_or(false, |word| word & (1u64 << (index % BITS_PER_WORD)) != 0)
+    }
+
+    fn mark_free(&mut self, order: usize, index: usize) {
+        self.bitmaps[order][index / BITS_PER_WORD] |= 1u64 << (index % BITS_PER_WORD);
+        self.free[order] += 1;
+    }
+
+    fn mark_used(&mut self, order: usize, index: usize) {
+        self.bitmaps[order][index / BITS_PER_WORD] &= !(1u64 << (index % BITS_PER_WORD));
+        self.free[order] -= 1;
+    }
+
+    fn address_of(&self, index: usize, order: usize) -> PhysicalAddress {
+        self.base + index * BuddyAllocator::block_size(order)
+    }
+
+    fn index_of(&self, address: PhysicalAddress, order: usize) -> usize {
+        assert!(address.is_aligned(BuddyAllocator::block_size(order)), "{:?} is not aligned to an order-{} block", address, order);
+        assert!(address >= self.base && (address - self.base) / FRAME_SIZE < self.frames, "{:?} is outside the allocator", address);
+
+        (address - self.base) / BuddyAllocator::block_size(order)
+    }
+
+    fn block_size(order: usize) -> usize {
+        FRAME_SIZE << order
+    }
+
+    fn words_for(frames: usize, order: usize) -> usize {
+        let blocks = (frames + (1 << order) - 1) >> order;
+        (blocks + BITS_PER_WORD - 1) / BITS_PER_WORD
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Statistics {
+    pub free:      [usize; ORDERS],
+    pub allocated: [usize; ORDERS]
+}
+
+impl Statistics {
+    pub fn free_bytes(&self) -> usize {
+        self.free.iter().enumerate().map(|(order, count)| count * (FRAME_SIZE << order)).sum()
+    }
+}
+
+impl core::fmt::Display for Statistics {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for order in 0..ORDERS {
+            if self.free[order] > 0 || self.allocated[order] > 0 {
+                writeln!(
+                    formatter, "Order {:>2} ({:>4} KiB blocks): {:>6} free, {:>6} allocated",
+                    order, (FRAME_SIZE << order) / 1024, self.free[order], self.allocated[order]
+                )?;
+            }
+        }
+
+        writeln!(formatter, "Free physical memory: {} KiB", self.free_bytes() / 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMES: usize = 1024;
+    static mut STORAGE: [u64; 64] = [0; 64];
+
+    fn allocator() -> BuddyAllocator {
+        // This is safe because tests run one at a time, and each builds a fresh allocator.
+        BuddyAllocator::new(PhysicalAddress::zero(), FRAMES, unsafe { &mut STORAGE })
+    }
+
+    #[test]
+    fn freeing_a_range_in_the_largest_blocks_that_fit() {
+        let mut allocator = allocator();
+        allocator.free_range(PhysicalAddress::new(0x1000), 0x3FF000);
+
+        let statistics = allocator.statistics();
+        assert_eq!(0x3FF000, statistics.free_bytes());
+        assert_eq!([1, 1, 1, 1, 1, 1, 1, 1, 1, 1], statistics.free[0..10]);
+        assert_eq!(0, statistics.free[10]);
+    }
+
+    #[test]
+    fn allocating_by_splitting_larger_blocks() {
+        let mut allocator = allocator();
+        allocator.free_range(PhysicalAddress::zero(), 0x10000);
+
+        assert_eq!(Ok(PhysicalAddress::zero()), allocator.allocate_frames(0));
+        assert_eq!(Ok(PhysicalAddress::new(0x2000)), allocator.allocate_frames(1));
+        assert_eq!(Ok(PhysicalAddress::new(0x1000)), allocator.allocate_frames(0));
+        assert_eq!(Ok(PhysicalAddress::new(0x8000)), allocator.allocate_frames(3));
+
+        let statistics = allocator.statistics();
+        assert_eq!(0x4000, statistics.free_bytes());
+        assert_eq!(2, statistics.allocated[0]);
+        assert_eq!(1, statistics.allocated[1]);
+        assert_eq!(1, statistics.allocated[3]);
+    }
+
+    #[test]
+    fn coalescing_freed_buddies() {
+        let mut allocator = allocator();
+        allocator.free_range(PhysicalAddress::zero(), 0x4000);
+
+        let first = allocator.allocate_frames(0).unwrap();
+        let second = allocator.allocate_frames(0).unwrap();
+        let third = allocator.allocate_frames(1).unwrap();
+
+        allocator.free_frames(second, 0);
+        allocator.free_frames(first, 0);
+        allocator.free_frames(third, 1);
+
+        let statistics = allocator.statistics();
+        assert_eq!(1, statistics.free[2]);
+        assert_eq!(0, statistics.free[0] + statistics.free[1]);
+        assert_eq!(Ok(PhysicalAddress::zero()), allocator.allocate_frames(2));
+    }
+
+    #[test]
+    fn running_out_of_frames() {
+        let mut allocator = allocator();
+        allocator.free_range(PhysicalAddress::zero(), 0x2000);
+
+        assert_eq!(
+            Err(AllocationError::OutOfMemory { size: 0x4000, alignment: 0x4000 }),
+            allocator.allocate_frames(2)
+        );
+
+        assert_eq!(Err(AllocationError::InvalidOrder(ORDERS)), allocator.allocate_frames(ORDERS));
+    }
+}
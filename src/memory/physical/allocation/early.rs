@@ -1,16 +1,20 @@
-use crate::multiboot::info::memory::*;
-use crate::memory::PhysicalAddress;
+use crate::memory::{PhysicalAddress, FRAME_SIZE, map::Map};
+use crate::util::alignment::align_up;
+use super::AllocationError;
 
+use core::ops::Range;
 use arrayvec::ArrayVec;
 use tap::tap::Tap;
 
+// Hands out physical memory before anything better is available. Allocations are carved off the
+// available regions and never returned, so this is only suitable for long-lived boot structures.
 pub struct EarlyPhysicalFrameAllocator<const S: usize = 32> {
     available: ArrayVec<Region, S>,
     reserved:  ArrayVec<Region, S>
 }
 
 impl<const S: usize> EarlyPhysicalFrameAllocator<S> {
-    pub fn new_from(map: MemoryMap) -> EarlyPhysicalFrameAllocator<S> {
+    pub fn new_from<const M: usize>(map: &Map<M>) -> EarlyPhysicalFrameAllocator<S> {
         EarlyPhysicalFrameAllocator::new().tap_mut(|allocator| allocator.add_from(map))
     }
 
@@ -22,30 +26,202 @@ impl<const S: usize> EarlyPhysicalFrameAllocator<S> {
     }
 
     pub fn add(&mut self, base: PhysicalAddress, length: usize) {
-        self.available.push(Region { base, length })
+        self.available.try_push(Region { base, length }).expect("too many available memory regions");
+
+        // Regions reserved before this one was added still need to be kept out of it.
+        for index in 0..self.reserved.len() {
+            self.exclude(self.reserved[index])
+        }
     }
 
-    pub fn add_from(&mut self, map: MemoryMap) {
-        for region in map.regions() {
-            if let Available = region.kind() {
-                self.add(region.starts_at(), region.length())
-            }
+    // Takes the available regions from a normalized map, which already leaves out the kernel and
+    // anything else in use.
+    pub fn add_from<const M: usize>(&mut self, map: &Map<M>) {
+        for range in map.available() {
+            self.add(range.start, range.end - range.start)
         }
     }
 
     pub fn reserve(&mut self, base: PhysicalAddress, length: usize) {
-        self.reserved.push(Region { base, length })
+        let region = Region { base, length };
+
+        self.reserved.try_push(region).expect("too many reserved memory regions");
+        self.exclude(region);
     }
 
-    pub fn allocate(size: usize, alignment: usize) -> Result<PhysicalAddress, AllocationError> {
-        Err(AllocationError)
+    // Allocates at least `size` bytes aligned to `alignment`. Both are rounded up to whole frames.
+    pub fn allocate(&mut self, size: usize, alignment: usize) -> Result<PhysicalAddress, AllocationError> {
+        if size == 0 {
+            return Err(AllocationError::ZeroSize)
+        }
+
+        if !alignment.is_power_of_two() {
+            return Err(AllocationError::InvalidAlignment(alignment))
+        }
+
+        let size = align_up(size, FRAME_SIZE);
+        let alignment = alignment.max(FRAME_SIZE);
+
+        let allocation = self.available.iter().find_map(|region| region.fit(size, alignment));
+
+        if let Some(allocation) = allocation {
+            self.exclude(allocation);
+            Ok(allocation.base)
+        } else {
+            Err(AllocationError::OutOfMemory { size, alignment })
+        }
+    }
+
+    // Returns the memory that hasn't been reserved or allocated yet.
+    pub fn regions(&self) -> impl Iterator<Item = Range<PhysicalAddress>> + '_ {
+        self.available.iter().map(|region| region.base..region.end())
+    }
+
+    // Removes the given region from the available regions, splitting any region it falls inside.
+    fn exclude(&mut self, excluded: Region) {
+        let mut index = 0;
+
+        while index < self.available.len() {
+            let region = self.available[index];
+
+            if !region.overlaps(&excluded) {
+                index += 1;
+                continue
+            }
+
+            match region.without(&excluded) {
+                (Some(before), Some(after)) => {
+                    self.available[index] = before;
+                    self.available.try_insert(index + 1, after).expect("too many available memory regions");
+                    index += 2
+                }
+
+                (Some(remainder), None) | (None, Some(remainder)) => {
+                    self.available[index] = remainder;
+                    index += 1
+                }
+
+                (None, None) => {
+                    self.available.remove(index);
+                }
+            }
+        }
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Region {
     base:   PhysicalAddress,
     length: usize
 }
 
-pub struct AllocationError;
+impl Region {
+    fn end(&self) -> PhysicalAddress {
+        self.base + self.length
+    }
+
+    fn overlaps(&self, other: &Region) -> bool {
+        self.base < other.end() && other.base < self.end()
+    }
+
+    // Returns the parts of this region before and after the other region, if any.
+    fn without(&self, other: &Region) -> (Option<Region>, Option<Region>) {
+        let before =
+            if other.base > self.base {
+                Some(Region { base: self.base, length: other.base - self.base })
+            } else {
+                None
+            };
+
+        let after =
+            if other.end() < self.end() {
+                Some(Region { base: other.end(), length: self.end() - other.end() })
+            } else {
+                None
+            };
+
+        (before, after)
+    }
+
+    fn fit(&self, size: usize, alignment: usize) -> Option<Region> {
+        let base = self.base.align_up(alignment);
+
+        if base <= self.end() && self.end() - base >= size {
+            Some(Region { base, length: size })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocating_from_an_available_region() {
+        let mut allocator = EarlyPhysicalFrameAllocator::<8>::new();
+        allocator.add(PhysicalAddress::new(0x100000), 0x10000);
+
+        assert_eq!(Ok(PhysicalAddress::new(0x100000)), allocator.allocate(0x1000, 0x1000));
+        assert_eq!(Ok(PhysicalAddress::new(0x101000)), allocator.allocate(0x1000, 0x1000));
+    }
+
+    #[test]
+    fn rounding_allocations_up_to_whole_frames() {
+        let mut allocator = EarlyPhysicalFrameAllocator::<8>::new();
+        allocator.add(PhysicalAddress::new(0x100000), 0x10000);
+
+        assert_eq!(Ok(PhysicalAddress::new(0x100000)), allocator.allocate(1, 8));
+        assert_eq!(Ok(PhysicalAddress::new(0x101000)), allocator.allocate(0x1001, 8));
+        assert_eq!(Ok(PhysicalAddress::new(0x103000)), allocator.allocate(0x1000, 8));
+    }
+
+    #[test]
+    fn allocating_aligned_memory() {
+        let mut allocator = EarlyPhysicalFrameAllocator::<8>::new();
+        allocator.add(PhysicalAddress::new(0x101000), 0x400000);
+
+        assert_eq!(Ok(PhysicalAddress::new(0x200000)), allocator.allocate(0x1000, 0x200000));
+
+        // The gap left behind by alignment is still available.
+        assert_eq!(Ok(PhysicalAddress::new(0x101000)), allocator.allocate(0x1000, 0x1000));
+    }
+
+    #[test]
+    fn skipping_reserved_memory() {
+        let mut allocator = EarlyPhysicalFrameAllocator::<8>::new();
+        allocator.reserve(PhysicalAddress::new(0x100000), 0x2000);
+        allocator.add(PhysicalAddress::new(0x100000), 0x4000);
+        allocator.reserve(PhysicalAddress::new(0x103000), 0x1000);
+
+        assert_eq!(Ok(PhysicalAddress::new(0x102000)), allocator.allocate(0x1000, 0x1000));
+
+        assert_eq!(
+            Err(AllocationError::OutOfMemory { size: 0x1000, alignment: 0x1000 }),
+            allocator.allocate(0x1000, 0x1000)
+        );
+    }
+
+    #[test]
+    fn running_out_of_memory() {
+        let mut allocator = EarlyPhysicalFrameAllocator::<8>::new();
+        allocator.add(PhysicalAddress::new(0x100000), 0x2000);
+
+        assert_eq!(
+            Err(AllocationError::OutOfMemory { size: 0x3000, alignment: 0x1000 }),
+            allocator.allocate(0x3000, 0x1000)
+        );
+
+        assert_eq!(Ok(PhysicalAddress::new(0x100000)), allocator.allocate(0x2000, 0x1000));
+    }
+
+    #[test]
+    fn rejecting_invalid_requests() {
+        let mut allocator = EarlyPhysicalFrameAllocator::<8>::new();
+        allocator.add(PhysicalAddress::new(0x100000), 0x2000);
+
+        assert_eq!(Err(AllocationError::ZeroSize), allocator.allocate(0, 0x1000));
+        assert_eq!(Err(AllocationError::InvalidAlignment(3)), allocator.allocate(0x1000, 3));
+    }
+}
@@ -1,2 +1,31 @@
 mod early;
 pub use early::EarlyPhysicalFrameAllocator;
+
+mod buddy;
+pub use buddy::{BuddyAllocator, Statistics};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationError {
+    OutOfMemory { size: usize, alignment: usize },
+    InvalidAlignment(usize),
+    InvalidOrder(usize),
+    ZeroSize
+}
+
+impl core::fmt::Display for AllocationError {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            AllocationError::OutOfMemory { size, alignment } => {
+                write!(formatter, "out of physical memory allocating {:#x} bytes aligned to {:#x}", size, alignment)
+            }
+
+            AllocationError::InvalidAlignment(alignment) => {
+                write!(formatter, "alignment {:#x} is not a power of two", alignment)
+            }
+
+            AllocationError::InvalidOrder(order) => write!(formatter, "order {} is too large", order),
+
+            AllocationError::ZeroSize => write!(formatter, "cannot allocate zero bytes")
+        }
+    }
+}
@@ -1,2 +1,2 @@
 mod allocation;
-pub use allocation::EarlyPhysicalFrameAllocator;
+pub use allocation::{EarlyPhysicalFrameAllocator, BuddyAllocator, AllocationError, Statistics};
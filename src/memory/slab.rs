@@ -0,0 +1,437 @@
+use crate::arch::interrupts::suppress;
+use crate::util::alignment::align_up;
+use crate::memory::{self, VirtualAddress, AllocationError, FRAME_SIZE};
+use crate::time::{Duration, Timer};
+
+use alloc::{boxed::Box, vec::Vec};
+use core::mem::size_of;
+use core::ptr::{self, NonNull};
+use spin::Mutex;
+
+// Slabs are made as small as possible while still holding at least this many objects...
+const MINIMUM_OBJECTS: usize = 8;
+
+// ...unless that would make them larger than 2^5 frames (128 KiB).
+const MAXIMUM_ORDER: usize = 5;
+
+// How often empty slabs go back to the frame allocator, whether or not it's running low.
+const RECLAIM_PERIOD: Duration = Duration::from_secs(10);
+
+static CACHES: Mutex<Vec<&'static Cache>> = Mutex::new(Vec::new());
+
+// Creates a cache of objects of the given size and alignment. If given, the constructor runs once
+// on each object when its slab is allocated, not on every allocation: objects should be returned
+// to the cache in their constructed state.
+pub fn create(name: &'static str, size: usize, alignment: usize, constructor: Option<fn(*mut u8)>) -> &'static Cache {
+    let cache: &'static Cache = Box::leak(Box::new(Cache::new(name, size, alignment, constructor)));
+    suppress(|| CACHES.lock().push(cache));
+    cache
+}
+
+// Frees every empty slab in every cache, returning the number of frames freed.
+//
+// This is called when the frame allocator runs dry, possibly while a cache is holding its own
+// lock to grow. Caches that are busy are skipped rather than waited on.
+pub fn reclaim() -> usize {
+    suppress(|| {
+        CACHES.try_lock().map_or(0, |caches| {
+            caches.iter().map(|cache| cache.slabs.try_lock().map_or(0, |mut slabs| cache.release(&mut slabs))).sum()
+        })
+    })
+}
+
+// Reclaims empty slabs every so often from now on, so that memory a burst of allocations left
+// behind in one cache is free for anything again.
+pub fn reclaim_periodically() {
+    Timer::every(RECLAIM_PERIOD, || { reclaim(); });
+}
+
+pub fn statistics() -> Vec<Statistics> {
+    suppress(|| CACHES.lock().iter().map(|cache| cache.statistics()).collect())
+}
+
+
+// Hands out fixed-size objects carved from slabs of contiguous frames.
+//
+// Each slab starts with a header and a stack of the indices of its free objects, followed by the
+// objects themselves. Keeping the free indices out of the objects leaves constructed objects intact
+// between uses. Slabs are naturally aligned to their size, so an object's slab is found by
+// rounding its address down.
+pub struct Cache {
+    name:        &'static str,
+    size:        usize,
+    stride:      usize,
+    order:       usize,
+    capacity:    usize,
+    offset:      usize,
+    constructor: Option<fn(*mut u8)>,
+    slabs:       Mutex<Slabs>
+}
+
+impl Cache {
+    fn new(name: &'static str, size: usize, alignment: usize, constructor: Option<fn(*mut u8)>) -> Cache {
+        assert!(alignment.is_power_of_two(), "{} cache alignment {} isn't a power of two", name, alignment);
+
+        let stride = align_up(size.max(1), alignment);
+
+        let (order, (capacity, offset)) =
+            (0..=MAXIMUM_ORDER)
+                .map(|order| (order, Cache::fit(FRAME_SIZE << order, stride, alignment)))
+                .find(|&(order, (capacity, _))| capacity >= MINIMUM_OBJECTS || order == MAXIMUM_ORDER)
+                .unwrap();
+
+        assert!(capacity > 0, "{}-byte objects in the {} cache don't fit in a slab", size, name);
+
+        Cache {
+            name,
+            size,
+            stride,
+            order,
+            capacity,
+            offset,
+            constructor,
+            slabs: Mutex::new(Slabs::new())
+        }
+    }
+
+    pub fn allocate(&self) -> Result<NonNull<u8>, AllocationError> {
+        suppress(|| {
+            let mut slabs = self.slabs.lock();
+
+            let slab =
+                if let Some(slab) = slabs.partial.first() {
+                    slab
+                } else if let Some(slab) = slabs.empty.first() {
+                    slabs.empty.remove(slab);
+                    slabs.partial.push(slab);
+                    slab
+                } else {
+                    let slab = self.grow()?;
+                    slabs.partial.push(slab);
+                    slab
+                };
+
+            unsafe {
+                (*slab).free -= 1;
+                let index = *self.free_indices(slab).add((*slab).free) as usize;
+
+                if (*slab).free == 0 {
+                    slabs.partial.remove(slab);
+                    slabs.full.push(slab);
+                }
+
+                slabs.in_use += 1;
+
+                Ok(NonNull::new_unchecked(self.object(slab, index)))
+            }
+        })
+    }
+
+    // Returns an object to the cache. It must have come from `allocate` on this cache.
+    pub unsafe fn free(&self, object: NonNull<u8>) {
+        let address = object.as_ptr() as usize;
+        let slab = (address & !((FRAME_SIZE << self.order) - 1)) as *mut Slab;
+        let offset = address.wrapping_sub(slab as usize + self.offset);
+
+        assert!(
+            address >= slab as usize + self.offset && offset % self.stride == 0 && offset / self.stride < self.capacity,
+            "{:#x} isn't an object from the {} cache", address, self.name
+        );
+
+        suppress(|| {
+            let mut slabs = self.slabs.lock();
+
+            assert!((*slab).free < self.capacity, "double free of {:#x} in the {} cache", address, self.name);
+
+            if (*slab).free == 0 {
+                slabs.full.remove(slab);
+                slabs.partial.push(slab);
+            }
+
+            *self.free_indices(slab).add((*slab).free) = (offset / self.stride) as u16;
+            (*slab).free += 1;
+
+            if (*slab).free == self.capacity {
+                slabs.partial.remove(slab);
+                slabs.empty.push(slab);
+            }
+
+            slabs.in_use -= 1;
+        })
+    }
+
+    // Frees this cache's empty slabs, returning the number of frames freed.
+    pub fn reclaim(&self) -> usize {
+        suppress(|| self.release(&mut self.slabs.lock()))
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        suppress(|| {
+            let slabs = self.slabs.lock();
+            let count = slabs.partial.length + slabs.full.length + slabs.empty.length;
+
+            Statistics {
+                name:           self.name,
+                object_size:    self.size,
+                slab_size:      FRAME_SIZE << self.order,
+                in_use:         slabs.in_use,
+                objects:        count * self.capacity,
+                partial_slabs:  slabs.partial.length,
+                full_slabs:     slabs.full.length,
+                empty_slabs:    slabs.empty.length,
+                waste:          count * ((FRAME_SIZE << self.order) - self.capacity * self.size)
+            }
+        })
+    }
+
+    // Allocates and lays out a new slab, with every object free and constructed.
+    fn grow(&self) -> Result<*mut Slab, AllocationError> {
+        let slab: *mut Slab = memory::allocate_frames(self.order)?.to_virtual().as_mut_ptr();
+
+        unsafe {
+            slab.write(Slab { previous: ptr::null_mut(), next: ptr::null_mut(), free: self.capacity });
+
+            // Hand out lower addresses first.
+            for index in 0..self.capacity {
+                *self.free_indices(slab).add(index) = (self.capacity - 1 - index) as u16;
+
+                if let Some(constructor) = self.constructor {
+                    constructor(self.object(slab, index))
+                }
+            }
+        }
+
+        Ok(slab)
+    }
+
+    fn release(&self, slabs: &mut Slabs) -> usize {
+        let mut freed = 0;
+
+        while let Some(slab) = slabs.empty.first() {
+            slabs.empty.remove(slab);
+            memory::free_frames(VirtualAddress::from(slab as *const Slab).to_physical(), self.order);
+            freed += 1 << self.order;
+        }
+
+        freed
+    }
+
+    unsafe fn free_indices(&self, slab: *mut Slab) -> *mut u16 {
+        (slab as *mut u8).add(size_of::<Slab>()) as *mut u16
+    }
+
+    unsafe fn object(&self, slab: *mut Slab, index: usize) -> *mut u8 {
+        (slab as *mut u8).add(self.offset + index * self.stride)
+    }
+
+    // Returns how many objects fit in a slab of the given size, and where the first one starts.
+    fn fit(slab_size: usize, stride: usize, alignment: usize) -> (usize, usize) {
+        let header = size_of::<Slab>();
+        let mut capacity = ((slab_size - header) / (stride + size_of::<u16>())).min(u16::MAX as usize + 1);
+
+        while capacity > 0 {
+            let offset = align_up(header + capacity * size_of::<u16>(), alignment);
+
+            if offset + capacity * stride <= slab_size {
+                return (capacity, offset)
+            }
+
+            capacity -= 1;
+        }
+
+        (0, 0)
+    }
+}
+
+
+struct Slabs {
+    partial: List,
+    full:    List,
+    empty:   List,
+    in_use:  usize
+}
+
+// Slabs are only reached through their cache's lock.
+unsafe impl Send for Slabs {}
+
+impl Slabs {
+    fn new() -> Slabs {
+        Slabs { partial: List::new(), full: List::new(), empty: List::new(), in_use: 0 }
+    }
+}
+
+#[repr(C)]
+struct Slab {
+    previous: *mut Slab,
+    next:     *mut Slab,
+    free:     usize
+}
+
+// An intrusive doubly linked list of slabs.
+struct List {
+    head:   *mut Slab,
+    length: usize
+}
+
+impl List {
+    fn new() -> List {
+        List { head: ptr::null_mut(), length: 0 }
+    }
+
+    fn first(&self) -> Option<*mut Slab> {
+        if self.head.is_null() { None } else { Some(self.head) }
+    }
+
+    fn push(&mut self, slab: *mut Slab) {
+        unsafe {
+            (*slab).previous = ptr::null_mut();
+            (*slab).next = self.head;
+
+            if !self.head.is_null() {
+                (*self.head).previous = slab;
+            }
+        }
+
+        self.head = slab;
+        self.length += 1;
+    }
+
+    fn remove(&mut self, slab: *mut Slab) {
+        unsafe {
+            if (*slab).previous.is_null() {
+                self.head = (*slab).next;
+            } else {
+                (*(*slab).previous).next = (*slab).next;
+            }
+
+            if !(*slab).next.is_null() {
+                (*(*slab).next).previous = (*slab).previous;
+            }
+        }
+
+        self.length -= 1;
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct Statistics {
+    pub name:          &'static str,
+    pub object_size:   usize,
+    pub slab_size:     usize,
+    pub in_use:        usize,
+    pub objects:       usize,
+    pub partial_slabs: usize,
+    pub full_slabs:    usize,
+    pub empty_slabs:   usize,
+
+    // Bytes in the cache's slabs that can't hold objects: headers, padding, and leftovers.
+    pub waste:         usize
+}
+
+impl Statistics {
+    pub fn slabs(&self) -> usize {
+        self.partial_slabs + self.full_slabs + self.empty_slabs
+    }
+}
+
+impl core::fmt::Display for Statistics {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            formatter, "{:<24} {:>6}/{:<6} objects of {:>5} bytes in {:>4} slabs of {:>3} KiB ({} partial, {} empty), {} bytes wasted",
+            self.name, self.in_use, self.objects, self.object_size, self.slabs(), self.slab_size / 1024,
+            self.partial_slabs, self.empty_slabs, self.waste
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::align_of;
+
+    #[test]
+    fn allocating_and_freeing_objects() {
+        let cache = create("test.objects", 100, 8, None);
+
+        let first = cache.allocate().unwrap();
+        let second = cache.allocate().unwrap();
+        assert_eq!(104, second.as_ptr() as usize - first.as_ptr() as usize);
+
+        let statistics = cache.statistics();
+        assert_eq!(2, statistics.in_use);
+        assert_eq!(1, statistics.partial_slabs);
+
+        unsafe {
+            cache.free(second);
+            cache.free(first);
+        }
+
+        let statistics = cache.statistics();
+        assert_eq!(0, statistics.in_use);
+        assert_eq!(1, statistics.empty_slabs);
+
+        // Freed objects are reused.
+        assert_eq!(first, cache.allocate().unwrap());
+    }
+
+    #[test]
+    fn aligning_objects() {
+        let cache = create("test.aligned", 24, 64, None);
+
+        for _ in 0..MINIMUM_OBJECTS * 2 {
+            assert_eq!(0, cache.allocate().unwrap().as_ptr() as usize % 64);
+        }
+    }
+
+    #[test]
+    fn constructing_objects_once_per_slab() {
+        fn construct(object: *mut u8) {
+            unsafe { (object as *mut u64).write(42) }
+        }
+
+        let cache = create("test.constructed", size_of::<u64>(), align_of::<u64>(), Some(construct));
+
+        let object = cache.allocate().unwrap();
+        assert_eq!(42, unsafe { *(object.as_ptr() as *const u64) });
+
+        // Objects come back in whatever state they were freed in.
+        unsafe {
+            *(object.as_ptr() as *mut u64) = 7;
+            cache.free(object);
+            assert_eq!(7, *(cache.allocate().unwrap().as_ptr() as *const u64));
+        }
+    }
+
+    #[test]
+    fn filling_slabs() {
+        let cache = create("test.full", 2048, 8, None);
+        let capacity = cache.capacity;
+        assert_eq!(15, capacity);
+
+        let objects: Vec<_> = (0..=capacity).map(|_| cache.allocate().unwrap()).collect();
+
+        let statistics = cache.statistics();
+        assert_eq!(1, statistics.full_slabs);
+        assert_eq!(1, statistics.partial_slabs);
+
+        for object in objects {
+            unsafe { cache.free(object) }
+        }
+
+        assert_eq!(2, cache.statistics().empty_slabs);
+    }
+
+    #[test]
+    fn reclaiming_empty_slabs() {
+        let cache = create("test.reclaimed", 512, 8, None);
+        let order = cache.order;
+
+        let object = cache.allocate().unwrap();
+        assert_eq!(0, cache.reclaim());
+
+        unsafe { cache.free(object) }
+        assert_eq!(1 << order, cache.reclaim());
+        assert_eq!(0, cache.statistics().slabs());
+    }
+}
@@ -0,0 +1,124 @@
+use super::tags::ELFSectionsTag;
+use crate::arch::memory::{PhysicalAddress, VirtualAddress, KERNEL_OFFSET};
+
+use core::convert::TryInto;
+use bitflags::bitflags;
+
+// The kernel image's ELF section headers, which the bootloader copies into the boot information.
+#[derive(Debug)]
+pub struct ELFSections<'a> {
+    tag: &'a ELFSectionsTag
+}
+
+impl<'a> ELFSections<'a> {
+    pub fn count(&self) -> usize {
+        self.tag.count as usize
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ELFSection<'a>> + 'a {
+        let tag = self.tag;
+        let names = self.section(tag.names_index as usize).map_or(&[][..], |section| section.contents());
+
+        tag.headers()
+            .chunks_exact(tag.entry_size as usize)
+            .take(tag.count as usize)
+            .map(move |header| ELFSection { header, names })
+    }
+
+    fn section(&self, index: usize) -> Option<ELFSection<'a>> {
+        let size = self.tag.entry_size as usize;
+
+        self.tag.headers()
+            .get(index * size..(index + 1) * size)
+            .map(|header| ELFSection { header, names: &[] })
+    }
+}
+
+impl<'a> From<&'a ELFSectionsTag> for ELFSections<'a> {
+    fn from(tag: &ELFSectionsTag) -> ELFSections {
+        ELFSections { tag }
+    }
+}
+
+impl<'a> core::fmt::Display for ELFSections<'a> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for section in self.iter().filter(|section| section.flags().contains(SectionFlags::ALLOCATED)) {
+            writeln!(formatter, "{}", section)?;
+        }
+
+        Ok(())
+    }
+}
+
+// A 64-bit ELF section header. Headers are only 4-byte aligned in the boot information, so fields
+// are read out byte by byte.
+#[derive(Clone, Copy)]
+pub struct ELFSection<'a> {
+    header: &'a [u8],
+    names:  &'a [u8]
+}
+
+impl<'a> ELFSection<'a> {
+    pub fn name(&self) -> &'a str {
+        self.names
+            .get(self.u32_at(0) as usize..)
+            .map(|name| &name[..name.iter().position(|&byte| byte == 0).unwrap_or(name.len())])
+            .and_then(|name| core::str::from_utf8(name).ok())
+            .unwrap_or("")
+    }
+
+    pub fn kind(&self) -> u32 {
+        self.u32_at(4)
+    }
+
+    pub fn flags(&self) -> SectionFlags {
+        SectionFlags::from_bits_truncate(self.u64_at(8))
+    }
+
+    pub fn address(&self) -> u64 {
+        self.u64_at(16)
+    }
+
+    pub fn size(&self) -> usize {
+        self.u64_at(32) as usize
+    }
+
+    // Returns the section's contents, wherever they were loaded. Sections the kernel image doesn't
+    // allocate, like the string tables, are placed in physical memory by the bootloader.
+    pub fn contents(&self) -> &'a [u8] {
+        let address =
+            if self.address() >= KERNEL_OFFSET {
+                VirtualAddress::new(self.address())
+            } else {
+                PhysicalAddress::new(self.address()).to_virtual()
+            };
+
+        if self.address() == 0 {
+            &[]
+        } else {
+            unsafe { core::slice::from_raw_parts(address.as_ptr(), self.size()) }
+        }
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.header[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.header[offset..offset + 8].try_into().unwrap())
+    }
+}
+
+impl<'a> core::fmt::Display for ELFSection<'a> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "{:<16} {:#18x} {:>8} bytes {:?}", self.name(), self.address(), self.size(), self.flags())
+    }
+}
+
+bitflags! {
+    pub struct SectionFlags: u64 {
+        const WRITABLE   = 1;
+        const ALLOCATED  = 1 << 1;
+        const EXECUTABLE = 1 << 2;
+    }
+}
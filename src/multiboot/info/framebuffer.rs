@@ -0,0 +1,83 @@
+use super::tags::FramebufferTag;
+use crate::memory::PhysicalAddress;
+
+use core::convert::TryInto;
+
+// A linear framebuffer the bootloader set up, or the VGA text buffer.
+#[derive(Debug)]
+pub struct Framebuffer<'a> {
+    tag: &'a FramebufferTag
+}
+
+impl<'a> Framebuffer<'a> {
+    pub fn address(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.tag.address)
+    }
+
+    // Returns the number of bytes per row.
+    pub fn pitch(&self) -> usize {
+        self.tag.pitch as usize
+    }
+
+    // Returns the width and height, in pixels or, for text mode, characters.
+    pub fn width(&self) -> usize {
+        self.tag.width as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.tag.height as usize
+    }
+
+    // Returns the number of bits per pixel.
+    pub fn depth(&self) -> u8 {
+        self.tag.depth
+    }
+
+    pub fn format(&self) -> Format {
+        let info = self.tag.color_info();
+
+        match self.tag.format {
+            // The palette's size is a 16-bit count, followed by padding.
+            0 => Format::Indexed { colors: info.get(0..2).map_or(0, |count| u16::from_le_bytes(count.try_into().unwrap()) as usize) },
+
+            1 if info.len() >= 6 => Format::RGB {
+                red:   Channel { position: info[0], size: info[1] },
+                green: Channel { position: info[2], size: info[3] },
+                blue:  Channel { position: info[4], size: info[5] }
+            },
+
+            2 => Format::Text,
+            format => Format::Unknown(format)
+        }
+    }
+}
+
+impl<'a> From<&'a FramebufferTag> for Framebuffer<'a> {
+    fn from(tag: &FramebufferTag) -> Framebuffer {
+        Framebuffer { tag }
+    }
+}
+
+impl<'a> core::fmt::Display for Framebuffer<'a> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            formatter, "{}x{}x{} at {:#x} ({:?})",
+            self.width(), self.height(), self.depth(), self.address(), self.format()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Indexed { colors: usize },
+    RGB { red: Channel, green: Channel, blue: Channel },
+    Text,
+    Unknown(u8)
+}
+
+// Where a color's bits sit in a pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channel {
+    pub position: u8,
+    pub size:     u8
+}
@@ -103,13 +103,7 @@ impl Region {
     }
 
     pub fn kind(&self) -> Kind {
-        match self.kind {
-            1 => Kind::Available,
-            3 => Kind::Reclaimable,
-            4 => Kind::Nonvolatile,
-            5 => Kind::Defective,
-            _ => Kind::Reserved
-        }
+        Kind::from(self.kind)
     }
 }
 
@@ -139,6 +133,19 @@ pub enum Kind {
     Defective
 }
 
+// Multiboot 1 uses the same numbering, which comes from the BIOS E820 call.
+impl From<u32> for Kind {
+    fn from(kind: u32) -> Kind {
+        match kind {
+            1 => Kind::Available,
+            3 => Kind::Reclaimable,
+            4 => Kind::Nonvolatile,
+            5 => Kind::Defective,
+            _ => Kind::Reserved
+        }
+    }
+}
+
 impl Kind {
     fn to_str(&self) -> &str {
         match self {
@@ -3,21 +3,20 @@ use table::{Table, Tags};
 
 mod tags;
 use tags::*;
+pub use tags::{Kind, ModuleTag, BasicMemoryInfoTag, BootDeviceTag, APMTag};
 
 pub mod memory;
 use memory::MemoryMap;
 
-use spin::RwLock;
+pub mod framebuffer;
+use framebuffer::Framebuffer;
 
-static INFO: RwLock<Option<&'static Info>> = RwLock::new(None);
+pub mod elf;
+use elf::ELFSections;
 
-pub fn set(info: &'static Info) {
-    INFO.write().replace(info);
-}
+use core::convert::TryInto;
 
-pub fn get() -> Option<&'static Info> {
-    *INFO.read()
-}
+use crate::memory::{PhysicalAddress, VirtualAddress};
 
 #[repr(C)]
 pub struct Info {
@@ -25,11 +24,172 @@ pub struct Info {
 }
 
 impl Info {
+    pub fn command_line(&self) -> Option<&str> {
+        self.tags().get(Kind::CommandLine).map(|tag: &StringTag| tag.string())
+    }
+
+    pub fn bootloader_name(&self) -> Option<&str> {
+        self.tags().get(Kind::BootloaderName).map(|tag: &StringTag| tag.string())
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &ModuleTag> {
+        self.tags().filter_map(|tag| tag.try_into().ok())
+    }
+
+    pub fn basic_memory_info(&self) -> Option<&BasicMemoryInfoTag> {
+        self.tags().get(Kind::BasicMemoryInfo)
+    }
+
+    pub fn boot_device(&self) -> Option<&BootDeviceTag> {
+        self.tags().get(Kind::BootDevice)
+    }
+
     pub fn memory_map(&self) -> Option<MemoryMap> {
         self.tags().get(Kind::MemoryMap).map(|tag: &MemoryMapTag| tag.into())
     }
 
+    pub fn framebuffer(&self) -> Option<Framebuffer> {
+        self.tags().get(Kind::Framebuffer).map(|tag: &FramebufferTag| tag.into())
+    }
+
+    pub fn elf_sections(&self) -> Option<ELFSections> {
+        self.tags().get(Kind::ELFSections).map(|tag: &ELFSectionsTag| tag.into())
+    }
+
+    pub fn apm(&self) -> Option<&APMTag> {
+        self.tags().get(Kind::APM)
+    }
+
+    pub fn efi_system_table(&self) -> Option<PhysicalAddress> {
+        self.tags().get(Kind::EFI64SystemTable)
+            .or_else(|| self.tags().get(Kind::EFI32SystemTable))
+            .map(|tag: &PointerTag| tag.address())
+    }
+
+    pub fn efi_image_handle(&self) -> Option<PhysicalAddress> {
+        self.tags().get(Kind::EFI64ImageHandle)
+            .or_else(|| self.tags().get(Kind::EFI32ImageHandle))
+            .map(|tag: &PointerTag| tag.address())
+    }
+
+    // Returns the physical address the bootloader loaded the image at, if it relocated it.
+    pub fn load_base(&self) -> Option<PhysicalAddress> {
+        self.tags().get(Kind::ImageLoadBase).map(|tag: &PointerTag| tag.address())
+    }
+
+    // Returns the bootloader's copy of the ACPI RSDP, preferring the ACPI 2.0 version.
+    pub fn rsdp(&self) -> Option<&[u8]> {
+        self.tags().get(Kind::RSDPv2)
+            .or_else(|| self.tags().get(Kind::RSDPv1))
+            .map(|tag: &RSDPTag| tag.bytes())
+    }
+
+    pub fn address(&self) -> PhysicalAddress {
+        VirtualAddress::from(self).to_physical()
+    }
+
+    pub fn size(&self) -> usize {
+        self.table.size()
+    }
+
     fn tags(&self) -> Tags {
         self.table.tags()
     }
 }
+
+impl core::fmt::Display for Info {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for tag in self.tags() {
+            match tag.kind() {
+                Kind::CommandLine => writeln!(formatter, "Command line: {:?}", typed::<StringTag>(tag).string())?,
+                Kind::BootloaderName => writeln!(formatter, "Bootloader: {}", typed::<StringTag>(tag).string())?,
+
+                Kind::Module => {
+                    let module = typed::<ModuleTag>(tag);
+                    writeln!(formatter, "Module: [{:#16x} - {:#16x}] {}", module.start(), module.end(), module.string())?
+                }
+
+                Kind::BasicMemoryInfo => {
+                    let info = typed::<BasicMemoryInfoTag>(tag);
+                    writeln!(formatter, "Basic memory: {} KiB lower, {} KiB upper", info.lower, info.upper)?
+                }
+
+                Kind::BootDevice => {
+                    let device = typed::<BootDeviceTag>(tag);
+                    writeln!(formatter, "Boot device: {:#x}, partition {:#x}.{:#x}", device.drive, device.partition, device.sub_partition)?
+                }
+
+                Kind::MemoryMap => writeln!(formatter, "Memory map: {} regions", MemoryMap::from(typed::<MemoryMapTag>(tag)).regions().count())?,
+                Kind::Framebuffer => writeln!(formatter, "Framebuffer: {}", Framebuffer::from(typed::<FramebufferTag>(tag)))?,
+                Kind::ELFSections => writeln!(formatter, "ELF sections: {}", ELFSections::from(typed::<ELFSectionsTag>(tag)).count())?,
+                Kind::APM => writeln!(formatter, "APM: version {:#x}", typed::<APMTag>(tag).version)?,
+
+                Kind::EFI32SystemTable | Kind::EFI64SystemTable => {
+                    writeln!(formatter, "EFI system table: {:#x}", typed::<PointerTag>(tag).address())?
+                }
+
+                Kind::EFI32ImageHandle | Kind::EFI64ImageHandle => {
+                    writeln!(formatter, "EFI image handle: {:#x}", typed::<PointerTag>(tag).address())?
+                }
+
+                Kind::ImageLoadBase => writeln!(formatter, "Load base: {:#x}", typed::<PointerTag>(tag).address())?,
+                Kind::RSDPv1 => writeln!(formatter, "ACPI 1.0 RSDP")?,
+                Kind::RSDPv2 => writeln!(formatter, "ACPI 2.0 RSDP")?,
+
+                kind => writeln!(formatter, "{:?} ({} bytes)", kind, tag.size)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Converts a tag whose kind was already checked to its type.
+fn typed<'a, T>(tag: &'a Tag) -> &'a T where &'a Tag: TryInto<&'a T> {
+    tag.try_into().ok().expect("tag doesn't match its kind")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Info;
+    use crate::{boot, arch::memory::KERNEL_OFFSET};
+    use log::info;
+
+    // These only apply when the kernel was booted with Multiboot 2, as it is from the GRUB image.
+    // Otherwise they pass without checking anything, and say so.
+    fn info() -> Option<&'static Info> {
+        match boot::get() {
+            Some(boot::Info::Multiboot2(info)) => Some(info),
+            _ => {
+                info!("Skipping a Multiboot 2 test: the kernel wasn't booted with Multiboot 2");
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn reading_the_bootloader_name() {
+        if let Some(info) = info() {
+            assert!(info.bootloader_name().unwrap().starts_with("GRUB"));
+        }
+    }
+
+    #[test]
+    fn reading_the_kernel_elf_sections() {
+        if let Some(info) = info() {
+            let sections = info.elf_sections().unwrap();
+            let text = sections.iter().find(|section| section.name() == ".text").unwrap();
+
+            assert!(text.address() >= KERNEL_OFFSET);
+            assert!(text.size() > 0);
+        }
+    }
+
+    #[test]
+    fn finding_the_rsdp_in_the_boot_information() {
+        if let Some(info) = info() {
+            let rsdp = info.rsdp().unwrap();
+            assert_eq!(b"RSD PTR ", &rsdp[0..8]);
+        }
+    }
+}
@@ -11,6 +11,10 @@ impl Table {
         Tags::new(unsafe { self.as_ptr().offset(1) } as *const Tag)
     }
 
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
     fn as_ptr(&self) -> *const Table {
         self
     }
@@ -34,12 +38,12 @@ impl<'a> Tags<'a> {
     }
 
     pub fn get<T>(&mut self, kind: Kind) -> Option<&'a T> where &'a Tag: TryInto<&'a T> {
-        self.find(|tag| tag.kind == kind).and_then(|tag| tag.try_into().ok())
+        self.find(|tag| tag.kind() == kind).and_then(|tag| tag.try_into().ok())
     }
 
     unsafe fn current(&self) -> Option<&'a Tag> {
         match &*self.current {
-            Tag { kind: Kind::End, .. } => None,
+            tag if tag.kind() == Kind::End => None,
             tag => Some(tag)
         }
     }
@@ -1,26 +1,88 @@
 #[repr(C)]
 #[derive(Debug)]
 pub struct Tag {
-    pub kind: Kind,
+    kind:     u32,
     pub size: u32
 }
 
-#[repr(u32)]
-#[derive(PartialEq, Debug)]
+impl Tag {
+    // The type is read as a plain integer: bootloaders may pass tags we don't know about.
+    pub fn kind(&self) -> Kind {
+        Kind::from(self.kind)
+    }
+
+    // Returns the tag's contents after the type and size.
+    pub fn body(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts((self as *const Tag).offset(1) as *const u8, self.size as usize - 8) }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Kind {
-    End = 0,
-    MemoryMap = 6
+    End,
+    CommandLine,
+    BootloaderName,
+    Module,
+    BasicMemoryInfo,
+    BootDevice,
+    MemoryMap,
+    VBEInfo,
+    Framebuffer,
+    ELFSections,
+    APM,
+    EFI32SystemTable,
+    EFI64SystemTable,
+    SMBIOS,
+    RSDPv1,
+    RSDPv2,
+    Network,
+    EFIMemoryMap,
+    EFIBootServicesNotTerminated,
+    EFI32ImageHandle,
+    EFI64ImageHandle,
+    ImageLoadBase,
+    Unknown(u32)
+}
+
+impl From<u32> for Kind {
+    fn from(kind: u32) -> Kind {
+        match kind {
+            0  => Kind::End,
+            1  => Kind::CommandLine,
+            2  => Kind::BootloaderName,
+            3  => Kind::Module,
+            4  => Kind::BasicMemoryInfo,
+            5  => Kind::BootDevice,
+            6  => Kind::MemoryMap,
+            7  => Kind::VBEInfo,
+            8  => Kind::Framebuffer,
+            9  => Kind::ELFSections,
+            10 => Kind::APM,
+            11 => Kind::EFI32SystemTable,
+            12 => Kind::EFI64SystemTable,
+            13 => Kind::SMBIOS,
+            14 => Kind::RSDPv1,
+            15 => Kind::RSDPv2,
+            16 => Kind::Network,
+            17 => Kind::EFIMemoryMap,
+            18 => Kind::EFIBootServicesNotTerminated,
+            19 => Kind::EFI32ImageHandle,
+            20 => Kind::EFI64ImageHandle,
+            21 => Kind::ImageLoadBase,
+            _  => Kind::Unknown(kind)
+        }
+    }
 }
 
 use core::convert::TryFrom;
 
 macro_rules! try_from_impl_for {
-    ($name:ident, $kind:expr) => {
+    ($name:ident, $($kind:expr),+) => {
         impl<'a> TryFrom<&'a Tag> for &'a $name {
             type Error = ();
 
             fn try_from(tag: &'a Tag) -> Result<&'a $name, ()> {
-                if tag.kind == $kind {
+                if $(tag.kind() == $kind)||+ {
                     Ok(unsafe { &*(tag as *const Tag as *const $name) })
                 } else {
                     Err(())
@@ -30,13 +92,94 @@ macro_rules! try_from_impl_for {
     }
 }
 
+// Reads a null-terminated UTF-8 string out of a tag.
+fn string(bytes: &[u8]) -> &str {
+    let length = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..length]).unwrap_or("")
+}
+
 use super::memory::Region;
+use crate::memory::PhysicalAddress;
+
+// Used for both the command line and the bootloader name.
+#[repr(C)]
+#[derive(Debug)]
+pub struct StringTag {
+    kind: u32,
+    size: u32
+}
+
+impl StringTag {
+    pub fn string(&self) -> &str {
+        string(self.as_tag().body())
+    }
+
+    fn as_tag(&self) -> &Tag {
+        unsafe { &*(self as *const StringTag as *const Tag) }
+    }
+}
+
+try_from_impl_for!(StringTag, Kind::CommandLine, Kind::BootloaderName);
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ModuleTag {
+    kind:  u32,
+    size:  u32,
+    start: u32,
+    end:   u32
+}
+
+impl ModuleTag {
+    pub fn start(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.start as u64)
+    }
+
+    pub fn end(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.end as u64)
+    }
+
+    pub fn string(&self) -> &str {
+        string(&self.as_tag().body()[8..])
+    }
+
+    fn as_tag(&self) -> &Tag {
+        unsafe { &*(self as *const ModuleTag as *const Tag) }
+    }
+}
+
+try_from_impl_for!(ModuleTag, Kind::Module);
+
+// The amounts of lower (below 1 MiB) and upper (above 1 MiB) memory, in KiB.
+#[repr(C)]
+#[derive(Debug)]
+pub struct BasicMemoryInfoTag {
+    kind:      u32,
+    size:      u32,
+    pub lower: u32,
+    pub upper: u32
+}
+
+try_from_impl_for!(BasicMemoryInfoTag, Kind::BasicMemoryInfo);
+
+// The BIOS disk the image was loaded from. Partition numbers are 0xFFFFFFFF if unused.
+#[repr(C)]
+#[derive(Debug)]
+pub struct BootDeviceTag {
+    kind:              u32,
+    size:              u32,
+    pub drive:         u32,
+    pub partition:     u32,
+    pub sub_partition: u32
+}
+
+try_from_impl_for!(BootDeviceTag, Kind::BootDevice);
 
 #[repr(C)]
 #[derive(Debug)]
 pub struct MemoryMapTag {
-    pub kind: Kind,
-    pub size: u32,
+    kind:           u32,
+    pub size:       u32,
     pub entry_size: u32,
     _entry_version: u32
 }
@@ -47,7 +190,7 @@ impl MemoryMapTag {
     }
 
     pub(super) fn last_region(&self) -> *const Region {
-        (self.as_ptr() as u32 + self.size - self.entry_size) as *const Region
+        (self.as_ptr() as usize + self.size as usize - self.entry_size as usize) as *const Region
     }
 
     fn as_ptr(&self) -> *const MemoryMapTag {
@@ -56,3 +199,126 @@ impl MemoryMapTag {
 }
 
 try_from_impl_for!(MemoryMapTag, Kind::MemoryMap);
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct FramebufferTag {
+    kind:                  u32,
+    size:                  u32,
+    pub(super) address:    u64,
+    pub(super) pitch:      u32,
+    pub(super) width:      u32,
+    pub(super) height:     u32,
+    pub(super) depth:      u8,
+    pub(super) format:     u8,
+    _reserved:             u16
+}
+
+impl FramebufferTag {
+    // Returns the color information that follows the fixed fields.
+    pub(super) fn color_info(&self) -> &[u8] {
+        &self.as_tag().body()[24..]
+    }
+
+    fn as_tag(&self) -> &Tag {
+        unsafe { &*(self as *const FramebufferTag as *const Tag) }
+    }
+}
+
+try_from_impl_for!(FramebufferTag, Kind::Framebuffer);
+
+// GRUB writes 32-bit fields here, though older revisions of the specification call for 16 bits.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ELFSectionsTag {
+    kind:                   u32,
+    size:                   u32,
+    pub(super) count:       u32,
+    pub(super) entry_size:  u32,
+    pub(super) names_index: u32
+}
+
+impl ELFSectionsTag {
+    pub(super) fn headers(&self) -> &[u8] {
+        &self.as_tag().body()[12..]
+    }
+
+    fn as_tag(&self) -> &Tag {
+        unsafe { &*(self as *const ELFSectionsTag as *const Tag) }
+    }
+}
+
+try_from_impl_for!(ELFSectionsTag, Kind::ELFSections);
+
+// The APM BIOS's protected-mode interface.
+#[repr(C)]
+#[derive(Debug)]
+pub struct APMTag {
+    kind:                       u32,
+    size:                       u32,
+    pub version:                u16,
+    pub code_segment:           u16,
+    pub offset:                 u32,
+    pub code_segment_16:        u16,
+    pub data_segment:           u16,
+    pub flags:                  u16,
+    pub code_segment_length:    u16,
+    pub code_segment_16_length: u16,
+    pub data_segment_length:    u16
+}
+
+try_from_impl_for!(APMTag, Kind::APM);
+
+// Holds a 32- or 64-bit physical address: the EFI system table or image handle, or the physical
+// address the image was loaded at.
+#[repr(C)]
+#[derive(Debug)]
+pub struct PointerTag {
+    kind: u32,
+    size: u32
+}
+
+impl PointerTag {
+    pub fn address(&self) -> PhysicalAddress {
+        let body = self.as_tag().body();
+
+        let address =
+            if body.len() >= 8 {
+                u64::from_le_bytes([body[0], body[1], body[2], body[3], body[4], body[5], body[6], body[7]])
+            } else {
+                u32::from_le_bytes([body[0], body[1], body[2], body[3]]) as u64
+            };
+
+        PhysicalAddress::new(address)
+    }
+
+    fn as_tag(&self) -> &Tag {
+        unsafe { &*(self as *const PointerTag as *const Tag) }
+    }
+}
+
+try_from_impl_for!(
+    PointerTag,
+    Kind::EFI32SystemTable, Kind::EFI64SystemTable, Kind::EFI32ImageHandle, Kind::EFI64ImageHandle, Kind::ImageLoadBase
+);
+
+// Holds a copy of the ACPI RSDP: the original 20-byte structure for ACPI 1.0 (RSDPv1), or the
+// extended one for ACPI 2.0 and later (RSDPv2).
+#[repr(C)]
+#[derive(Debug)]
+pub struct RSDPTag {
+    kind: u32,
+    size: u32
+}
+
+impl RSDPTag {
+    pub fn bytes(&self) -> &[u8] {
+        self.as_tag().body()
+    }
+
+    fn as_tag(&self) -> &Tag {
+        unsafe { &*(self as *const RSDPTag as *const Tag) }
+    }
+}
+
+try_from_impl_for!(RSDPTag, Kind::RSDPv1, Kind::RSDPv2);
@@ -1,9 +1,5 @@
+// The bootloader leaves one of these in EAX to say which protocol it used.
 pub type Magic = u32;
 
-const EXPECTED: Magic = 0x36D76289;
-
-pub fn validate(actual: Magic) {
-    if actual != EXPECTED {
-        panic!("Georgix requires a Multiboot 2-compliant bootloader");
-    }
-}
+pub const MULTIBOOT1: Magic = 0x2BADB002;
+pub const MULTIBOOT2: Magic = 0x36D76289;
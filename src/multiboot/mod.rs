@@ -3,3 +3,5 @@ pub use magic::Magic;
 
 pub mod info;
 pub use info::Info;
+
+pub mod v1;
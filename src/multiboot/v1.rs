@@ -0,0 +1,321 @@
+// The original Multiboot protocol, which QEMU's -kernel loader speaks.
+//
+// Unlike Multiboot 2's tagged table, the boot information is a fixed structure whose flags say
+// which fields are valid. The structure points to everything else by 32-bit physical address.
+
+use super::info::memory::Kind;
+use crate::memory::PhysicalAddress;
+
+use bitflags::bitflags;
+use core::ops::Range;
+use core::mem::size_of;
+
+#[repr(C)]
+pub struct Info {
+    flags:              u32,
+    mem_lower:          u32,
+    mem_upper:          u32,
+    _boot_device:       u32,
+    cmdline:            u32,
+    mods_count:         u32,
+    mods_addr:          u32,
+    _syms:              [u32; 4],
+    mmap_length:        u32,
+    mmap_addr:          u32,
+    _drives_length:     u32,
+    _drives_addr:       u32,
+    _config_table:      u32,
+    boot_loader_name:   u32,
+    _apm_table:         u32,
+    _vbe_control_info:  u32,
+    _vbe_mode_info:     u32,
+    _vbe_mode:          u16,
+    _vbe_interface_seg: u16,
+    _vbe_interface_off: u16,
+    _vbe_interface_len: u16
+}
+
+bitflags! {
+    pub struct Flags: u32 {
+        const MEMORY           = 1;
+        const BOOT_DEVICE      = 1 << 1;
+        const COMMAND_LINE     = 1 << 2;
+        const MODULES          = 1 << 3;
+        const AOUT_SYMBOLS     = 1 << 4;
+        const ELF_SECTIONS     = 1 << 5;
+        const MEMORY_MAP       = 1 << 6;
+        const DRIVES           = 1 << 7;
+        const CONFIG_TABLE     = 1 << 8;
+        const BOOTLOADER_NAME  = 1 << 9;
+        const APM_TABLE        = 1 << 10;
+        const VBE              = 1 << 11;
+        const FRAMEBUFFER      = 1 << 12;
+    }
+}
+
+impl Info {
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(self.flags)
+    }
+
+    pub fn command_line(&self) -> Option<&str> {
+        self.string(Flags::COMMAND_LINE, self.cmdline)
+    }
+
+    pub fn bootloader_name(&self) -> Option<&str> {
+        self.string(Flags::BOOTLOADER_NAME, self.boot_loader_name)
+    }
+
+    // Returns the amounts of lower (below 1 MiB) and upper (above 1 MiB) memory, in KiB.
+    pub fn basic_memory_info(&self) -> Option<(u32, u32)> {
+        if self.flags().contains(Flags::MEMORY) { Some((self.mem_lower, self.mem_upper)) } else { None }
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        if self.flags().contains(Flags::MODULES) && self.mods_count > 0 {
+            unsafe { core::slice::from_raw_parts(at(self.mods_addr), self.mods_count as usize) }
+        } else {
+            &[]
+        }
+    }
+
+    pub fn memory_map(&self) -> Option<MemoryMap> {
+        if self.flags().contains(Flags::MEMORY_MAP) {
+            Some(MemoryMap { address: self.mmap_addr, length: self.mmap_length })
+        } else {
+            None
+        }
+    }
+
+    pub fn address(&self) -> PhysicalAddress {
+        crate::memory::VirtualAddress::from(self).to_physical()
+    }
+
+    // Returns the memory the boot information occupies: the structure itself, and everything it
+    // points to except the modules' contents. Bootloaders are free to scatter these.
+    pub fn extents(&self) -> impl Iterator<Item = Range<PhysicalAddress>> + '_ {
+        let map = self.memory_map().map(|map| range(map.address, map.length as usize));
+        let modules = Some(range(self.mods_addr, self.modules().len() * size_of::<Module>()))
+            .filter(|_| !self.modules().is_empty());
+
+        let strings = self.command_line().map(|string| (self.cmdline, string))
+            .into_iter()
+            .chain(self.bootloader_name().map(|string| (self.boot_loader_name, string)))
+            .chain(self.modules().iter().filter_map(|module| module.string().map(|string| (module.string, string))))
+            .map(|(address, string)| range(address, string.len() + 1));
+
+        core::iter::once(self.address()..self.address() + size_of::<Info>())
+            .chain(map)
+            .chain(modules)
+            .chain(strings)
+    }
+
+    fn string(&self, flag: Flags, address: u32) -> Option<&str> {
+        if self.flags().contains(flag) { string(address) } else { None }
+    }
+}
+
+impl core::fmt::Display for Info {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if let Some(command_line) = self.command_line() {
+            writeln!(formatter, "Command line: {:?}", command_line)?;
+        }
+
+        if let Some(name) = self.bootloader_name() {
+            writeln!(formatter, "Bootloader: {}", name)?;
+        }
+
+        for module in self.modules() {
+            writeln!(formatter, "Module: [{:#16x} - {:#16x}] {}", module.start(), module.end(), module.string().unwrap_or(""))?;
+        }
+
+        if let Some((lower, upper)) = self.basic_memory_info() {
+            writeln!(formatter, "Basic memory: {} KiB lower, {} KiB upper", lower, upper)?;
+        }
+
+        if let Some(map) = self.memory_map() {
+            writeln!(formatter, "Memory map: {} regions", map.regions().count())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[repr(C)]
+pub struct Module {
+    start:     u32,
+    end:       u32,
+    string:    u32,
+    _reserved: u32
+}
+
+impl Module {
+    pub fn start(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.start as u64)
+    }
+
+    pub fn end(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.end as u64)
+    }
+
+    pub fn string(&self) -> Option<&str> {
+        string(self.string)
+    }
+}
+
+// A buffer of variable-size entries. Each starts with its size, not counting the size field itself.
+#[derive(Clone, Copy)]
+pub struct MemoryMap {
+    address: u32,
+    length:  u32
+}
+
+impl MemoryMap {
+    pub fn regions(&self) -> Regions {
+        Regions { current: self.address as u64, end: self.address as u64 + self.length as u64 }
+    }
+}
+
+impl core::fmt::Display for MemoryMap {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for region in self.regions() {
+            writeln!(formatter, "{}", region)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Regions {
+    current: u64,
+    end:     u64
+}
+
+impl Iterator for Regions {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Region> {
+        if self.current + size_of::<Region>() as u64 > self.end {
+            return None
+        }
+
+        let region: Region = unsafe { core::ptr::read_unaligned(PhysicalAddress::new(self.current).to_virtual().as_ptr()) };
+        self.current += region.size as u64 + 4;
+
+        Some(region)
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct Region {
+    size:   u32,
+    base:   u64,
+    length: u64,
+    kind:   u32
+}
+
+impl Region {
+    pub fn starts_at(&self) -> PhysicalAddress {
+        PhysicalAddress::new(self.base)
+    }
+
+    pub fn ends_at(&self) -> PhysicalAddress {
+        self.starts_at() + self.length
+    }
+
+    pub fn kind(&self) -> Kind {
+        Kind::from(self.kind)
+    }
+}
+
+impl core::fmt::Display for Region {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "[{:#16x} - {:#16x}] {}", self.starts_at(), self.ends_at(), self.kind())
+    }
+}
+
+// Everything the boot information points to is in the first 4 GiB, which the direct map covers.
+unsafe fn at<T>(address: u32) -> *const T {
+    PhysicalAddress::new(address as u64).to_virtual().as_ptr()
+}
+
+fn range(address: u32, length: usize) -> Range<PhysicalAddress> {
+    let start = PhysicalAddress::new(address as u64);
+    start..start + length
+}
+
+// Reads a NUL-terminated string. Anything that isn't UTF-8 is treated as missing.
+fn string(address: u32) -> Option<&'static str> {
+    if address == 0 {
+        return None
+    }
+
+    unsafe {
+        let start: *const u8 = at(address);
+        let length = (0..).take_while(|&index| *start.add(index) != 0).count();
+
+        core::str::from_utf8(core::slice::from_raw_parts(start, length)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[test]
+    fn reading_multiboot_1_information() {
+        // Lay out boot information in a frame, which the fields can point to by physical address.
+        let frame = memory::allocate_frames(0).unwrap();
+        let base = frame.as_u64() as u32;
+        let write = |offset: usize, bytes: &[u8]| unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), frame.to_virtual().as_mut_ptr::<u8>().add(offset), bytes.len())
+        };
+
+        let info = Info {
+            flags:       (Flags::MEMORY_MAP | Flags::COMMAND_LINE | Flags::MODULES).bits(),
+            cmdline:     base + 224,
+            mods_count:  1,
+            mods_addr:   base + 192,
+            mmap_length: 2 * size_of::<Region>() as u32,
+            mmap_addr:   base + 128,
+            ..unsafe { core::mem::zeroed() }
+        };
+
+        let regions = [
+            Region { size: 20, base: 0, length: 0x9FC00, kind: 1 },
+            Region { size: 20, base: 0x100000, length: 0x7F00000, kind: 1 }
+        ];
+
+        let module = Module { start: 0x200000, end: 0x201000, string: base + 256, _reserved: 0 };
+
+        unsafe {
+            write(0, core::slice::from_raw_parts(&info as *const Info as *const u8, size_of::<Info>()));
+            write(128, core::slice::from_raw_parts(regions.as_ptr() as *const u8, size_of::<[Region; 2]>()));
+            write(192, core::slice::from_raw_parts(&module as *const Module as *const u8, size_of::<Module>()));
+        }
+
+        write(224, b"console=serial\0");
+        write(256, b"initramfs\0");
+
+        let info: &Info = unsafe { &*frame.to_virtual().as_ptr() };
+
+        assert_eq!(Some("console=serial"), info.command_line());
+        assert_eq!(None, info.bootloader_name());
+
+        assert_eq!(1, info.modules().len());
+        assert_eq!(PhysicalAddress::new(0x200000), info.modules()[0].start());
+        assert_eq!(Some("initramfs"), info.modules()[0].string());
+
+        let map = info.memory_map().unwrap();
+        assert_eq!(2, map.regions().count());
+        assert!(map.regions().all(|region| matches!(region.kind(), Kind::Available)));
+        assert_eq!(PhysicalAddress::new(0x8000000), map.regions().last().unwrap().ends_at());
+
+        // The structure, the memory map, the module list, and both strings.
+        assert_eq!(5, info.extents().count());
+
+        memory::free_frames(frame, 0);
+    }
+}
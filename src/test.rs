@@ -1,11 +1,27 @@
 #![cfg(test)]
 
+use crate::cmdline::Parameter;
+use arrayvec::ArrayString;
+
+// Runs only the tests whose names contain the given text, as in `test.filter=acpi`.
+pub static FILTER: Parameter<Option<ArrayString<64>>> = Parameter::new("test.filter", None);
+
 #[no_mangle]
 extern "Rust" fn __print(args: core::fmt::Arguments) {
-    crate::arch::test::console::print(args);
+    crate::arch::serial::print(args);
 }
 
 #[no_mangle]
 extern "Rust" fn __exit(code: u32) {
     crate::arch::test::exit(code);
 }
+
+#[no_mangle]
+extern "Rust" fn __selected(name: &str) -> bool {
+    FILTER.get().map_or(true, |filter| name.contains(filter.as_str()))
+}
+
+#[no_mangle]
+extern "Rust" fn __backtrace() {
+    crate::arch::serial::print(format_args!("{}", crate::backtrace::Backtrace::here()));
+}
@@ -0,0 +1,96 @@
+// Dates and times of day in the proleptic Gregorian calendar, always in UTC.
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Days from 0000-03-01 to the Unix epoch, 1970-01-01. Counting from March puts leap days at the
+// end of the year.
+const EPOCH_DAYS: u64 = 719_468;
+
+// Days in 400 years, after which the calendar repeats.
+const DAYS_PER_ERA: u64 = 146_097;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year:   u16,
+    pub month:  u8,
+    pub day:    u8,
+    pub hour:   u8,
+    pub minute: u8,
+    pub second: u8
+}
+
+impl DateTime {
+    // Returns the number of seconds since the Unix epoch. Dates before it count as the epoch.
+    pub fn timestamp(&self) -> u64 {
+        let (month, day) = (self.month as u64, self.day as u64);
+
+        // Years start in March, so January and February belong to the year before.
+        let year = self.year as u64 - if month <= 2 { 1 } else { 0 };
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = (era * DAYS_PER_ERA + day_of_era).saturating_sub(EPOCH_DAYS);
+
+        days * SECONDS_PER_DAY + self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+
+    pub fn from_timestamp(timestamp: u64) -> DateTime {
+        let days = timestamp / SECONDS_PER_DAY + EPOCH_DAYS;
+        let seconds = timestamp % SECONDS_PER_DAY;
+
+        let era = days / DAYS_PER_ERA;
+        let day_of_era = days - era * DAYS_PER_ERA;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year:   year as u16,
+            month:  month as u8,
+            day:    day as u8,
+            hour:   (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8
+        }
+    }
+}
+
+impl core::fmt::Display for DateTime {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            formatter, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn converting_dates_to_timestamps() {
+        let dates = [
+            (DateTime { year: 1970, month: 1, day: 1, hour: 0, minute: 0, second: 0 }, 0),
+            (DateTime { year: 2000, month: 3, day: 1, hour: 0, minute: 0, second: 0 }, 951_868_800),
+            (DateTime { year: 2024, month: 2, day: 29, hour: 12, minute: 0, second: 0 }, 1_709_208_000),
+            (DateTime { year: 2038, month: 1, day: 19, hour: 3, minute: 14, second: 8 }, 2_147_483_648)
+        ];
+
+        for &(date, timestamp) in dates.iter() {
+            assert_eq!(timestamp, date.timestamp());
+            assert_eq!(date, DateTime::from_timestamp(timestamp));
+        }
+    }
+
+    #[test]
+    fn formatting_dates() {
+        let date = DateTime { year: 2024, month: 2, day: 29, hour: 9, minute: 5, second: 3 };
+        assert_eq!("2024-02-29 09:05:03", date.to_string());
+    }
+}
@@ -0,0 +1,268 @@
+// Keeping time: how long it's been since the kernel started its clock, as told by the best clock
+// source the machine has.
+
+mod calendar;
+pub use calendar::DateTime;
+
+mod system;
+pub use system::SystemTime;
+
+pub mod timer;
+pub use timer::Timer;
+
+pub use core::time::Duration;
+
+use crate::arch::{self, interrupts::suppress, time::rtc::Alarm};
+use crate::cmdline::Parameter;
+
+use alloc::vec::Vec;
+use arrayvec::ArrayString;
+use core::ops::{Add, Sub};
+use log::{info, warn};
+use spin::Mutex;
+
+// Picks a clock source by name rather than the best one, as in `clocksource=hpet`.
+pub static CLOCK_SOURCE: Parameter<Option<ArrayString<16>>> = Parameter::new("clocksource", None);
+
+static CLOCK: Mutex<Option<Clock>> = Mutex::new(None);
+
+// A free-running counter ticking at a fixed rate.
+pub trait ClockSource: Sync {
+    fn name(&self) -> &'static str;
+
+    // In Hz.
+    fn frequency(&self) -> u64;
+
+    // Which bits of the counter are valid. It wraps around to zero after the mask.
+    fn mask(&self) -> u64;
+
+    // How good a clock it makes, taking in resolution, reliability and the cost of reading it. The
+    // highest-rated source wins.
+    fn rating(&self) -> u32;
+
+    fn read(&self) -> u64;
+}
+
+// Starts the clock with the best source available, and sets the wall clock from the real-time
+// clock. Before this, both stand still at zero.
+pub fn initialize() {
+    let sources = arch::time::clock_sources();
+    let source = choose(&sources, CLOCK_SOURCE.get().as_ref().map(ArrayString::as_str)).expect("no clock source");
+
+    let names: Vec<&str> = sources.iter().map(|source| source.name()).collect();
+    info!("Clock sources: {}; using {} at {} kHz", names.join(", "), source.name(), source.frequency() / 1000);
+
+    suppress(|| CLOCK.lock().replace(Clock::new(source)));
+    crate::logging::set_clock(uptime);
+
+    system::set(SystemTime::from(arch::time::rtc::read()));
+    info!("Wall clock: {}", SystemTime::now().date_time());
+
+    keep_in_step();
+
+    timer::initialize();
+}
+
+// The clock source and the real-time clock drift apart, so have the wall clock checked against the
+// real-time clock once an hour, on the hour.
+pub fn keep_in_step() {
+    let hourly = Alarm { hour: None, minute: Some(0), second: Some(0) };
+
+    if let Err(error) = arch::time::rtc::set_alarm(hourly, system::synchronize) {
+        warn!("The wall clock won't be kept in step with the real-time clock: {}", error);
+    }
+}
+
+fn choose<'a>(sources: &[&'a dyn ClockSource], name: Option<&str>) -> Option<&'a dyn ClockSource> {
+    if let Some(name) = name {
+        match sources.iter().find(|source| source.name() == name) {
+            Some(source) => return Some(*source),
+            None => warn!("Ignoring clocksource={}: there's no such clock source", name)
+        }
+    }
+
+    sources.iter().max_by_key(|source| source.rating()).copied()
+}
+
+// Returns how long the clock's been running.
+pub fn uptime() -> Duration {
+    suppress(|| CLOCK.lock().as_mut().map_or(Duration::from_secs(0), Clock::update))
+}
+
+// Catches the clock up with its source. Counters can wrap around in well under a second, so the
+// timer interrupt calls this on every tick to make sure none go unnoticed.
+pub fn update() {
+    uptime();
+}
+
+// Busy-waits, for drivers that need to give hardware a moment. This works in early boot too,
+// before the clock is running.
+pub fn delay(nanoseconds: u64) {
+    if suppress(|| CLOCK.lock().is_none()) {
+        let mut remaining = nanoseconds;
+
+        // The PIT can only wait so long at once.
+        while remaining > 0 {
+            let step = remaining.min(50_000_000);
+            arch::time::wait(step);
+            remaining -= step;
+        }
+
+        return
+    }
+
+    let deadline = Instant::now() + Duration::from_nanos(nanoseconds);
+
+    while Instant::now() < deadline {
+        core::hint::spin_loop();
+    }
+}
+
+// Waits, halting the CPU until a timer interrupt finds the time's up. Interrupts have to be on.
+pub fn sleep(duration: Duration) {
+    let deadline = Instant::now() + duration;
+
+    // A tickless timer might not go off otherwise. This one's due a moment after the deadline.
+    let timer = Timer::after(duration, || {});
+
+    while Instant::now() < deadline {
+        arch::halt();
+    }
+
+    timer.cancel();
+}
+
+// A point in time, measured on the clock. Instants never go backwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Instant {
+        Instant(uptime())
+    }
+
+    // Returns the time between an earlier instant and this one, or zero if it was actually later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.checked_sub(earlier.0).unwrap_or(Duration::from_secs(0))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(duration).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration).expect("overflow when adding a duration to an instant")
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+// Adds up a clock source's ticks, so the time keeps going when the counter wraps around.
+struct Clock {
+    source: &'static dyn ClockSource,
+    last:   u64,
+    cycles: u64
+}
+
+impl Clock {
+    fn new(source: &'static dyn ClockSource) -> Clock {
+        Clock { source, last: source.read(), cycles: 0 }
+    }
+
+    fn update(&mut self) -> Duration {
+        let now = self.source.read();
+
+        self.cycles += now.wrapping_sub(self.last) & self.source.mask();
+        self.last = now;
+
+        Duration::from_nanos((self.cycles as u128 * 1_000_000_000 / self.source.frequency() as u128) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    // A one-byte counter ticking at 1 kHz that the test winds by hand.
+    struct Manual {
+        name:    &'static str,
+        rating:  u32,
+        counter: AtomicU64
+    }
+
+    impl ClockSource for Manual {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn frequency(&self) -> u64 {
+            1000
+        }
+
+        fn mask(&self) -> u64 {
+            0xFF
+        }
+
+        fn rating(&self) -> u32 {
+            self.rating
+        }
+
+        fn read(&self) -> u64 {
+            self.counter.load(Ordering::Relaxed) & 0xFF
+        }
+    }
+
+    static SLOW: Manual = Manual { name: "slow", rating: 100, counter: AtomicU64::new(250) };
+    static FAST: Manual = Manual { name: "fast", rating: 300, counter: AtomicU64::new(0) };
+
+    #[test]
+    fn choosing_clock_sources() {
+        let sources: [&dyn ClockSource; 2] = [&SLOW, &FAST];
+
+        assert_eq!(Some("fast"), choose(&sources, None).map(|source| source.name()));
+        assert_eq!(Some("slow"), choose(&sources, Some("slow")).map(|source| source.name()));
+        assert_eq!(Some("fast"), choose(&sources, Some("sundial")).map(|source| source.name()));
+    }
+
+    #[test]
+    fn counting_through_wraparounds() {
+        let mut clock = Clock::new(&SLOW);
+
+        SLOW.counter.store(260, Ordering::Relaxed);
+        assert_eq!(Duration::from_millis(10), clock.update());
+
+        SLOW.counter.store(500, Ordering::Relaxed);
+        assert_eq!(Duration::from_millis(250), clock.update());
+    }
+
+    #[test]
+    fn delaying() {
+        let start = Instant::now();
+        delay(2_000_000);
+        assert!(start.elapsed() >= Duration::from_millis(2));
+    }
+
+    #[test]
+    fn running_on_a_clock_source() {
+        let sources = arch::time::clock_sources();
+        assert!(sources.iter().any(|source| source.name() == "pit"));
+
+        let earlier = Instant::now();
+        assert!(Instant::now() >= earlier);
+    }
+}
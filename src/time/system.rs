@@ -0,0 +1,105 @@
+use super::{uptime, DateTime, Duration};
+use crate::arch::{self, interrupts::suppress};
+
+use core::ops::Add;
+use log::info;
+use spin::RwLock;
+
+// What the wall clock read when the monotonic clock started.
+static BOOT_TIME: RwLock<Duration> = RwLock::new(Duration::from_secs(0));
+
+// The time of day, as the time since the Unix epoch. The wall clock counts forward with the
+// monotonic clock, so the two always agree on how much time has passed. Only setting it can make it
+// jump, backwards included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime(Duration);
+
+impl SystemTime {
+    pub fn now() -> SystemTime {
+        SystemTime(suppress(|| *BOOT_TIME.read()) + uptime())
+    }
+
+    // Returns the time between an earlier time and this one, or nothing if it was actually later.
+    pub fn duration_since(&self, earlier: SystemTime) -> Option<Duration> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    pub fn date_time(&self) -> DateTime {
+        DateTime::from_timestamp(self.0.as_secs())
+    }
+}
+
+impl From<DateTime> for SystemTime {
+    fn from(date_time: DateTime) -> SystemTime {
+        SystemTime(Duration::from_secs(date_time.timestamp()))
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn add(self, duration: Duration) -> SystemTime {
+        SystemTime(self.0.checked_add(duration).expect("overflow when adding a duration to a system time"))
+    }
+}
+
+impl core::fmt::Display for SystemTime {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "{}.{:06}", self.date_time(), self.0.subsec_micros())
+    }
+}
+
+// Sets the wall clock to the given time, as of now.
+pub fn set(now: SystemTime) {
+    let boot_time = now.0.checked_sub(uptime()).unwrap_or(Duration::from_secs(0));
+    suppress(|| *BOOT_TIME.write() = boot_time);
+}
+
+// Sets the wall clock from the real-time clock if the two are more than a second apart. The
+// real-time clock only counts whole seconds, so they're never much closer than that.
+pub fn synchronize() {
+    let clock = SystemTime::from(arch::time::rtc::read());
+    let now = SystemTime::now();
+
+    if let Some(drift) = now.duration_since(clock).or_else(|| clock.duration_since(now)) {
+        if drift > Duration::from_secs(1) {
+            info!("Wall clock off by {:?}; setting it to {}", drift, clock);
+            set(clock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeping_in_step_with_the_monotonic_clock() {
+        let start = SystemTime::now();
+
+        // The real-time clock was running before the machine started.
+        assert!(start.date_time().year >= 2021);
+
+        super::super::delay(2_000_000);
+        assert!(SystemTime::now().duration_since(start).unwrap() >= Duration::from_millis(2));
+    }
+
+    #[test]
+    fn setting_the_time() {
+        let saved = SystemTime::now();
+        let date_time = DateTime { year: 2030, month: 6, day: 15, hour: 8, minute: 30, second: 0 };
+
+        set(SystemTime::from(date_time));
+        assert_eq!(date_time, SystemTime::now().date_time());
+
+        set(saved);
+    }
+
+    #[test]
+    fn synchronizing_with_the_real_time_clock() {
+        set(SystemTime::from(DateTime { year: 2100, month: 1, day: 1, hour: 0, minute: 0, second: 0 }));
+        synchronize();
+
+        assert_ne!(2100, SystemTime::now().date_time().year);
+    }
+}
@@ -0,0 +1,307 @@
+// Callbacks scheduled for later, for timeouts, sleeping and anything else that has to wait. They're
+// kept in order of deadline and run from the timer interrupt, with interrupts off, so they have to
+// be quick.
+//
+// Normally the timer ticks HZ times a second and each tick runs whatever's due, so timers go off up
+// to a tick late. With `tickless`, the timer's armed for the earliest deadline instead, so timers
+// go off on time and the CPU isn't woken when there's nothing to do.
+
+use super::{Duration, Instant};
+use crate::arch::interrupts::{suppress, timer::{self as hardware, Mode}};
+use crate::cmdline::Parameter;
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use spin::Mutex;
+
+// Arms the timer for each deadline rather than ticking: `tickless`.
+pub static TICKLESS: Parameter<bool> = Parameter::new("tickless", false);
+
+// The longest a tickless CPU goes between interrupts, deadline or not. The clock has to be caught
+// up before its source wraps around, and this is well before any of them do.
+const MAX_IDLE: Duration = Duration::from_millis(50);
+
+lazy_static! {
+    static ref QUEUE: Mutex<Queue> = Mutex::new(Queue::new());
+}
+
+// A scheduled callback. Dropping the handle leaves it scheduled; cancelling it takes it back.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Timer {
+    id: u64
+}
+
+impl Timer {
+    // Calls back once, after the duration.
+    pub fn after<F>(duration: Duration, callback: F) -> Timer where F: FnOnce() + Send + 'static {
+        Timer::at(Instant::now() + duration, callback)
+    }
+
+    // Calls back once, at the deadline. One that's already passed goes off at the next interrupt.
+    pub fn at<F>(deadline: Instant, callback: F) -> Timer where F: FnOnce() + Send + 'static {
+        schedule(deadline, Callback::Once(Box::new(callback)))
+    }
+
+    // Calls back every period, starting a period from now. Deadlines follow on from the last one,
+    // not from when the callback ran, so they don't drift. Any that are missed are skipped.
+    pub fn every<F>(period: Duration, callback: F) -> Timer where F: FnMut() + Send + 'static {
+        assert!(period > Duration::from_secs(0), "timers can't go off continuously");
+        schedule(Instant::now() + period, Callback::Periodic(period, Box::new(callback)))
+    }
+
+    // Stops the timer going off again, and returns whether it was still going to. A one-shot timer
+    // that's already gone off, or is going off right now, can't be stopped.
+    pub fn cancel(self) -> bool {
+        suppress(|| QUEUE.lock().cancel(self.id))
+    }
+}
+
+enum Callback {
+    Once(Box<dyn FnOnce() + Send>),
+    Periodic(Duration, Box<dyn FnMut() + Send>)
+}
+
+// Goes tickless if the command line asks for it.
+pub(super) fn initialize() {
+    if TICKLESS.get() {
+        set_tickless(true);
+        info!("Going tickless");
+    }
+}
+
+// Runs every callback that's due. The timer interrupt calls this.
+pub fn run() {
+    let now = Instant::now();
+
+    // Take callbacks out one at a time, so they can schedule and cancel timers themselves.
+    while let Some((id, deadline, callback)) = suppress(|| QUEUE.lock().pop(now)) {
+        match callback {
+            Callback::Once(callback) => callback(),
+
+            Callback::Periodic(period, mut callback) => {
+                callback();
+
+                let next = following(deadline, period, now);
+                suppress(|| QUEUE.lock().reschedule(id, next, Callback::Periodic(period, callback)));
+            }
+        }
+    }
+
+    suppress(|| {
+        let mut queue = QUEUE.lock();
+
+        if queue.tickless {
+            queue.rearm();
+        }
+    })
+}
+
+fn schedule(deadline: Instant, callback: Callback) -> Timer {
+    suppress(|| {
+        let mut queue = QUEUE.lock();
+        let id = queue.insert(deadline, callback);
+
+        // The timer might be armed for later than this.
+        if queue.tickless && queue.armed.map_or(true, |armed| deadline < armed) {
+            queue.rearm();
+        }
+
+        Timer { id }
+    })
+}
+
+// Switches between arming the timer for each deadline and ticking HZ times a second.
+fn set_tickless(tickless: bool) {
+    suppress(|| {
+        let mut queue = QUEUE.lock();
+        queue.tickless = tickless;
+
+        if tickless {
+            queue.rearm();
+        } else {
+            queue.armed = None;
+
+            if let Err(error) = hardware::arm(Mode::Periodic, 1_000_000_000 / hardware::HZ) {
+                warn!("Failed to start ticking: {}", error);
+            }
+        }
+    })
+}
+
+// Returns a periodic timer's first deadline after now.
+fn following(deadline: Instant, period: Duration, now: Instant) -> Instant {
+    let missed = now.duration_since(deadline).as_nanos() / period.as_nanos();
+    deadline + Duration::from_nanos(((missed + 1) * period.as_nanos()) as u64)
+}
+
+struct Queue {
+    // Callbacks in the order they're due, with ties broken by which was scheduled first.
+    callbacks: BTreeMap<(Instant, u64), Callback>,
+
+    // When each timer's due. Periodic timers stay here while their callback runs, so that they can
+    // be cancelled then too.
+    deadlines: BTreeMap<u64, Instant>,
+    next:      u64,
+
+    tickless:  bool,
+    armed:     Option<Instant>
+}
+
+impl Queue {
+    fn new() -> Queue {
+        Queue { callbacks: BTreeMap::new(), deadlines: BTreeMap::new(), next: 0, tickless: false, armed: None }
+    }
+
+    fn insert(&mut self, deadline: Instant, callback: Callback) -> u64 {
+        let id = self.next;
+        self.next += 1;
+
+        self.deadlines.insert(id, deadline);
+        self.callbacks.insert((deadline, id), callback);
+        id
+    }
+
+    // Takes out the earliest callback, if it's due.
+    fn pop(&mut self, now: Instant) -> Option<(u64, Instant, Callback)> {
+        let &(deadline, id) = self.callbacks.keys().next()?;
+
+        if deadline > now {
+            return None
+        }
+
+        let callback = self.callbacks.remove(&(deadline, id))?;
+
+        if let Callback::Once(_) = callback {
+            self.deadlines.remove(&id);
+        }
+
+        Some((id, deadline, callback))
+    }
+
+    // Puts a periodic timer back after its callback's run, unless it was cancelled meanwhile.
+    fn reschedule(&mut self, id: u64, deadline: Instant, callback: Callback) {
+        if let Some(entry) = self.deadlines.get_mut(&id) {
+            *entry = deadline;
+            self.callbacks.insert((deadline, id), callback);
+        }
+    }
+
+    fn cancel(&mut self, id: u64) -> bool {
+        match self.deadlines.remove(&id) {
+            Some(deadline) => {
+                self.callbacks.remove(&(deadline, id));
+                true
+            }
+
+            None => false
+        }
+    }
+
+    // Arms the timer to go off at the earliest deadline, or after the longest idle if that's
+    // sooner.
+    fn rearm(&mut self) {
+        let now = Instant::now();
+        let latest = now + MAX_IDLE;
+        let deadline = self.callbacks.keys().next().map_or(latest, |&(deadline, _)| deadline.min(latest));
+
+        match hardware::arm(Mode::OneShot, deadline.duration_since(now).as_nanos() as u64) {
+            Ok(()) => self.armed = Some(deadline),
+            Err(error) => warn!("Failed to arm the timer: {}", error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::halt;
+    use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        while !condition() {
+            halt();
+        }
+    }
+
+    #[test]
+    fn ordering_deadlines() {
+        let mut queue = Queue::new();
+        let start = Instant::now();
+
+        queue.insert(start + Duration::from_millis(2), Callback::Once(Box::new(|| {})));
+        queue.insert(start + Duration::from_millis(1), Callback::Once(Box::new(|| {})));
+        queue.insert(start + Duration::from_millis(1), Callback::Once(Box::new(|| {})));
+        let cancelled = queue.insert(start, Callback::Once(Box::new(|| {})));
+
+        assert!(queue.cancel(cancelled));
+        assert!(!queue.cancel(cancelled));
+
+        assert!(queue.pop(start).is_none());
+
+        let later = start + Duration::from_millis(5);
+        let order: alloc::vec::Vec<u64> = core::iter::from_fn(|| queue.pop(later).map(|(id, _, _)| id)).collect();
+        assert_eq!(&[1, 2, 0], order.as_slice());
+    }
+
+    #[test]
+    fn skipping_missed_deadlines() {
+        let start = Instant::now();
+        let period = Duration::from_millis(10);
+
+        assert_eq!(start + Duration::from_millis(10), following(start, period, start));
+        assert_eq!(start + Duration::from_millis(40), following(start, period, start + Duration::from_millis(35)));
+    }
+
+    #[test]
+    fn going_off_once() {
+        static FIRED: AtomicBool = AtomicBool::new(false);
+
+        let start = Instant::now();
+        let timer = Timer::after(Duration::from_millis(5), || FIRED.store(true, Ordering::Relaxed));
+
+        wait_until(|| FIRED.load(Ordering::Relaxed));
+        assert!(start.elapsed() >= Duration::from_millis(5));
+        assert!(!timer.cancel());
+    }
+
+    #[test]
+    fn cancelling() {
+        static FIRED: AtomicBool = AtomicBool::new(false);
+
+        let timer = Timer::after(Duration::from_millis(1), || FIRED.store(true, Ordering::Relaxed));
+        assert!(timer.cancel());
+
+        super::super::sleep(Duration::from_millis(30));
+        assert!(!FIRED.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn going_off_periodically() {
+        static COUNT: AtomicU64 = AtomicU64::new(0);
+
+        let timer = Timer::every(Duration::from_millis(2), || { COUNT.fetch_add(1, Ordering::Relaxed); });
+        wait_until(|| COUNT.load(Ordering::Relaxed) >= 3);
+        assert!(timer.cancel());
+
+        let count = COUNT.load(Ordering::Relaxed);
+        super::super::sleep(Duration::from_millis(30));
+        assert_eq!(count, COUNT.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn going_tickless() {
+        static FIRED: AtomicBool = AtomicBool::new(false);
+
+        set_tickless(true);
+
+        Timer::after(Duration::from_millis(3), || FIRED.store(true, Ordering::Relaxed));
+        wait_until(|| FIRED.load(Ordering::Relaxed));
+
+        // With nothing to do, the timer still goes off now and then to keep the clock up to date.
+        let ticks = hardware::ticks();
+        wait_until(|| hardware::ticks() > ticks);
+
+        set_tickless(false);
+    }
+}
@@ -8,6 +8,10 @@ pub fn align_up(address: usize, alignment: usize) -> usize {
     }
 }
 
+pub fn align_down(address: usize, alignment: usize) -> usize {
+    address & !(alignment - 1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -21,4 +25,14 @@ mod tests {
     fn aligning_an_unaligned_address_up() {
         assert_eq!(0xFEE00008, align_up(0xFEE00004, 8))
     }
+
+    #[test]
+    fn aligning_an_already_aligned_address_down() {
+        assert_eq!(0xFEE00000, align_down(0xFEE00000, 8))
+    }
+
+    #[test]
+    fn aligning_an_unaligned_address_down() {
+        assert_eq!(0xFEE00000, align_down(0xFEE00004, 8))
+    }
 }
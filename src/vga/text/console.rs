@@ -2,10 +2,11 @@ use core::fmt::Write;
 use lazy_static::*;
 use spin::Mutex;
 use super::*;
+use crate::memory::PhysicalAddress;
 
 lazy_static! {
     static ref CONSOLE: Mutex<Console> = Mutex::new(
-        Console::new(unsafe { &mut *(0xB8000 as *mut Buffer) })
+        Console::new(unsafe { &mut *PhysicalAddress::new(0xB8000).to_virtual().as_mut_ptr() })
     );
 
     static ref WRITER: Mutex<Writer> = Mutex::new(
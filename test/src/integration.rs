@@ -2,6 +2,8 @@
 extern "Rust" {
     fn __print(args: core::fmt::Arguments);
     fn __exit(status: u32) -> !;
+    fn __selected(name: &str) -> bool;
+    fn __backtrace();
 }
 
 pub fn print(args: core::fmt::Arguments) {
@@ -11,3 +13,11 @@ pub fn print(args: core::fmt::Arguments) {
 pub fn exit(code: u32) -> ! {
     unsafe { __exit(code) }
 }
+
+pub fn selected(name: &str) -> bool {
+    unsafe { __selected(name) }
+}
+
+pub fn backtrace() {
+    unsafe { __backtrace() }
+}
@@ -4,13 +4,19 @@
 mod integration;
 mod types;
 
-use integration::{print, exit};
+use integration::{print, exit, selected, backtrace};
 pub use types::*;
 
 pub fn test_main_static(tests: &[&TestDescAndFn]) {
-    println!("Running {} tests:", tests.len());
+    let count = tests.iter().filter(|test| selected(test.desc.name.0)).count();
 
-    for test in tests {
+    if count == tests.len() {
+        println!("Running {} tests:", count);
+    } else {
+        println!("Running {} of {} tests:", count, tests.len());
+    }
+
+    for test in tests.iter().filter(|test| selected(test.desc.name.0)) {
         print!("{}... ", test.desc.name);
         (test.testfn)();
         println!("[ok]");
@@ -23,6 +29,7 @@ pub fn test_main_static(tests: &[&TestDescAndFn]) {
 fn panic(info: &core::panic::PanicInfo) -> ! {
     println!("[failed]\n");
     println!("Error: {}\n", info);
+    backtrace();
     exit(1);
 }
 